@@ -41,6 +41,8 @@ extern fn eh_personality() {}
 #[no_mangle]
 pub extern fn init(mbi_addr: u64) -> &'static mut GPR64Context {
     Logger::init(Serial::init(SerialPort::Com1));
+    // debugger link, driven by the VMM
+    Serial::init(SerialPort::Com2);
     log!("\n\n-= RustM00fl4x =-\n\n");
 
     smem::init(mbi_addr);
@@ -99,7 +99,8 @@ pub fn init(mbi_addr: u64) {
     let elf_addr = mbi_vmm_elf(&mbi);
 
     // get physical memory layout
-    let (area_end, ram_end, smap_cnt) = inspect(&mbi);
+    let (area_end, ram_end, mbi_cnt) = inspect(&mbi);
+    let smap_cnt = SystemMap::capacity(mbi_cnt);
     let pfn = pgutils::pfn(ram_end);
 
     // compute some sizes
@@ -214,6 +215,6 @@ pub fn init(mbi_addr: u64) {
     info.vmm.pfr = FrameRegistry::init(addr, &info.hwmm);
     addr += pfr_sz as u64;
 
-    info.vm.smap = SystemMap::init(addr, smap_cnt, secret.start, &mbi);
+    info.vm.smap = SystemMap::init(addr, smap_cnt, &secret, &mbi);
     addr += smap_sz as u64;
 }
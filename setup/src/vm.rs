@@ -3,9 +3,12 @@ use vmx::vmcs::setup::Setup;
 use share::vmx::insn as vmx;
 
 use share::rmode;
+use share::vmx::ept::{self, EPTRegion, EPTRegionKind};
 use share::mmap::PageMapper;
 use share::utils::RawValue;
 use share::info::info_data;
+use share::vm::{ResetPolicy, MwaitPolicy};
+use share::cpuid::{CpuidRule, CpuidReg, CPUID_FEATURES, CPUID_ECX_MONITOR};
 
 pub fn init() {
     let mut info = info_data();
@@ -23,4 +26,19 @@ pub fn init() {
     info.vm.vmcs.commit();
 
     rmode::vm_set_entry(info.vm.vmcs.guest.rip.field().as_u64());
+
+    // legacy VGA window goes through the VMM MMIO devices
+    if !ept::track(EPTRegion::new(rmode::VGA_START, rmode::VGA_END, EPTRegionKind::MMIO)) {
+        panic!("can not track VGA window");
+    }
+    info.vm.reset = ResetPolicy::Reboot;
+    info.vm.native_hlt = false;
+    info.vm.mwait = MwaitPolicy::Ud;
+
+    if info.vm.mwait == MwaitPolicy::Ud {
+        info.vm.cpu.cpuid.add(CpuidRule {
+            leaf: CPUID_FEATURES, subleaf: None, reg: CpuidReg::Ecx,
+            keep: !CPUID_ECX_MONITOR, value: 0,
+        });
+    }
 }
@@ -5,7 +5,9 @@ use x86_64::registers::control_regs::cr4 as cr4_read;
 
 use share::cr;
 use share::cr::cr2_write;
+use share::dr;
 use share::msr;
+use share::port;
 
 use share::vmx::ACTIVITY_STATE;
 use share::vmx::ept;
@@ -108,8 +110,9 @@ impl Setup for EntryCtl {
         entry.set_load_ia32_pat(true);
         entry.set_load_ia32_efer(true);
 
+        entry.set_load_dbgctl(true);
+
         // XXX: TODO
-        //entry.set_load_dbgctl(true);
         //entry.set_load_ia32_bnd(true);
 
         self.msr_load_addr.set_field_value(info.vm.vmc.entry_load.get_addr());
@@ -153,9 +156,19 @@ impl Setup for ExecCtl {
     fn init(&mut self) {
         let info = info_data();
 
+        // external interrupts and NMIs are forwarded
+        // through the pending event queue
+        let pin = self.pin.field_mut();
+        pin.set_eint(true);
+        pin.set_nmi(true);
+        pin.set_vnmi(true);
+
         let proc1 = self.proc1.field_mut();
         proc1.set_tsc(true);
-        //proc1.set_cr3l(true);
+        proc1.set_hlt(true);
+        proc1.set_mwait(true);
+        proc1.set_mon(true);
+        proc1.set_cr3l(true);
         proc1.set_usio(true);
         proc1.set_umsr(true);
         proc1.set_proc2(true);
@@ -176,26 +189,49 @@ impl Setup for ExecCtl {
 
         self.excp_bitmap.set_field_value((1<<excp::GP|1<<excp::MC) as u64);
 
+        // every address space switch exits, see vm::space
+        self.cr3_target_cnt.set_field_value(0);
+
         self.pf_err_msk.set_field_value(0);
         self.pf_err_mch.set_field_value(0);
 
+        // VMM owns fixed bits and mode related ones
+        let fixed = info.vmm.cpu.vmx.fixed;
+
         let cr0_mask = self.cr0_mask.field_mut();
-        // cr0_mask.set_pe(true);
-        // cr0_mask.set_cd(true);
-        // cr0_mask.set_pg(true);
+        cr0_mask.update_u64(fixed.cr0.allow_0.as_u64() | !fixed.cr0.allow_1.as_u64());
+        cr0_mask.set_pe(true);
+        cr0_mask.set_cd(true);
+        cr0_mask.set_pg(true);
 
         let cr4_mask = self.cr4_mask.field_mut();
-        // cr4_mask.set_mce(true);
-        // cr4_mask.set_pae(true);
-        // cr4_mask.set_pse(true);
-        // cr4_mask.set_pge(true);
-        // cr4_mask.set_vmxe(true);
-
-        self.ioA_bitmap.set_field_value(info.vm.vmc.ioA_map.get_addr());
-        self.ioB_bitmap.set_field_value(info.vm.vmc.ioB_map.get_addr());
-
-        info.vm.vmc.msr_map.deny(msr::IA32_MTRR_DEF_TYPE);
-        info.vm.vmc.msr_map.deny(msr::IA32_EFER);
+        cr4_mask.update_u64(fixed.cr4.allow_0.as_u64() | !fixed.cr4.allow_1.as_u64());
+        cr4_mask.set_mce(true);
+        cr4_mask.set_pae(true);
+        cr4_mask.set_pse(true);
+        cr4_mask.set_pge(true);
+        cr4_mask.set_vmxe(true);
+
+        self.ioA_bitmap.set_field_value(info.vm.vmc.io_map.a.get_addr());
+        self.ioB_bitmap.set_field_value(info.vm.vmc.io_map.b.get_addr());
+
+        // A20 gate
+        info.vm.vmc.io_map.deny(port::KBC_DATA);
+        info.vm.vmc.io_map.deny(port::KBC_CMD);
+        info.vm.vmc.io_map.deny(port::SYS_CTRL_A);
+
+        info.vm.vmc.msr_map.deny(msr::IA32_MTRR_DEF_TYPE, MsrAccess::ReadWrite);
+        info.vm.vmc.msr_map.deny(msr::IA32_EFER, MsrAccess::ReadWrite);
+        info.vm.vmc.msr_map.deny(msr::IA32_PAT, MsrAccess::ReadWrite);
+        info.vm.vmc.msr_map.deny(msr::IA32_APIC_BASE, MsrAccess::ReadWrite);
+        info.vm.vmc.msr_map.deny(msr::IA32_FEATURE_CONTROL, MsrAccess::ReadWrite);
+
+        let mtrr_cnt = info.vm.cpu.msr.mtrr_count() as u32;
+        if mtrr_cnt != 0 {
+            info.vm.vmc.msr_map.deny_range(msr::IA32_MTRR_PHYSBASE0,
+                                           msr::IA32_MTRR_PHYSBASE0 + 2*mtrr_cnt - 1,
+                                           MsrAccess::ReadWrite);
+        }
 
         self.msr_bitmap.set_field_value(info.vm.vmc.msr_map.get_addr());
     }
@@ -337,8 +373,9 @@ impl Setup for  ExitCtl {
         exit.set_save_ia32_pat(true);
         exit.set_save_ia32_efer(true);
 
+        exit.set_save_dbgctl(true);
+
         // XXX: TODO
-        //exit.set_save_dbgctl(true);
         //exit.set_clear_bnd(true);
 
         self.msr_store_addr.set_field_value(info.vm.vmc.exit_store.get_addr());
@@ -531,6 +568,7 @@ impl Setup for Guest {
         rflags.update_u64(0);
         rflags.set_it(true);
 
+        self.dr7.set_field_value(dr::DR7_DFT);
         self.rsp.set_field_value(rmode::BASE_SP);
         self.rip.set_field_value(rmode::BASE_IP);
     }
@@ -657,7 +695,7 @@ impl Setup for Guest {
             self.ia32_perf.force_flush();
         }
 
-        // self.ia32_dbgctl.force_flush();
+        self.ia32_dbgctl.force_flush();
         // self.ia32_bndcfg.force_flush();
         self.pdpe_0.force_flush();
         self.pdpe_1.force_flush();
@@ -0,0 +1,200 @@
+// VMM owned guest breakpoints and watchpoints
+//
+// Software breakpoints replace the byte at a guest linear address
+// with INT3, the original one is saved to be restored when the
+// breakpoint is removed or stepped over.
+//
+// Watchpoints are guest physical ranges whose covering pages lose
+// EPT permissions.
+
+pub const BRK_MAX: usize = 16;
+pub const BRK_INT3: u8 = 0xcc;
+
+#[derive(Debug, Copy, Clone)]
+pub struct Breakpoint {
+    pub addr:  u64,
+    pub saved: u8,
+}
+
+pub struct Breakpoints {
+    count:     usize,
+    list:      [Breakpoint;BRK_MAX],
+    pub rearm: Option<u64>, // breakpoint stepped over
+}
+
+impl Breakpoints {
+    pub fn list(&self) -> &[Breakpoint] { &self.list[..self.count] }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn add(&mut self, brk: Breakpoint) -> bool {
+        if self.count >= BRK_MAX {
+            return false
+        }
+
+        self.list[self.count] = brk;
+        self.count += 1;
+        true
+    }
+
+    pub fn del(&mut self, addr: u64) -> Option<Breakpoint> {
+        for i in 0..self.count {
+            if self.list[i].addr == addr {
+                let brk = self.list[i];
+                self.count -= 1;
+                self.list[i] = self.list[self.count];
+                return Some(brk)
+            }
+        }
+
+        None
+    }
+
+    pub fn find(&self, addr: u64) -> Option<Breakpoint> {
+        self.list().iter().find(|b| b.addr == addr).map(|b| *b)
+    }
+}
+
+pub const WATCH_MAX: usize = 16;
+
+#[derive(Debug, Copy, Clone)]
+pub struct Watchpoint {
+    pub addr:  u64,  // as given, linear or physical
+    pub start: u64,
+    pub end:   u64,
+    pub write: bool, // only report writes
+}
+
+impl Watchpoint {
+    pub fn has(&self, gpa: u64) -> bool {
+        gpa >= self.start && gpa < self.end
+    }
+}
+
+pub struct Watchpoints {
+    count: usize,
+    list:  [Watchpoint;WATCH_MAX],
+}
+
+impl Watchpoints {
+    pub fn list(&self) -> &[Watchpoint] { &self.list[..self.count] }
+
+    pub fn add(&mut self, wp: Watchpoint) -> bool {
+        if self.count >= WATCH_MAX {
+            return false
+        }
+
+        self.list[self.count] = wp;
+        self.count += 1;
+        true
+    }
+
+    pub fn find(&self, addr: u64, write: bool) -> Option<Watchpoint> {
+        self.list().iter().find(|w| w.addr == addr && w.write == write).map(|w| *w)
+    }
+
+    // A linear watchpoint may span several physical pieces
+    pub fn del(&mut self, addr: u64, write: bool) -> Option<Watchpoint> {
+        for i in 0..self.count {
+            if self.list[i].addr == addr && self.list[i].write == write {
+                let wp = self.list[i];
+                self.count -= 1;
+                self.list[i] = self.list[self.count];
+                return Some(wp)
+            }
+        }
+
+        None
+    }
+
+    // Watchpoint hit by an access to gpa
+    pub fn hit(&self, gpa: u64, write: bool) -> Option<Watchpoint> {
+        self.list().iter().find(|w| w.has(gpa) && (write || !w.write)).map(|w| *w)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem;
+
+    #[test]
+    fn breakpoints() {
+        let mut brk: Breakpoints = unsafe { mem::zeroed() };
+
+        assert!(brk.is_empty() && brk.rearm.is_none());
+        assert!(brk.add(Breakpoint { addr: 0x1000, saved: 0x55 }));
+        assert!(brk.add(Breakpoint { addr: 0x2000, saved: 0x90 }));
+        assert_eq!(brk.find(0x2000).map(|b| b.saved), Some(0x90));
+        assert!(brk.find(0x3000).is_none());
+
+        assert_eq!(brk.del(0x1000).map(|b| b.saved), Some(0x55));
+        assert!(brk.del(0x1000).is_none());
+        assert_eq!(brk.list().len(), 1);
+        assert_eq!(brk.list()[0].addr, 0x2000);
+    }
+
+    #[test]
+    fn breakpoints_full() {
+        let mut brk: Breakpoints = unsafe { mem::zeroed() };
+
+        for n in 0..BRK_MAX {
+            assert!(brk.add(Breakpoint { addr: n as u64, saved: 0 }));
+        }
+
+        assert!(!brk.add(Breakpoint { addr: 0x1000, saved: 0 }));
+        assert!(brk.del(0).is_some());
+        assert!(brk.add(Breakpoint { addr: 0x1000, saved: 0 }));
+    }
+
+    fn wp(addr: u64, start: u64, end: u64, write: bool) -> Watchpoint {
+        Watchpoint { addr: addr, start: start, end: end, write: write }
+    }
+
+    #[test]
+    fn watchpoints_hit() {
+        let mut wps: Watchpoints = unsafe { mem::zeroed() };
+
+        assert!(wps.add(wp(0x7000, 0x3000, 0x3004, true)));
+        assert!(wps.add(wp(0x8000, 0x5ff0, 0x6000, false)));
+
+        assert_eq!(wps.hit(0x3000, true).map(|w| w.addr), Some(0x7000));
+        assert!(wps.hit(0x3000, false).is_none());
+        assert!(wps.hit(0x3004, true).is_none());
+        assert_eq!(wps.hit(0x5fff, false).map(|w| w.addr), Some(0x8000));
+        assert_eq!(wps.hit(0x5ff0, true).map(|w| w.addr), Some(0x8000));
+        assert!(wps.hit(0x6000, true).is_none());
+    }
+
+    #[test]
+    fn watchpoints_del() {
+        let mut wps: Watchpoints = unsafe { mem::zeroed() };
+
+        // write watchpoint split over two pages, access one at the same address
+        assert!(wps.add(wp(0x7ffe, 0x3ffe, 0x4000, true)));
+        assert!(wps.add(wp(0x7ffe, 0x9000, 0x9002, true)));
+        assert!(wps.add(wp(0x7ffe, 0x3ffe, 0x4000, false)));
+
+        assert!(wps.find(0x7ffe, false).is_some());
+        assert!(wps.del(0x7ffe, true).is_some());
+        assert!(wps.del(0x7ffe, true).is_some());
+        assert!(wps.del(0x7ffe, true).is_none());
+
+        assert_eq!(wps.list().len(), 1);
+        assert!(!wps.list()[0].write);
+        assert!(wps.find(0x7ffe, true).is_none());
+    }
+
+    #[test]
+    fn watchpoints_full() {
+        let mut wps: Watchpoints = unsafe { mem::zeroed() };
+
+        for n in 0..WATCH_MAX {
+            assert!(wps.add(wp(n as u64, 0, 1, true)));
+        }
+
+        assert!(!wps.add(wp(0x1000, 0, 1, true)));
+    }
+}
@@ -6,11 +6,19 @@ use x86_64::registers::control_regs::cr4 as cr4_read;
 use x86_64::registers::control_regs::cr4_write;
 
 use vmx::ept;
+use vmx::event::EventQueue;
+use vmx::step::StepState;
+use vmx::insn::invvpid;
 use vmx::regs::VMXInfo;
 use mtrr::MTRRInfo;
 use gpr::GPR64Context;
+use cpuid::CpuidPolicy;
 use msr;
 use cr;
+use dr;
+
+// Architectural instruction length limit
+pub const INSN_MAX_LEN: usize = 15;
 
 // This struct uses external types from extern crate we can't derive
 // traits for them. We can use newtype pattern: see documentation "The
@@ -34,6 +42,13 @@ pub struct HardwareCPU {
 
 pub struct VirtualCPU {
     pub gpr: &'static mut GPR64Context,
+    pub cpuid: CpuidPolicy,
+    pub msr: msr::VirtualMSR,
+    pub a20: bool,
+    pub insn_cache: [u8; INSN_MAX_LEN],
+    pub events: EventQueue,
+    pub step: StepState,
+    pub dr: dr::VirtualDR,
     paddr_sz: u8,
     vaddr_sz: u8,
     max_paddr: u64,
@@ -65,6 +80,12 @@ impl CPUSkillz for VirtualCPU {
 }
 
 impl VirtualCPU {
+    // Invalidate VM linear mappings, global ones included if asked
+    pub fn tlb_flush(&self, vpid: u16, global: bool) {
+        let kind = if global { self.tlb_g } else { self.tlb };
+        invvpid(kind as u64, vpid, 0);
+    }
+
     pub fn setup(&mut self, hcpu: &HardwareCPU, gpr: u64) {
         self.gpr = unsafe { &mut *(gpr as *mut GPR64Context) };
 
@@ -77,6 +98,12 @@ impl VirtualCPU {
         self.pg_2m = hcpu.vmx.ept.pg_2m();
         self.pg_1g = hcpu.vmx.ept.pg_1g();
 
+        self.cpuid.setup(self.paddr_sz);
+        self.msr.setup(self.max_paddr, hcpu.mtrr.cap.cnt());
+
+        // enabled by the multiboot loader
+        self.a20 = true;
+
         if hcpu.vmx.ept.invvpid_s() && hcpu.vmx.ept.invvpid_r() {
             self.tlb   = ept::VPID_INV_TYPE::Single;
             self.tlb_g = ept::VPID_INV_TYPE::SingleAll;
@@ -0,0 +1,248 @@
+// CPUID virtualization
+//
+// The VMM runs the native instruction and filters its result through
+// a per-leaf/per-register rule table. Each rule keeps some native bits
+// and forces others:
+//
+//   final = (native & keep) | value
+//
+// A full override is a rule with keep = 0.
+
+pub const CPUID_MAX_RULES: usize = 16;
+
+// Standard leaves
+pub const CPUID_FEATURES:         u32 = 0x00000001;
+pub const CPUID_EXT_ADDR_SIZE:    u32 = 0x80000008;
+
+// Hypervisor leaves
+pub const CPUID_HYP_VENDOR:       u32 = 0x40000000;
+pub const CPUID_HYP_MAX:          u32 = CPUID_HYP_VENDOR;
+
+// "RustMooflax\0" as returned in EBX:ECX:EDX
+pub const CPUID_HYP_SIG_EBX:      u32 = 0x74737552; // "Rust"
+pub const CPUID_HYP_SIG_ECX:      u32 = 0x666f6f4d; // "Moof"
+pub const CPUID_HYP_SIG_EDX:      u32 = 0x0078616c; // "lax\0"
+
+// Leaf 1 ECX bits
+pub const CPUID_ECX_MONITOR:      u32 = 1<<3;
+pub const CPUID_ECX_VMX:          u32 = 1<<5;
+pub const CPUID_ECX_HYP:          u32 = 1<<31;
+
+// Leaf 0x80000008 EAX physical address width
+pub const CPUID_EAX_PADDR_MSK:    u32 = 0xff;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CpuidReg {
+    Eax,
+    Ebx,
+    Ecx,
+    Edx,
+}
+
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct CpuidResult {
+    pub eax: u32,
+    pub ebx: u32,
+    pub ecx: u32,
+    pub edx: u32,
+}
+
+impl CpuidResult {
+    pub fn reg_mut(&mut self, reg: CpuidReg) -> &mut u32 {
+        match reg {
+            CpuidReg::Eax => &mut self.eax,
+            CpuidReg::Ebx => &mut self.ebx,
+            CpuidReg::Ecx => &mut self.ecx,
+            CpuidReg::Edx => &mut self.edx,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct CpuidRule {
+    pub leaf:    u32,
+    pub subleaf: Option<u32>, // None matches any ECX input
+    pub reg:     CpuidReg,
+    pub keep:    u32,
+    pub value:   u32,
+}
+
+impl CpuidRule {
+    pub fn matches(&self, leaf: u32, subleaf: u32) -> bool {
+        if self.leaf != leaf {
+            return false
+        }
+
+        match self.subleaf {
+            None => true,
+            Some(sub) => sub == subleaf,
+        }
+    }
+
+    pub fn apply(&self, res: &mut CpuidResult) {
+        let reg = res.reg_mut(self.reg);
+        *reg = (*reg & self.keep) | self.value;
+    }
+}
+
+// Default rules, not depending on the virtual CPU
+const CPUID_DFT_RULES: [CpuidRule;5] = [
+    // hide VMX and expose hypervisor presence
+    CpuidRule { leaf: CPUID_FEATURES, subleaf: None, reg: CpuidReg::Ecx,
+                keep: !(CPUID_ECX_VMX|CPUID_ECX_HYP), value: CPUID_ECX_HYP },
+
+    // hypervisor vendor leaf
+    CpuidRule { leaf: CPUID_HYP_VENDOR, subleaf: None, reg: CpuidReg::Eax,
+                keep: 0, value: CPUID_HYP_MAX },
+    CpuidRule { leaf: CPUID_HYP_VENDOR, subleaf: None, reg: CpuidReg::Ebx,
+                keep: 0, value: CPUID_HYP_SIG_EBX },
+    CpuidRule { leaf: CPUID_HYP_VENDOR, subleaf: None, reg: CpuidReg::Ecx,
+                keep: 0, value: CPUID_HYP_SIG_ECX },
+    CpuidRule { leaf: CPUID_HYP_VENDOR, subleaf: None, reg: CpuidReg::Edx,
+                keep: 0, value: CPUID_HYP_SIG_EDX },
+];
+
+pub struct CpuidPolicy {
+    count: usize,
+    rules: [CpuidRule;CPUID_MAX_RULES],
+}
+
+impl CpuidPolicy {
+    pub fn rules(&self) -> &[CpuidRule] { &self.rules[..self.count] }
+
+    pub fn clear(&mut self) {
+        self.count = 0;
+    }
+
+    pub fn add(&mut self, rule: CpuidRule) -> bool {
+        if self.count >= CPUID_MAX_RULES {
+            return false
+        }
+
+        self.rules[self.count] = rule;
+        self.count += 1;
+        true
+    }
+
+    // Load default rules and clamp physical address width
+    pub fn setup(&mut self, paddr_sz: u8) {
+        self.clear();
+
+        for rule in CPUID_DFT_RULES.iter() {
+            self.add(*rule);
+        }
+
+        self.add(CpuidRule {
+            leaf: CPUID_EXT_ADDR_SIZE, subleaf: None, reg: CpuidReg::Eax,
+            keep: !CPUID_EAX_PADDR_MSK, value: paddr_sz as u32,
+        });
+    }
+
+    // Rules are applied in insertion order
+    pub fn filter(&self, leaf: u32, subleaf: u32, res: &mut CpuidResult) {
+        for rule in self.rules() {
+            if rule.matches(leaf, subleaf) {
+                rule.apply(res);
+            }
+        }
+    }
+}
+
+pub fn cpuid(leaf: u32, subleaf: u32) -> CpuidResult {
+    let (a, b, c, d): (u32, u32, u32, u32);
+
+    unsafe {
+        asm!("cpuid"
+             : "={eax}" (a), "={ebx}" (b), "={ecx}" (c), "={edx}" (d)
+             : "{eax}" (leaf), "{ecx}" (subleaf)
+             :: "volatile");
+    }
+
+    CpuidResult { eax: a, ebx: b, ecx: c, edx: d }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem;
+
+    // same as the VMM area
+    fn policy(paddr_sz: u8) -> CpuidPolicy {
+        let mut policy: CpuidPolicy = unsafe { mem::zeroed() };
+        policy.setup(paddr_sz);
+        policy
+    }
+
+    fn filter(policy: &CpuidPolicy, leaf: u32, subleaf: u32, native: CpuidResult) -> CpuidResult {
+        let mut res = native;
+        policy.filter(leaf, subleaf, &mut res);
+        res
+    }
+
+    #[test]
+    fn hide_vmx_show_hypervisor() {
+        let native = CpuidResult { eax: 0x306a9, ebx: 1, ecx: CPUID_ECX_VMX|CPUID_ECX_MONITOR, edx: 2 };
+        let res = filter(&policy(36), CPUID_FEATURES, 0, native);
+
+        assert_eq!(res.ecx, CPUID_ECX_HYP|CPUID_ECX_MONITOR);
+        assert_eq!((res.eax, res.ebx, res.edx), (native.eax, native.ebx, native.edx));
+    }
+
+    #[test]
+    fn hypervisor_leaf_signature() {
+        let native = CpuidResult { eax: 0xdead, ebx: 0xbeef, ecx: 0xcafe, edx: 0xf00d };
+        let res = filter(&policy(36), CPUID_HYP_VENDOR, 0, native);
+
+        assert_eq!(res.eax, CPUID_HYP_MAX);
+
+        let mut sig = [0u8; 12];
+        for (i, r) in [res.ebx, res.ecx, res.edx].iter().enumerate() {
+            for b in 0..4 {
+                sig[i*4 + b] = (r >> (b*8)) as u8;
+            }
+        }
+        assert_eq!(&sig, b"RustMooflax\0");
+    }
+
+    #[test]
+    fn paddr_sz_clamped() {
+        // linear width (bits 15:8) is kept
+        let native = CpuidResult { eax: 0x3027, ebx: 0, ecx: 0, edx: 0 };
+
+        assert_eq!(filter(&policy(36), CPUID_EXT_ADDR_SIZE, 0, native).eax, 0x3024);
+        assert_eq!(filter(&policy(32), CPUID_EXT_ADDR_SIZE, 7, native).eax, 0x3020);
+    }
+
+    #[test]
+    fn other_leaves_untouched() {
+        let native = CpuidResult { eax: 1, ebx: 2, ecx: 3, edx: 4 };
+        assert_eq!(filter(&policy(36), 0, 0, native), native);
+        assert_eq!(filter(&policy(36), 7, 0, native), native);
+    }
+
+    #[test]
+    fn subleaf_and_order() {
+        let mut policy = policy(36);
+
+        policy.add(CpuidRule { leaf: 7, subleaf: Some(0), reg: CpuidReg::Ebx, keep: !0xf, value: 0 });
+        policy.add(CpuidRule { leaf: 7, subleaf: Some(0), reg: CpuidReg::Ebx, keep: !0, value: 1 });
+
+        let native = CpuidResult { eax: 0, ebx: 0xff, ecx: 0, edx: 0 };
+        assert_eq!(filter(&policy, 7, 0, native).ebx, 0xf1);
+        assert_eq!(filter(&policy, 7, 1, native).ebx, 0xff);
+    }
+
+    #[test]
+    fn policy_full() {
+        let mut policy = policy(36);
+        let rule = CpuidRule { leaf: 0, subleaf: None, reg: CpuidReg::Eax, keep: !0, value: 0 };
+
+        while policy.rules().len() < CPUID_MAX_RULES {
+            assert!(policy.add(rule));
+        }
+        assert!(!policy.add(rule));
+
+        policy.clear();
+        assert!(policy.rules().is_empty());
+    }
+}
@@ -29,6 +29,7 @@ bitfield!{
     pub pwt,_:3;
     pub pcd,_:4;
     pub addr,_:51,12;
+    pub noflush,_:63; // PCIDE: keep TLB entries, never stored
 }
 
 bitfield!{
@@ -63,6 +64,16 @@ pub fn cr2_write(val: u64) {
     unsafe { asm!("mov $0, %cr2" :: "r" (val) : "memory") };
 }
 
+pub fn cr8_read() -> u64 {
+    let val: u64;
+    unsafe { asm!("mov %cr8, $0" : "=r" (val)) };
+    val
+}
+
+pub fn cr8_write(val: u64) {
+    unsafe { asm!("mov $0, %cr8" :: "r" (val) : "memory") };
+}
+
 // XXX: macro to generate that impl
 impl utils::RawValue for Cr0 {
     fn from_u32(x: u32) -> Cr0 { Cr0(x as u64) }
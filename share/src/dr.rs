@@ -9,3 +9,106 @@ pub fn dr6_read() -> u64 {
 pub fn dr6_write(val: u64) {
     unsafe { asm!("mov $0, %dr6" :: "r" (val) : "memory") };
 }
+
+// DR0-DR3 are not part of the VMCS: the guest runs with hardware ones
+pub fn dr_read(n: usize) -> u64 {
+    let ret: u64;
+    unsafe {
+        match n {
+            0 => asm!("mov %dr0, $0" : "=r" (ret)),
+            1 => asm!("mov %dr1, $0" : "=r" (ret)),
+            2 => asm!("mov %dr2, $0" : "=r" (ret)),
+            3 => asm!("mov %dr3, $0" : "=r" (ret)),
+            _ => panic!("no DR{}", n),
+        }
+    }
+    ret
+}
+
+pub fn dr_write(n: usize, val: u64) {
+    unsafe {
+        match n {
+            0 => asm!("mov $0, %dr0" :: "r" (val) : "memory"),
+            1 => asm!("mov $0, %dr1" :: "r" (val) : "memory"),
+            2 => asm!("mov $0, %dr2" :: "r" (val) : "memory"),
+            3 => asm!("mov $0, %dr3" :: "r" (val) : "memory"),
+            _ => panic!("no DR{}", n),
+        }
+    }
+}
+
+pub const DR_COUNT: usize = 4;
+
+pub const DR6_DFT: u64 = 0xffff0ff0;
+pub const DR7_DFT: u64 = 0x400;
+
+// B0-B3, BD and BS
+pub const DR6_CAUSE_MSK: u64 = 0xf | 1<<13 | 1<<14;
+
+// DR6 layout, also used by #DB exit qualification
+bitfield!{
+    #[derive(Default, Copy, Clone)]
+    pub struct Dr6(u64);
+
+    impl Debug;
+
+    pub u8, b,_:3,0;  // DR0-DR3 condition hits
+    pub bd,_:13;
+    pub bs,_:14;
+    pub bt,_:15;
+    pub rtm,_:16;
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DrKind {
+    Exec   = 0,
+    Write  = 1,
+    Io     = 2,
+    Access = 3, // read or write
+}
+
+// Length encoding from size in bytes
+pub fn dr_len(size: usize) -> Option<u64> {
+    match size {
+        1 => Some(0),
+        2 => Some(1),
+        8 => Some(2),
+        4 => Some(3),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Dr7(pub u64);
+
+impl Dr7 {
+    // Enable, condition and length bits of DRn
+    pub fn mask(n: usize) -> u64 {
+        0xf<<(16 + 4*n) | 3<<(2*n)
+    }
+
+    pub fn enabled(&self, n: usize) -> bool {
+        self.0 & (3<<(2*n)) != 0
+    }
+
+    // Global enable, condition and length of DRn
+    pub fn enable(&mut self, n: usize, kind: DrKind, len: u64) {
+        self.0 &= !Dr7::mask(n);
+        self.0 |= ((kind as u64) | len<<2)<<(16 + 4*n) | 2<<(2*n);
+    }
+
+    pub fn disable(&mut self, n: usize) {
+        self.0 &= !Dr7::mask(n);
+    }
+}
+
+// Debug registers ownership
+//
+// While the VMM debugger owns DR0-DR3/DR7, the guest ones live in
+// the shadow copies below and mov-DR accesses are virtualised.
+pub struct VirtualDR {
+    pub owned: u8,               // VMM owned DRn mask
+    pub guest: [u64;DR_COUNT],
+    pub dr6:   u64,
+    pub dr7:   u64,
+}
@@ -49,7 +49,28 @@ pub enum Exception {
 
 use self::Exception::*;
 
+// Double fault conditions (SDM Vol. 3 table 6-5)
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ExceptionClass {
+    Benign,
+    Contributory,
+    PageFault,
+}
+
 impl Exception {
+    pub fn class(&self) -> ExceptionClass {
+        match *self {
+            DivideZero        |
+            InvalidTSS        |
+            SegmentNotPresent |
+            StackFault        |
+            GeneralProtection => ExceptionClass::Contributory,
+            PageFault         |
+            Virtualization    => ExceptionClass::PageFault,
+            _ => ExceptionClass::Benign,
+        }
+    }
+
     pub fn has_code(&self) -> bool {
         match *self {
             DoubleFault       |
@@ -0,0 +1,391 @@
+// GDB remote serial protocol packet codec
+//
+// Packets are framed as $<data>#<checksum>, the checksum being the
+// modulo 256 sum of the framed data bytes as two hex digits. '}'
+// escapes the next byte, xored with 0x20. The receiver acknowledges
+// a packet with '+' or asks for it again with '-'. A lone 0x03 asks
+// the target to stop.
+//
+// Nothing here touches the hardware: the VMM stub feeds received
+// bytes to a Decoder and sends out what encode() produces.
+
+pub const PACKET_MAX: usize = 512;
+
+pub const ACK:       u8 = b'+';
+pub const NACK:      u8 = b'-';
+pub const INTERRUPT: u8 = 0x03;
+
+const START:  u8 = b'$';
+const END:    u8 = b'#';
+const ESCAPE: u8 = b'}';
+const RLE:    u8 = b'*';
+
+const HEX: &'static [u8;16] = b"0123456789abcdef";
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Event {
+    None,      // need more bytes
+    Packet,    // valid packet, to be acknowledged
+    Bad,       // checksum mismatch or overflow, to be sent again
+    Ack,
+    Nack,
+    Interrupt,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum State {
+    Idle,   // between packets, zeroed decoder state
+    Data,
+    Escape,
+    Sum1,
+    Sum2,
+}
+
+pub struct Decoder {
+    state:    State,
+    overflow: bool,
+    sum:      u8, // computed
+    csum:     u8, // received
+    len:      usize,
+    data:     [u8;PACKET_MAX],
+}
+
+impl Decoder {
+    pub fn new() -> Decoder {
+        Decoder {
+            state: State::Idle, overflow: false,
+            sum: 0, csum: 0, len: 0, data: [0;PACKET_MAX],
+        }
+    }
+
+    // Unescaped data of the last packet
+    pub fn packet(&self) -> &[u8] { &self.data[..self.len] }
+
+    fn start(&mut self) {
+        self.state    = State::Data;
+        self.overflow = false;
+        self.sum      = 0;
+        self.len      = 0;
+    }
+
+    fn push(&mut self, byte: u8) {
+        if self.len >= PACKET_MAX {
+            self.overflow = true;
+            return
+        }
+
+        self.data[self.len] = byte;
+        self.len += 1;
+    }
+
+    pub fn feed(&mut self, byte: u8) -> Event {
+        match self.state {
+            State::Idle => match byte {
+                START     => { self.start(); Event::None },
+                ACK       => Event::Ack,
+                NACK      => Event::Nack,
+                INTERRUPT => Event::Interrupt,
+                _         => Event::None, // line noise
+            },
+            State::Data => {
+                match byte {
+                    END    => self.state = State::Sum1,
+                    START  => self.start(), // sender started over
+                    ESCAPE => {
+                        self.sum   = self.sum.wrapping_add(byte);
+                        self.state = State::Escape;
+                    },
+                    _ => {
+                        self.sum = self.sum.wrapping_add(byte);
+                        self.push(byte);
+                    },
+                }
+                Event::None
+            },
+            State::Escape => {
+                self.sum   = self.sum.wrapping_add(byte);
+                self.state = State::Data;
+                self.push(byte ^ 0x20);
+                Event::None
+            },
+            State::Sum1 => match digit(byte) {
+                Some(d) => {
+                    self.csum  = d<<4;
+                    self.state = State::Sum2;
+                    Event::None
+                },
+                None => {
+                    self.state = State::Idle;
+                    Event::Bad
+                },
+            },
+            State::Sum2 => {
+                self.state = State::Idle;
+                match digit(byte) {
+                    Some(d) if self.csum | d == self.sum && !self.overflow => Event::Packet,
+                    _ => Event::Bad,
+                }
+            },
+        }
+    }
+}
+
+// Frame data as a packet, bytes go out through put
+pub fn encode<F: FnMut(u8)>(data: &[u8], mut put: F) {
+    let mut sum = 0u8;
+
+    put(START);
+
+    for &byte in data {
+        if byte == START || byte == END || byte == ESCAPE || byte == RLE {
+            put(ESCAPE);
+            put(byte ^ 0x20);
+            sum = sum.wrapping_add(ESCAPE).wrapping_add(byte ^ 0x20);
+        } else {
+            put(byte);
+            sum = sum.wrapping_add(byte);
+        }
+    }
+
+    put(END);
+    put(HEX[(sum >> 4) as usize]);
+    put(HEX[(sum & 0xf) as usize]);
+}
+
+// Packet data under construction, pushes beyond PACKET_MAX fail
+pub struct Reply {
+    len:  usize,
+    data: [u8;PACKET_MAX],
+}
+
+impl Reply {
+    pub fn new() -> Reply {
+        Reply { len: 0, data: [0;PACKET_MAX] }
+    }
+
+    pub fn data(&self) -> &[u8] { &self.data[..self.len] }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    pub fn push(&mut self, byte: u8) -> bool {
+        if self.len >= PACKET_MAX {
+            return false
+        }
+
+        self.data[self.len] = byte;
+        self.len += 1;
+        true
+    }
+
+    pub fn push_str(&mut self, s: &str) -> bool {
+        s.bytes().all(|b| self.push(b))
+    }
+
+    // Two hex digits per byte
+    pub fn push_hex(&mut self, bytes: &[u8]) -> bool {
+        bytes.iter().all(|&b| self.push(HEX[(b >> 4) as usize]) && self.push(HEX[(b & 0xf) as usize]))
+    }
+
+    // Big endian number without leading zeros
+    pub fn push_num(&mut self, value: u64) -> bool {
+        let digits = if value == 0 { 1 } else { (67 - value.leading_zeros() as usize) / 4 };
+        (0..digits).rev().all(|n| self.push(HEX[(value >> (4*n)) as usize & 0xf]))
+    }
+
+    // Register value in target (little endian) byte order
+    pub fn push_le(&mut self, value: u64, size: usize) -> bool {
+        (0..size).all(|n| self.push_hex(&[(value >> (8*n)) as u8]))
+    }
+}
+
+pub fn digit(c: u8) -> Option<u8> {
+    match c {
+        b'0'...b'9' => Some(c - b'0'),
+        b'a'...b'f' => Some(c - b'a' + 10),
+        b'A'...b'F' => Some(c - b'A' + 10),
+        _ => None,
+    }
+}
+
+// Big endian number (addresses, lengths, register numbers)
+pub fn hex(s: &[u8]) -> Option<u64> {
+    if s.is_empty() || s.len() > 16 {
+        return None
+    }
+
+    let mut value = 0u64;
+    for &c in s {
+        match digit(c) {
+            Some(d) => value = value << 4 | d as u64,
+            None => return None,
+        }
+    }
+
+    Some(value)
+}
+
+// Hex digit pairs into out, returns the number of bytes
+pub fn unhex(s: &[u8], out: &mut [u8]) -> Option<usize> {
+    if s.len() % 2 != 0 || s.len() / 2 > out.len() {
+        return None
+    }
+
+    for (n, pair) in s.chunks(2).enumerate() {
+        match (digit(pair[0]), digit(pair[1])) {
+            (Some(h), Some(l)) => out[n] = h << 4 | l,
+            _ => return None,
+        }
+    }
+
+    Some(s.len() / 2)
+}
+
+// Register value in target (little endian) byte order
+pub fn le(s: &[u8]) -> Option<u64> {
+    let mut bytes = [0u8;8];
+
+    match unhex(s, &mut bytes) {
+        Some(n) if n != 0 => Some(bytes.iter().rev().fold(0, |v, &b| v << 8 | b as u64)),
+        _ => None,
+    }
+}
+
+// Split around the first sep
+pub fn split(s: &[u8], sep: u8) -> Option<(&[u8], &[u8])> {
+    s.iter().position(|&c| c == sep).map(|n| (&s[..n], &s[n+1..]))
+}
+
+// Zeroed: detached with nothing pending
+pub struct GdbState {
+    pub attached: bool,        // stops wait for the debugger
+    pub stepping: bool,        // debugger single-step pending
+    pub watch:    Option<u64>, // watchpoint hit, reported after the access
+    pub write:    bool,        // hit watchpoint only reports writes
+    pub rx:       Decoder,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(dec: &mut Decoder, bytes: &[u8]) -> Vec<Event> {
+        bytes.iter().map(|&b| dec.feed(b)).filter(|&e| e != Event::None).collect()
+    }
+
+    fn framed(data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        encode(data, |b| out.push(b));
+        out
+    }
+
+    #[test]
+    fn encode_checksum() {
+        assert_eq!(framed(b"OK"), b"$OK#9a".to_vec());
+        assert_eq!(framed(b""), b"$#00".to_vec());
+        assert_eq!(framed(b"S05"), b"$S05#b8".to_vec());
+    }
+
+    #[test]
+    fn encode_escapes() {
+        assert_eq!(framed(b"a#b"), b"$a}\x03b#43".to_vec());
+        assert_eq!(framed(b"$}*"), b"$}\x04}]}\x0a#e2".to_vec());
+    }
+
+    #[test]
+    fn decode_packet() {
+        let mut dec = Decoder::new();
+
+        assert_eq!(feed(&mut dec, b"$g#67"), vec![Event::Packet]);
+        assert_eq!(dec.packet(), b"g");
+
+        assert_eq!(feed(&mut dec, b"+$m1000,4#8E"), vec![Event::Ack, Event::Packet]);
+        assert_eq!(dec.packet(), b"m1000,4");
+    }
+
+    #[test]
+    fn decode_round_trip() {
+        let mut dec = Decoder::new();
+        let data: Vec<u8> = (0..256).map(|b| b as u8).collect();
+
+        assert_eq!(feed(&mut dec, &framed(&data)), vec![Event::Packet]);
+        assert_eq!(dec.packet(), &data[..]);
+    }
+
+    #[test]
+    fn decode_errors() {
+        let mut dec = Decoder::new();
+
+        assert_eq!(feed(&mut dec, b"$g#68"), vec![Event::Bad]);
+        assert_eq!(feed(&mut dec, b"$g#x7"), vec![Event::Bad]);
+        assert_eq!(feed(&mut dec, b"$g#6x"), vec![Event::Bad]);
+
+        let long = vec![b'0'; PACKET_MAX + 1];
+        let sum = long.iter().fold(0u8, |s, &b| s.wrapping_add(b));
+        let mut pkt = vec![b'$'];
+        pkt.extend_from_slice(&long);
+        pkt.extend_from_slice(format!("#{:02x}", sum).as_bytes());
+        assert_eq!(feed(&mut dec, &pkt), vec![Event::Bad]);
+
+        // decoder recovers
+        assert_eq!(feed(&mut dec, b"$?#3f"), vec![Event::Packet]);
+        assert_eq!(dec.packet(), b"?");
+    }
+
+    #[test]
+    fn decode_out_of_band() {
+        let mut dec = Decoder::new();
+
+        assert_eq!(feed(&mut dec, b"\x03"), vec![Event::Interrupt]);
+        assert_eq!(feed(&mut dec, b"-x+"), vec![Event::Nack, Event::Ack]);
+        // restart inside a packet
+        assert_eq!(feed(&mut dec, b"$ab$c#63"), vec![Event::Packet]);
+        assert_eq!(dec.packet(), b"c");
+    }
+
+    #[test]
+    fn reply() {
+        let mut r = Reply::new();
+
+        assert!(r.push_str("T"));
+        assert!(r.push_hex(&[5, 0xab]));
+        assert!(r.push_le(0x1122334455667788, 8));
+        assert!(r.push_le(0x202, 4));
+        assert_eq!(r.data(), &b"T05ab887766554433221102020000"[..]);
+
+        r.clear();
+        assert!(r.push_num(0) && r.push(b',') && r.push_num(0x200) && r.push(b',') && r.push_num(!0));
+        assert_eq!(r.data(), &b"0,200,ffffffffffffffff"[..]);
+
+        r.clear();
+        assert!(r.data().is_empty());
+        assert!((0..PACKET_MAX).all(|_| r.push(b'x')));
+        assert!(!r.push(b'x'));
+        assert!(!r.push_hex(&[0]));
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(hex(b"ffff8000"), Some(0xffff8000));
+        assert_eq!(hex(b"FFFFFFFFFFFFFFFF"), Some(!0));
+        assert_eq!(hex(b"10000000000000000"), None);
+        assert_eq!(hex(b""), None);
+        assert_eq!(hex(b"12g"), None);
+
+        let mut out = [0u8;4];
+        assert_eq!(unhex(b"90cc", &mut out), Some(2));
+        assert_eq!(&out[..2], &[0x90, 0xcc]);
+        assert_eq!(unhex(b"9", &mut out), None);
+        assert_eq!(unhex(b"0102030405", &mut out), None);
+        assert_eq!(unhex(b"zz", &mut out), None);
+
+        assert_eq!(le(b"8877665544332211"), Some(0x1122334455667788));
+        assert_eq!(le(b"02020000"), Some(0x202));
+        assert_eq!(le(b""), None);
+        assert_eq!(le(b"001122334455667788"), None);
+
+        assert_eq!(split(b"m1000,4", b','), Some((&b"m1000"[..], &b"4"[..])));
+        assert_eq!(split(b"g", b','), None);
+    }
+}
@@ -1,5 +1,6 @@
 #![feature(lang_items, const_fn, asm, unique, try_from)]
-#![no_std]
+// host unit tests run with std
+#![cfg_attr(not(test), no_std)]
 
 #[macro_use]
 extern crate bitflags;
@@ -9,6 +10,7 @@ extern crate spin;
 extern crate x86; // cpuid
 extern crate x86_64;
 extern crate multiboot;
+#[cfg(not(test))]
 extern crate rlibc;
 
 #[macro_use]
@@ -24,6 +26,7 @@ pub mod frame;
 pub mod smap;
 pub mod vmx;
 pub mod msr;
+pub mod port;
 pub mod mtrr;
 pub mod cr;
 pub mod dr;
@@ -33,6 +36,10 @@ pub mod exceptions;
 pub mod segmentation;
 pub mod interrupts;
 pub mod cpu;
+pub mod cpuid;
 pub mod vm;
 pub mod mmap;
 pub mod pool;
+pub mod breakpoint;
+pub mod space;
+pub mod gdb;
@@ -91,8 +91,8 @@ bitfield!{
     impl Debug;
 
     pub syscall,_:0;
-    pub ia32_e,_:8;
-    pub ia32_a,_:10;
+    pub ia32_e,set_ia32_e:8;
+    pub ia32_a,set_ia32_a:10;
     pub nx_e,_:11;
 }
 
@@ -110,3 +110,163 @@ impl utils::RawValue for IA32Efer {
     fn as_u64(&self) -> u64 { self.0 as u64 }
     fn update_u64(&mut self, v: u64) { self.0 = v; }
 }
+
+
+// Virtual MSRs
+//
+// Intercepted MSRs without VMCS guest field have their guest visible
+// value tracked here. EFER and PAT live in the VMCS.
+
+pub const MTRR_VAR_MAX: usize = 16;
+
+pub const IA32_EFER_SCE: u64 = 1<<0;
+pub const IA32_EFER_LME: u64 = 1<<8;
+pub const IA32_EFER_LMA: u64 = 1<<10;
+pub const IA32_EFER_NXE: u64 = 1<<11;
+pub const IA32_EFER_MSK: u64 = IA32_EFER_SCE|IA32_EFER_LME|IA32_EFER_LMA|IA32_EFER_NXE;
+
+const APIC_BASE_MSK:   u64 = 1<<8 | 1<<10 | 1<<11; // BSP, EXTD, EN
+const MTRR_DEF_MSK:    u64 = 0xff | 1<<10 | 1<<11;  // type, FE, E
+const MTRR_BASE_MSK:   u64 = 0xff;
+const MTRR_MASK_MSK:   u64 = 1<<11;                 // V
+const PG_ADDR_MSK:     u64 = !0xfff;
+
+// What to do with unknown intercepted MSRs
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MsrPolicy {
+    Fault,
+    Passthrough,
+    Ignore,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MsrError {
+    Unknown,
+    Reserved,
+}
+
+pub fn mtrr_type_valid(kind: u64) -> bool {
+    match kind {
+        0 | 1 | 4 | 5 | 6 => true,
+        _ => false,
+    }
+}
+
+// Each PAT entry accepts MTRR types plus UC- (7)
+pub fn pat_valid(value: u64) -> bool {
+    (0..8).all(|i| {
+        let kind = (value >> (i*8)) & 0xff;
+        kind == 7 || mtrr_type_valid(kind)
+    })
+}
+
+pub struct VirtualMSR {
+    pub policy:  MsrPolicy,
+    paddr_msk:   u64,
+    apic_base:   u64,
+    feat_ctl:    u64,
+    mtrr_def:    u64,
+    mtrr_cnt:    usize,
+    mtrr_base:   [u64;MTRR_VAR_MAX],
+    mtrr_mask:   [u64;MTRR_VAR_MAX],
+}
+
+impl VirtualMSR {
+    pub fn mtrr_count(&self) -> usize { self.mtrr_cnt }
+
+    // Variable range MTRR index, if any
+    fn mtrr_var(&self, index: u32) -> Option<(usize, bool)> {
+        if index < IA32_MTRR_PHYSBASE0 {
+            return None
+        }
+
+        let n = (index - IA32_MTRR_PHYSBASE0) as usize;
+        if n/2 < self.mtrr_cnt {
+            Some((n/2, n%2 == 1))
+        } else {
+            None
+        }
+    }
+
+    pub fn read(&self, index: u32) -> Result<u64, MsrError> {
+        match index {
+            IA32_APIC_BASE       => Ok(self.apic_base),
+            IA32_FEATURE_CONTROL => Ok(self.feat_ctl),
+            IA32_MTRR_DEF_TYPE   => Ok(self.mtrr_def),
+            _ => match self.mtrr_var(index) {
+                Some((n, false)) => Ok(self.mtrr_base[n]),
+                Some((n, true))  => Ok(self.mtrr_mask[n]),
+                None => Err(MsrError::Unknown),
+            },
+        }
+    }
+
+    pub fn write(&mut self, index: u32, value: u64) -> Result<(), MsrError> {
+        let addr_msk = self.paddr_msk & PG_ADDR_MSK;
+
+        match index {
+            IA32_APIC_BASE => {
+                if value & !(APIC_BASE_MSK|addr_msk) != 0 {
+                    return Err(MsrError::Reserved)
+                }
+                self.apic_base = value;
+            },
+
+            // locked
+            IA32_FEATURE_CONTROL => return Err(MsrError::Reserved),
+
+            IA32_MTRR_DEF_TYPE => {
+                if value & !MTRR_DEF_MSK != 0 || !mtrr_type_valid(value & 0xff) {
+                    return Err(MsrError::Reserved)
+                }
+                self.mtrr_def = value;
+            },
+
+            _ => match self.mtrr_var(index) {
+                Some((n, false)) => {
+                    if value & !(MTRR_BASE_MSK|addr_msk) != 0 ||
+                        !mtrr_type_valid(value & 0xff) {
+                        return Err(MsrError::Reserved)
+                    }
+                    self.mtrr_base[n] = value;
+                },
+                Some((n, true)) => {
+                    if value & !(MTRR_MASK_MSK|addr_msk) != 0 {
+                        return Err(MsrError::Reserved)
+                    }
+                    self.mtrr_mask[n] = value;
+                },
+                None => return Err(MsrError::Unknown),
+            },
+        }
+
+        Ok(())
+    }
+
+    // Initial values from hardware ones, with VMX hidden
+    pub fn setup(&mut self, max_paddr: u64, mtrr_cnt: u8) {
+        self.policy    = MsrPolicy::Fault;
+        self.paddr_msk = max_paddr;
+
+        self.apic_base = rdmsr(IA32_APIC_BASE);
+        self.mtrr_def  = rdmsr(IA32_MTRR_DEF_TYPE);
+
+        let mut feat = IA32_FEAT_CTL::Flags::from_bits_truncate(
+            rdmsr(IA32_FEATURE_CONTROL));
+        feat.remove(IA32_FEAT_CTL::VMX);
+        feat.insert(IA32_FEAT_CTL::LOCK);
+        self.feat_ctl = feat.bits();
+
+        self.mtrr_cnt = if (mtrr_cnt as usize) < MTRR_VAR_MAX {
+            mtrr_cnt as usize
+        } else {
+            MTRR_VAR_MAX
+        };
+
+        for n in 0..self.mtrr_cnt {
+            let msr = IA32_MTRR_PHYSBASE0 + 2*n as u32;
+            self.mtrr_base[n] = rdmsr(msr);
+            self.mtrr_mask[n] = rdmsr(msr+1);
+        }
+    }
+}
@@ -1,3 +1,4 @@
 pub mod utils;
 pub mod ptb;
 pub mod map;
+pub mod walk;
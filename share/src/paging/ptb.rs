@@ -185,8 +185,9 @@ pub trait PTBEntry {
             entry |= addr & msk;
         }
 
+        // offset may be "negative"
         if (conf.modifier & PG_OP_OFF) != 0 {
-            entry += conf.offset & msk;
+            entry = entry.wrapping_add(conf.offset) & msk;
         }
 
         // XXX: cache attributes (PAT, PCD, PWT, LPAT)
@@ -260,10 +261,47 @@ pub trait PTBEntry {
         }
     }
 
+    // Update page attributes (pvl, memory type) and/or frames
     fn remap(&mut self, addr: u64, conf: &PagingConfig, alloc: &mut Self::Alloc) {
-        panic!("remap not implemented (lv{})\n", self.shift());
+        if self.is_page() {
+            if self.raw() == 0 {
+                return
+            }
+
+            let mut entry = self.raw();
+
+            if (conf.modifier & PG_OP_ADDR) != 0 {
+                let msk = addr_mask(self.shift());
+                let mut frame = addr;
+
+                if (conf.modifier & PG_OP_OFF) != 0 {
+                    frame = frame.wrapping_add(conf.offset);
+                }
+
+                entry = (entry & !msk) | (frame & msk);
+            }
+
+            if (conf.modifier & PG_OP_PVL) != 0 {
+                entry = (entry & !conf.pvl_msk) | (conf.pg_attr & conf.pvl_msk);
+            }
+
+            if (conf.modifier & PG_OP_MMT) != 0 {
+                entry = (entry & !conf.mmt_msk) | (conf.pg_attr & conf.mmt_msk);
+            }
+
+            self.set(entry);
+
+            #[cfg(feature = "debug_paging")]
+            log!("@{:#x} (lv{}) PTE.RMP 0x{:x} PTE = 0x{:08x}\n"
+                 ,{self as *const _ as *const u64 as u64}
+                 ,self.shift(), addr, self.raw());
+
+        } else if self.present() {
+            self.as_table_mut().remap(addr, conf, alloc);
+        }
     }
 
+    // Split a large page one level down, keeping its frame and attributes
     fn finest(&mut self, addr: u64, conf: &PagingConfig, alloc: &mut Self::Alloc) {
         let algn = if !pg_aligned(self.shift(), addr) {
             pg_align(self.shift(), addr)
@@ -271,14 +309,21 @@ pub trait PTBEntry {
             addr
         };
 
+        // frame may not be identity mapped (ie. A20 mirror)
         let mut nconf = *conf;
-        nconf.large = false;
-        nconf.modifier = PG_OP_ADDR|PG_OP_PVL|PG_OP_MMT;
+        nconf.modifier = PG_OP_ADDR|PG_OP_OFF|PG_OP_PVL|PG_OP_MMT;
+        nconf.offset  = self.page_addr().wrapping_sub(algn);
         nconf.pg_attr = self.raw() & (conf.pvl_msk|conf.mmt_msk);
 
-        /// XXX: self.erase() ? (always a large parge)
         self.unmap(algn, &nconf, alloc);
-        self.map(algn, &nconf, alloc);
+
+        match alloc.get_page() {
+            None => panic!("No memory to split page (lv{}) !", self.shift()),
+            Some(tbl_addr) => self.set_table(tbl_addr, &nconf),
+        };
+
+        // next level entries may still be large pages
+        self.as_table_mut().map(algn, &nconf, alloc);
     }
 
 
@@ -286,15 +331,16 @@ pub trait PTBEntry {
     fn next(&mut self, addr: u64, conf: &PagingConfig, alloc: &mut Self::Alloc)
             -> &mut <Self::Next as PTBMap>::Entry {
 
-        if ! self.present() {
+        // large pages may have no permission (nested paging)
+        if self.is_large() {
+            self.finest(addr, conf, alloc);
+        } else if ! self.present() {
             let nt = match alloc.get_page() {
                 None => panic!("No memory for next table (lv{}) !", self.shift()),
                 Some(a) => a,
             };
 
             self.set_table(nt, conf);
-        } else if self.is_large() {
-            self.finest(addr, conf, alloc);
         }
 
         self.as_table_mut().at_mut(addr)
@@ -312,8 +358,9 @@ pub trait PTBEntry {
         unsafe { &mut *(self.table_addr() as *mut _) }
     }
 
+    // Tables are never offset
     fn set_table(&mut self, addr: u64, conf: &PagingConfig) {
-        let base = self.basic(addr, addr_mask(PG_4K_SHIFT), conf);
+        let base = addr & addr_mask(PG_4K_SHIFT);
         self.set(base | conf.tb_attr);
 
         #[cfg(feature = "debug_paging")]
@@ -0,0 +1,524 @@
+// Guest paging walker
+//
+// Translate guest linear addresses through 32 bits, PAE or 4 levels
+// page tables. Page tables are read from a generic physical memory
+// so that the walker does not depend on VMM memory layout.
+
+use paging::utils::*;
+
+pub trait PhysicalMemory {
+    fn read_u32(&mut self, addr: u64) -> Option<u32>;
+    fn read_u64(&mut self, addr: u64) -> Option<u64>;
+    fn write_u32(&mut self, addr: u64, value: u32) -> bool;
+    fn write_u64(&mut self, addr: u64, value: u64) -> bool;
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PagingMode {
+    Legacy32,
+    Pae,
+    Long4,
+}
+
+// Paging related guest state
+#[derive(Debug, Copy, Clone)]
+pub struct WalkCtx {
+    pub mode:  PagingMode,
+    pub cr3:   u64,
+    pub pdpte: [u64;4], // PAE only
+    pub wp:    bool,
+    pub pse:   bool,
+    pub nxe:   bool,
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct WalkAccess {
+    pub write: bool,
+    pub user:  bool,
+    pub fetch: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Translation {
+    pub paddr: u64,
+    pub shift: usize, // page size
+}
+
+impl Translation {
+    // Bytes left in the page from translated address
+    pub fn remaining(&self) -> usize {
+        (pg_size(self.shift) as u64 - pg_offset(self.shift, self.paddr)) as usize
+    }
+}
+
+// #PF error code bits
+pub const PF_ERR_P:    u32 = 1<<0;
+pub const PF_ERR_W:    u32 = 1<<1;
+pub const PF_ERR_U:    u32 = 1<<2;
+pub const PF_ERR_RSVD: u32 = 1<<3;
+pub const PF_ERR_I:    u32 = 1<<4;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum WalkError {
+    Fault(u32), // #PF error code
+    Memory(u64),  // page table unreadable
+}
+
+// Entries crossed by a walk, to be updated with accessed/dirty bits
+struct Trail {
+    entries: [(u64, u64);4], // address, value
+    depth:   usize,
+}
+
+// Large page reserved bits between PAT bit and frame address
+const PG_LARGE_RSV_2M: u64 = 0x1fe000;
+const PG_LARGE_RSV_1G: u64 = 0x3fffe000;
+
+struct Level {
+    shift: usize,
+    bits:  usize, // index width
+    large: bool,  // PS allowed
+}
+
+static LONG4_LVL: [Level;4] = [
+    Level { shift: PG_512G_SHIFT, bits: 9, large: false },
+    Level { shift: PG_1G_SHIFT,   bits: 9, large: true  },
+    Level { shift: PG_2M_SHIFT,   bits: 9, large: true  },
+    Level { shift: PG_4K_SHIFT,   bits: 9, large: false },
+];
+
+static PAE_LVL: [Level;2] = [
+    Level { shift: PG_2M_SHIFT,   bits: 9, large: true  },
+    Level { shift: PG_4K_SHIFT,   bits: 9, large: false },
+];
+
+static LEGACY32_LVL: [Level;2] = [
+    Level { shift: PG_4M_SHIFT,   bits: 10, large: true  },
+    Level { shift: PG_4K_SHIFT,   bits: 10, large: false },
+];
+
+impl WalkCtx {
+    fn error(&self, access: &WalkAccess, flags: u32) -> WalkError {
+        let mut code = flags;
+
+        if access.write { code |= PF_ERR_W }
+        if access.user  { code |= PF_ERR_U }
+        if access.fetch && self.nxe && self.mode != PagingMode::Legacy32 {
+            code |= PF_ERR_I
+        }
+
+        WalkError::Fault(code)
+    }
+
+    fn read_entry<M>(&self, mem: &mut M, addr: u64) -> Result<u64, WalkError>
+        where M: PhysicalMemory {
+        let entry = if self.mode == PagingMode::Legacy32 {
+            mem.read_u32(addr).map(|e| e as u64)
+        } else {
+            mem.read_u64(addr)
+        };
+
+        entry.ok_or(WalkError::Memory(addr))
+    }
+
+    fn write_entry<M>(&self, mem: &mut M, addr: u64, entry: u64) -> Result<(), WalkError>
+        where M: PhysicalMemory {
+        let done = if self.mode == PagingMode::Legacy32 {
+            mem.write_u32(addr, entry as u32)
+        } else {
+            mem.write_u64(addr, entry)
+        };
+
+        if done { Ok(()) } else { Err(WalkError::Memory(addr)) }
+    }
+
+    fn levels(&self) -> &'static [Level] {
+        match self.mode {
+            PagingMode::Long4    => &LONG4_LVL,
+            PagingMode::Pae      => &PAE_LVL,
+            PagingMode::Legacy32 => &LEGACY32_LVL,
+        }
+    }
+
+    // Large page frame address
+    fn large_frame(&self, entry: u64, shift: usize) -> u64 {
+        if self.mode == PagingMode::Legacy32 {
+            // PSE-36: bits 20:13 hold address bits 39:32
+            let low  = entry & addr_mask(shift) & 0xffffffff;
+            let high = (entry >> 13) & 0xff;
+            low | high << 32
+        } else {
+            entry & addr_mask(shift)
+        }
+    }
+
+    fn large_reserved(&self, entry: u64, shift: usize) -> bool {
+        match shift {
+            PG_2M_SHIFT => entry & PG_LARGE_RSV_2M != 0,
+            PG_1G_SHIFT => entry & PG_LARGE_RSV_1G != 0,
+            _ => false,
+        }
+    }
+
+    // Translate and check permissions, page tables are not modified
+    fn lookup<M>(&self, mem: &mut M, vaddr: u64, access: &WalkAccess, trail: &mut Trail)
+                 -> Result<Translation, WalkError> where M: PhysicalMemory {
+        let esz: u64 = if self.mode == PagingMode::Legacy32 { 4 } else { 8 };
+
+        let mut table = match self.mode {
+            PagingMode::Pae => {
+                let pdpte = self.pdpte[pdp_pae_idx(vaddr)];
+                if pdpte & PG_P == 0 {
+                    return Err(self.error(access, 0))
+                }
+                pdpte & addr_mask(PG_4K_SHIFT)
+            },
+            PagingMode::Legacy32 => self.cr3 & 0xfffff000,
+            PagingMode::Long4    => self.cr3 & addr_mask(PG_4K_SHIFT),
+        };
+
+        let mut rw = true;
+        let mut us = true;
+        let mut nx = false;
+
+        let mut frame = 0;
+        let mut shift = PG_4K_SHIFT;
+
+        for lvl in self.levels() {
+            let idx   = (vaddr as usize >> lvl.shift) & ((1<<lvl.bits) - 1);
+            let addr  = table + idx as u64 * esz;
+            let entry = self.read_entry(mem, addr)?;
+
+            if entry & PG_P == 0 {
+                return Err(self.error(access, 0))
+            }
+
+            if entry & PG_NX != 0 && self.mode != PagingMode::Legacy32 {
+                if !self.nxe {
+                    return Err(self.error(access, PF_ERR_P|PF_ERR_RSVD))
+                }
+                nx = true;
+            }
+
+            rw &= entry & PG_RW  != 0;
+            us &= entry & PG_USR != 0;
+
+            trail.entries[trail.depth] = (addr, entry);
+            trail.depth += 1;
+
+            let large = lvl.large && entry & PG_PS != 0 &&
+                (self.mode != PagingMode::Legacy32 || self.pse);
+
+            if large {
+                if self.large_reserved(entry, lvl.shift) {
+                    return Err(self.error(access, PF_ERR_P|PF_ERR_RSVD))
+                }
+                frame = self.large_frame(entry, lvl.shift);
+                shift = lvl.shift;
+                break;
+            }
+
+            if lvl.shift == PG_4K_SHIFT {
+                frame = entry & addr_mask(PG_4K_SHIFT);
+                break;
+            }
+
+            table = entry & addr_mask(PG_4K_SHIFT);
+        }
+
+        if access.user && !us {
+            return Err(self.error(access, PF_ERR_P))
+        }
+
+        if access.write && !rw && (access.user || self.wp) {
+            return Err(self.error(access, PF_ERR_P))
+        }
+
+        if access.fetch && nx {
+            return Err(self.error(access, PF_ERR_P))
+        }
+
+        Ok(Translation {
+            paddr: frame + pg_offset(shift, vaddr),
+            shift: shift,
+        })
+    }
+
+    // Translation without side effects (no accessed/dirty bits)
+    pub fn probe<M>(&self, mem: &mut M, vaddr: u64, access: &WalkAccess)
+                    -> Result<Translation, WalkError> where M: PhysicalMemory {
+        let mut trail = Trail { entries: [(0, 0);4], depth: 0 };
+        self.lookup(mem, vaddr, access, &mut trail)
+    }
+
+    // Accessed/dirty bits are only set once the whole walk succeeded
+    pub fn walk<M>(&self, mem: &mut M, vaddr: u64, access: &WalkAccess)
+                   -> Result<Translation, WalkError> where M: PhysicalMemory {
+        let mut trail = Trail { entries: [(0, 0);4], depth: 0 };
+        let tr = self.lookup(mem, vaddr, access, &mut trail)?;
+
+        for n in 0..trail.depth {
+            let (addr, entry) = trail.entries[n];
+            let mut update = entry | PG_ACC;
+
+            if n == trail.depth-1 && access.write {
+                update |= PG_DRT;
+            }
+
+            if update != entry {
+                self.write_entry(mem, addr, update)?;
+            }
+        }
+
+        Ok(tr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MEM_SZ: usize = 64*PG_4KB;
+
+    // Synthetic guest physical memory holding page tables
+    struct Mem(Vec<u8>);
+
+    impl Mem {
+        fn new() -> Mem { Mem(vec![0; MEM_SZ]) }
+
+        fn set32(&mut self, addr: u64, value: u32) { self.write_u32(addr, value); }
+        fn set64(&mut self, addr: u64, value: u64) { self.write_u64(addr, value); }
+        fn get32(&mut self, addr: u64) -> u32 { self.read_u32(addr).unwrap() }
+        fn get64(&mut self, addr: u64) -> u64 { self.read_u64(addr).unwrap() }
+
+        fn write(&mut self, addr: u64, value: u64, len: usize) -> bool {
+            let a = addr as usize;
+            match self.0.get_mut(a..a+len) {
+                Some(b) => {
+                    for i in 0..len { b[i] = (value >> (i*8)) as u8 }
+                    true
+                },
+                None => false,
+            }
+        }
+    }
+
+    impl PhysicalMemory for Mem {
+        fn read_u32(&mut self, addr: u64) -> Option<u32> {
+            let a = addr as usize;
+            self.0.get(a..a+4).map(|b| b.iter().rev().fold(0, |v, x| v<<8 | *x as u32))
+        }
+
+        fn read_u64(&mut self, addr: u64) -> Option<u64> {
+            let a = addr as usize;
+            self.0.get(a..a+8).map(|b| b.iter().rev().fold(0, |v, x| v<<8 | *x as u64))
+        }
+
+        fn write_u32(&mut self, addr: u64, value: u32) -> bool {
+            self.write(addr, value as u64, 4)
+        }
+
+        fn write_u64(&mut self, addr: u64, value: u64) -> bool {
+            self.write(addr, value, 8)
+        }
+    }
+
+    const RW:  u64 = PG_P|PG_RW;
+    const URW: u64 = PG_P|PG_RW|PG_USR;
+
+    fn ctx(mode: PagingMode, cr3: u64) -> WalkCtx {
+        WalkCtx { mode: mode, cr3: cr3, pdpte: [0;4], wp: true, pse: true, nxe: true }
+    }
+
+    fn read()  -> WalkAccess { WalkAccess::default() }
+    fn write() -> WalkAccess { WalkAccess { write: true, ..WalkAccess::default() } }
+    fn user()  -> WalkAccess { WalkAccess { user: true, ..WalkAccess::default() } }
+    fn fetch() -> WalkAccess { WalkAccess { fetch: true, ..WalkAccess::default() } }
+
+    // 0x00401234 -> 0x5234 through PD 0x1000, PT 0x2000
+    fn legacy32(mem: &mut Mem, pte: u32) -> WalkCtx {
+        mem.set32(0x1000 + 1*4, 0x2000 | URW as u32);
+        mem.set32(0x2000 + 1*4, 0x5000 | pte);
+        ctx(PagingMode::Legacy32, 0x1000)
+    }
+
+    // 0x0000_0080_4020_1234 -> 0x9234 through PML4 0x1000 ... PT 0x4000
+    const LONG_VA: u64 = 1<<39 | 1<<30 | 1<<21 | 1<<12 | 0x234;
+
+    fn long4(mem: &mut Mem, pte: u64) -> WalkCtx {
+        mem.set64(0x1000 + 1*8, 0x2000 | URW);
+        mem.set64(0x2000 + 1*8, 0x3000 | URW);
+        mem.set64(0x3000 + 1*8, 0x4000 | URW);
+        mem.set64(0x4000 + 1*8, 0x9000 | pte);
+        ctx(PagingMode::Long4, 0x1000)
+    }
+
+    #[test]
+    fn legacy32_4k() {
+        let mut mem = Mem::new();
+        let ctx = legacy32(&mut mem, URW as u32);
+
+        let tr = ctx.walk(&mut mem, 0x00401234, &read()).unwrap();
+        assert_eq!((tr.paddr, tr.shift, tr.remaining()), (0x5234, PG_4K_SHIFT, 0xdcc));
+        assert_eq!(mem.get32(0x1004) as u64 & (PG_ACC|PG_DRT), PG_ACC);
+        assert_eq!(mem.get32(0x2004) as u64 & (PG_ACC|PG_DRT), PG_ACC);
+
+        // dirty on the last level only
+        ctx.walk(&mut mem, 0x00401234, &write()).unwrap();
+        assert_eq!(mem.get32(0x1004) as u64 & (PG_ACC|PG_DRT), PG_ACC);
+        assert_eq!(mem.get32(0x2004) as u64 & (PG_ACC|PG_DRT), PG_ACC|PG_DRT);
+    }
+
+    #[test]
+    fn legacy32_4m_pse36() {
+        let mut mem = Mem::new();
+        let mut ctx = ctx(PagingMode::Legacy32, 0x1000);
+
+        // bits 20:13 give address bits 39:32
+        mem.set32(0x1000 + 3*4, 0x00800000 | 0x5<<13 | (PG_PS|RW) as u32);
+
+        let tr = ctx.walk(&mut mem, 0x00c12345, &write()).unwrap();
+        assert_eq!((tr.paddr, tr.shift), (0x5_00812345, PG_4M_SHIFT));
+        assert_eq!(mem.get32(0x100c) as u64 & (PG_ACC|PG_DRT), PG_ACC|PG_DRT);
+
+        // PS is ignored without CR4.PSE: 0x00800000 is a page table
+        ctx.pse = false;
+        assert_eq!(ctx.walk(&mut mem, 0x00c12345, &read()),
+                   Err(WalkError::Memory(0x0080a000 + 0x12*4)));
+    }
+
+    #[test]
+    fn pae() {
+        let mut mem = Mem::new();
+        let mut ctx = ctx(PagingMode::Pae, 0);
+
+        ctx.pdpte[2] = 0x1000 | PG_P;
+        mem.set64(0x1000 + 1*8, 0x2000 | URW);
+        mem.set64(0x2000 + 3*8, 0x7000 | URW);
+        mem.set64(0x1000 + 2*8, 0x00400000 | PG_PS | RW);
+
+        // 4KB: 0x80203456
+        let tr = ctx.walk(&mut mem, 0x80203456, &read()).unwrap();
+        assert_eq!((tr.paddr, tr.shift), (0x7456, PG_4K_SHIFT));
+
+        // 2MB: 0x80412345
+        let tr = ctx.walk(&mut mem, 0x80412345, &read()).unwrap();
+        assert_eq!((tr.paddr, tr.shift), (0x00412345, PG_2M_SHIFT));
+        assert_eq!(mem.get64(0x1010) & PG_ACC, PG_ACC);
+
+        // not present PDPTE
+        assert_eq!(ctx.walk(&mut mem, 0x40000000, &write()), Err(WalkError::Fault(PF_ERR_W)));
+
+        // reserved bits of a large page
+        mem.set64(0x1000 + 2*8, 0x00400000 | 1<<13 | PG_PS | RW);
+        assert_eq!(ctx.walk(&mut mem, 0x80412345, &read()),
+                   Err(WalkError::Fault(PF_ERR_P|PF_ERR_RSVD)));
+    }
+
+    #[test]
+    fn long4_4k() {
+        let mut mem = Mem::new();
+        let ctx = long4(&mut mem, URW);
+
+        let tr = ctx.walk(&mut mem, LONG_VA, &write()).unwrap();
+        assert_eq!((tr.paddr, tr.shift), (0x9234, PG_4K_SHIFT));
+
+        for &(addr, bits) in [(0x1008, PG_ACC), (0x2008, PG_ACC),
+                              (0x3008, PG_ACC), (0x4008, PG_ACC|PG_DRT)].iter() {
+            assert_eq!(mem.get64(addr) & (PG_ACC|PG_DRT), bits, "entry {:#x}", addr);
+        }
+    }
+
+    #[test]
+    fn long4_large() {
+        let mut mem = Mem::new();
+        let ctx = ctx(PagingMode::Long4, 0x1000);
+
+        mem.set64(0x1000, 0x2000 | RW);
+        mem.set64(0x2000 + 1*8, 0x80000000 | PG_PS | RW);   // 1GB
+        mem.set64(0x2000 + 2*8, 0x3000 | RW);
+        mem.set64(0x3000 + 5*8, 0x00e00000 | PG_PS | RW);   // 2MB
+
+        let tr = ctx.walk(&mut mem, 0x4abcdef0, &read()).unwrap();
+        assert_eq!((tr.paddr, tr.shift), (0x8abcdef0, PG_1G_SHIFT));
+
+        let tr = ctx.walk(&mut mem, 0x80a12345, &read()).unwrap();
+        assert_eq!((tr.paddr, tr.shift, tr.remaining()), (0x00e12345, PG_2M_SHIFT, 0x1edcbb));
+
+        // 1GB reserved bits
+        mem.set64(0x2000 + 1*8, 0x80000000 | 1<<20 | PG_PS | RW);
+        assert_eq!(ctx.walk(&mut mem, 0x4abcdef0, &user()),
+                   Err(WalkError::Fault(PF_ERR_P|PF_ERR_RSVD|PF_ERR_U)));
+    }
+
+    #[test]
+    fn error_codes() {
+        let mut mem = Mem::new();
+
+        // not present
+        let ctx = long4(&mut mem, 0);
+        assert_eq!(ctx.walk(&mut mem, LONG_VA, &user()), Err(WalkError::Fault(PF_ERR_U)));
+
+        // supervisor page
+        let ctx = long4(&mut mem, RW);
+        assert_eq!(ctx.walk(&mut mem, LONG_VA, &user()), Err(WalkError::Fault(PF_ERR_P|PF_ERR_U)));
+
+        // read-only page, supervisor writes allowed without CR0.WP
+        let mut ctx = long4(&mut mem, PG_P|PG_USR);
+        assert_eq!(ctx.walk(&mut mem, LONG_VA, &write()), Err(WalkError::Fault(PF_ERR_P|PF_ERR_W)));
+        ctx.wp = false;
+        assert!(ctx.walk(&mut mem, LONG_VA, &write()).is_ok());
+
+        // no-execute page
+        let mut ctx = long4(&mut mem, URW|PG_NX);
+        assert_eq!(ctx.walk(&mut mem, LONG_VA, &fetch()), Err(WalkError::Fault(PF_ERR_P|PF_ERR_I)));
+        assert!(ctx.walk(&mut mem, LONG_VA, &read()).is_ok());
+
+        // NX is reserved when EFER.NXE is clear
+        ctx.nxe = false;
+        assert_eq!(ctx.walk(&mut mem, LONG_VA, &fetch()),
+                   Err(WalkError::Fault(PF_ERR_P|PF_ERR_RSVD)));
+
+        // no I/D bit in 32 bits paging
+        let mut ctx = legacy32(&mut mem, 0);
+        ctx.nxe = true;
+        assert_eq!(ctx.walk(&mut mem, 0x00401234, &fetch()), Err(WalkError::Fault(0)));
+    }
+
+    #[test]
+    fn no_update_on_fault() {
+        let mut mem = Mem::new();
+
+        // last level not present, then permission fault found
+        // after crossing every level
+        for &pte in [0, PG_P].iter() {
+            let ctx = long4(&mut mem, pte);
+            assert!(ctx.walk(&mut mem, LONG_VA, &write()).is_err());
+
+            for &addr in [0x1008, 0x2008, 0x3008, 0x4008].iter() {
+                assert_eq!(mem.get64(addr) & (PG_ACC|PG_DRT), 0, "entry {:#x}", addr);
+            }
+        }
+    }
+
+    #[test]
+    fn probe_no_update() {
+        let mut mem = Mem::new();
+        let ctx = long4(&mut mem, URW);
+
+        let tr = ctx.probe(&mut mem, LONG_VA, &write()).unwrap();
+        assert_eq!(tr.paddr, 0x9234);
+
+        for &addr in [0x1008, 0x2008, 0x3008, 0x4008].iter() {
+            assert_eq!(mem.get64(addr) & (PG_ACC|PG_DRT), 0, "entry {:#x}", addr);
+        }
+    }
+
+    #[test]
+    fn unreadable_table() {
+        let mut mem = Mem::new();
+        let ctx = ctx(PagingMode::Long4, MEM_SZ as u64);
+
+        assert_eq!(ctx.walk(&mut mem, 0, &read()), Err(WalkError::Memory(MEM_SZ as u64)));
+    }
+}
@@ -0,0 +1,44 @@
+// I/O ports access
+pub use x86_64::instructions::port::*;
+
+// Sized native port access (1, 2 or 4 bytes)
+pub fn read(port: u16, size: usize) -> u32 {
+    unsafe {
+        match size {
+            1 => inb(port) as u32,
+            2 => inw(port) as u32,
+            _ => inl(port),
+        }
+    }
+}
+
+pub fn write(port: u16, size: usize, value: u32) {
+    unsafe {
+        match size {
+            1 => outb(port, value as u8),
+            2 => outw(port, value as u16),
+            _ => outl(port, value),
+        }
+    }
+}
+
+// Keyboard controller
+pub const KBC_DATA:              u16 = 0x60;
+pub const KBC_CMD:               u16 = 0x64;
+
+pub const KBC_CMD_READ_OUTPUT:   u8 = 0xd0;
+pub const KBC_CMD_WRITE_OUTPUT:  u8 = 0xd1;
+pub const KBC_CMD_A20_OFF:       u8 = 0xdd;
+pub const KBC_CMD_A20_ON:        u8 = 0xdf;
+
+pub const KBC_OUTPUT_A20:        u8 = 1<<1;
+
+// System control port A
+pub const SYS_CTRL_A:            u16 = 0x92;
+pub const SYS_CTRL_A_A20:        u8 = 1<<1;
+
+// Keyboard controller command waiting for its data byte
+#[derive(Default, Copy, Clone)]
+pub struct KbcState {
+    pub cmd: u8,
+}
@@ -60,7 +60,7 @@ pub const BIOS_BOOT_INTERRUPT           : u8 = 0x19;
 
 // AX values
 pub const BIOS_GET_SMAP                 : u16 = 0xe820;
-pub const BIOS_SMAP_ID                  : u16 = 0x534d4150;
+pub const BIOS_SMAP_ID                  : u32 = 0x534d4150; // "SMAP"
 pub const BIOS_SMAP_ERROR               : u16 = 0x86;
 pub const BIOS_GET_EXT_MEM_32           : u16 = 0xe881;
 pub const BIOS_GET_EXT_MEM              : u16 = 0xe801;
@@ -1,7 +1,15 @@
 use core::slice;
-use multiboot::{Multiboot, MemoryType};
+use core::mem;
+use core::cmp;
+#[cfg(feature = "setup")]
+use multiboot::Multiboot;
+use smem::SecretArea;
 
-#[derive(PartialEq)]
+// Multiboot mmap entry: size u32, base u64, length u64, type u32
+#[cfg(feature = "setup")]
+const MBI_MMAP_TYPE_OFF: isize = 20;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum SystemMapEntryType {
     Available = 1, // memory, available to OS
     Reserved  = 2, // reserved, not available (rom, mem map dev)
@@ -10,32 +18,208 @@ pub enum SystemMapEntryType {
 }
 
 impl SystemMapEntryType {
-    pub fn from_mbi(mtype: MemoryType) -> SystemMapEntryType {
+    // Multiboot and E820 share the same type values
+    pub fn from_raw(mtype: u32) -> SystemMapEntryType {
         match mtype {
-            MemoryType::RAM => SystemMapEntryType::Available,
+            1 => SystemMapEntryType::Available,
+            3 => SystemMapEntryType::ACPI,
+            4 => SystemMapEntryType::NVS,
             _ => SystemMapEntryType::Reserved,
         }
     }
+
+    // Overlapping entries take the most restrictive type
+    fn priority(&self) -> u8 {
+        match *self {
+            SystemMapEntryType::Available => 0,
+            SystemMapEntryType::ACPI      => 1,
+            SystemMapEntryType::NVS       => 2,
+            SystemMapEntryType::Reserved  => 3,
+        }
+    }
 }
 
+#[derive(Debug, Copy, Clone)]
 pub struct SystemMapEntry {
     pub base: u64,
     pub len: usize,
     pub typ: SystemMapEntryType,
 }
 
+impl SystemMapEntry {
+    pub fn end(&self) -> u64 { self.base + self.len as u64 }
+}
+
+// BIOS E820 memory map entry
+#[repr(C, packed)]
+#[derive(Debug, Copy, Clone)]
+pub struct E820Entry {
+    pub base: u64,
+    pub len:  u64,
+    pub typ:  u32,
+}
+
+impl E820Entry {
+    pub fn as_u8(&self) -> &[u8] {
+        let ptr = self as *const _ as *const u8;
+        unsafe { slice::from_raw_parts(ptr, mem::size_of::<E820Entry>()) }
+    }
+}
+
+// Guest view of the system map as E820 records
+pub struct E820Iter<'a> {
+    entries: slice::Iter<'a, SystemMapEntry>,
+}
+
+impl<'a> Iterator for E820Iter<'a> {
+    type Item = E820Entry;
+
+    fn next(&mut self) -> Option<E820Entry> {
+        self.entries.next().map(|sme| E820Entry {
+            base: sme.base,
+            len:  sme.len as u64,
+            typ:  sme.typ as u32,
+        })
+    }
+}
+
 pub struct SystemMap {
     count: usize,
     entries: &'static mut[SystemMapEntry],
 }
 
 impl SystemMap {
+    // Worst case: every region boundary splits an entry, plus
+    // the VMM area hole in the middle of an available entry
+    pub fn capacity(regions: usize) -> usize { 2*regions + 1 }
+
+    pub fn entries(&self) -> &[SystemMapEntry] { &self.entries[..self.count] }
+
+    pub fn e820(&self) -> E820Iter {
+        E820Iter { entries: self.entries().iter() }
+    }
+
+    fn remove(&mut self, idx: usize) {
+        for n in idx..self.count-1 {
+            self.entries[n] = self.entries[n+1];
+        }
+        self.count -= 1;
+    }
+
+    // Keep entries sorted by base
+    fn insert(&mut self, sme: SystemMapEntry) {
+        if sme.len == 0 {
+            return
+        }
+
+        if self.count == self.entries.len() {
+            panic!("system map full !");
+        }
+
+        let mut idx = self.count;
+        while idx > 0 && self.entries[idx-1].base > sme.base {
+            self.entries[idx] = self.entries[idx-1];
+            idx -= 1;
+        }
+
+        self.entries[idx] = sme;
+        self.count += 1;
+    }
+
+    // Resolve overlaps and merge contiguous entries of same type
+    fn normalize(&mut self) {
+        let mut idx = 0;
+
+        while idx + 1 < self.count {
+            let cur  = self.entries[idx];
+            let next = self.entries[idx+1];
+
+            if next.base > cur.end() || (next.base == cur.end() && next.typ != cur.typ) {
+                idx += 1;
+                continue
+            }
+
+            if next.typ == cur.typ {
+                let end = cmp::max(cur.end(), next.end());
+                self.entries[idx].len = (end - cur.base) as usize;
+                self.remove(idx+1);
+            } else if cur.typ.priority() >= next.typ.priority() {
+                // current owns the overlap, keep next tail
+                self.remove(idx+1);
+                if next.end() > cur.end() {
+                    self.insert(SystemMapEntry {
+                        base: cur.end(),
+                        len:  (next.end() - cur.end()) as usize,
+                        typ:  next.typ,
+                    });
+                }
+            } else {
+                // next owns the overlap, split current around it
+                self.remove(idx);
+                self.insert(SystemMapEntry {
+                    base: cur.base,
+                    len:  (next.base - cur.base) as usize,
+                    typ:  cur.typ,
+                });
+                if cur.end() > next.end() {
+                    self.insert(SystemMapEntry {
+                        base: next.end(),
+                        len:  (cur.end() - next.end()) as usize,
+                        typ:  cur.typ,
+                    });
+                }
+            }
+
+            // previous entry may now be mergeable
+            idx = idx.saturating_sub(1);
+        }
+    }
+
+    // Remove [start, end[ from available memory
+    fn punch(&mut self, start: u64, end: u64) {
+        let mut idx = 0;
+
+        while idx < self.count {
+            let sme = self.entries[idx];
+
+            if sme.typ != SystemMapEntryType::Available
+                || sme.end() <= start || sme.base >= end {
+                idx += 1;
+                continue
+            }
+
+            self.remove(idx);
+
+            if sme.base < start {
+                self.insert(SystemMapEntry {
+                    base: sme.base,
+                    len:  (start - sme.base) as usize,
+                    typ:  sme.typ,
+                });
+            }
+
+            if sme.end() > end {
+                self.insert(SystemMapEntry {
+                    base: end,
+                    len:  (sme.end() - end) as usize,
+                    typ:  sme.typ,
+                });
+            }
+        }
+    }
+
+    // Sort/merge entries and hide the VMM area
+    fn finish(&mut self, area: &SecretArea) {
+        self.normalize();
+        self.punch(area.start, area.end);
+    }
+
     #[cfg(feature = "setup")]
-    pub fn init(addr: u64, cnt: usize, end: u64, mbi: &Multiboot) -> SystemMap {
+    pub fn init(addr: u64, cnt: usize, area: &SecretArea, mbi: &Multiboot) -> SystemMap {
         let ptr = addr as *mut SystemMapEntry;
         let sme = unsafe {slice::from_raw_parts_mut(ptr, cnt)};
         let mut smap = SystemMap {
-            count: cnt,
+            count: 0,
             entries: sme,
         };
 
@@ -44,19 +228,152 @@ impl SystemMap {
             Some(mm) => mm,
         };
 
-        let mut idx = 0;
         for m in mmaps {
-            let mut sme = &mut smap.entries[idx];
-            sme.base = m.base_address();
-            sme.typ = SystemMapEntryType::from_mbi(m.memory_type());
+            // XXX: multiboot crate hides ACPI/NVS types behind Unusable,
+            // raw type is the last field of the packed entry
+            let raw = unsafe {
+                let ptr = m as *const _ as *const u8;
+                *(ptr.offset(MBI_MMAP_TYPE_OFF) as *const u32)
+            };
 
-            if SystemMapEntryType::Available == sme.typ && sme.base == 1<<20 {
-                sme.len = (end - sme.base) as usize;
-            } else {
-                sme.len = m.length() as usize;
-            }
+            smap.insert(SystemMapEntry {
+                base: m.base_address(),
+                len:  m.length() as usize,
+                typ:  SystemMapEntryType::from_raw(raw),
+            });
         }
 
+        smap.finish(area);
+
+        for sme in smap.entries() {
+            log!("smap {:#x} - {:#x} {:?}\n", sme.base, sme.end(), sme.typ);
+        }
+
+        smap
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::SystemMapEntryType::*;
+
+    // (base, len, multiboot type) regions, as given by the bootloader
+    fn build(regions: &[(u64, usize, u32)], area: (u64, u64)) -> SystemMap {
+        let none = SystemMapEntry { base: 0, len: 0, typ: Reserved };
+        let buf  = vec![none; SystemMap::capacity(regions.len())].into_boxed_slice();
+        let mut smap = SystemMap { count: 0, entries: unsafe { &mut *Box::into_raw(buf) } };
+
+        for &(base, len, typ) in regions {
+            smap.insert(SystemMapEntry { base: base, len: len, typ: SystemMapEntryType::from_raw(typ) });
+        }
+
+        smap.finish(&SecretArea { start: area.0, end: area.1, size: (area.1 - area.0) as usize });
         smap
     }
+
+    fn ranges(smap: &SystemMap) -> Vec<(u64, u64, SystemMapEntryType)> {
+        smap.entries().iter().map(|e| (e.base, e.end(), e.typ)).collect()
+    }
+
+    const NO_AREA: (u64, u64) = (0xfff00000, 0xfff00000);
+
+    #[test]
+    fn types() {
+        let smap = build(&[(0x0000000, 0x9fc00, 1), (0x7fe0000, 0x10000, 3),
+                           (0x7ff0000, 0x10000, 4), (0x00f0000, 0x10000, 2),
+                           (0xfec0000, 0x1000,  5)], NO_AREA);
+
+        assert_eq!(ranges(&smap), vec![(0x0000000, 0x009fc00, Available),
+                                       (0x00f0000, 0x0100000, Reserved),
+                                       (0x7fe0000, 0x7ff0000, ACPI),
+                                       (0x7ff0000, 0x8000000, NVS),
+                                       (0xfec0000, 0xfec1000, Reserved)]);
+    }
+
+    #[test]
+    fn unsorted() {
+        let smap = build(&[(0x100000, 0x7f00000, 1), (0x9fc00, 0x400, 2),
+                           (0x000000, 0x9fc00,   1), (0xe0000, 0x20000, 2)], NO_AREA);
+
+        assert_eq!(ranges(&smap), vec![(0x000000, 0x009fc00, Available),
+                                       (0x09fc00, 0x00a0000, Reserved),
+                                       (0x0e0000, 0x0100000, Reserved),
+                                       (0x100000, 0x8000000, Available)]);
+    }
+
+    #[test]
+    fn merge() {
+        // contiguous, overlapping and zero length entries
+        let smap = build(&[(0x2000, 0x1000, 1), (0x0000, 0x2000, 1),
+                           (0x2800, 0x2000, 1), (0x8000, 0, 2),
+                           (0x4800, 0x800, 2),  (0x5000, 0x1000, 2)], NO_AREA);
+
+        assert_eq!(ranges(&smap), vec![(0x0000, 0x4800, Available),
+                                       (0x4800, 0x6000, Reserved)]);
+    }
+
+    #[test]
+    fn overlap_restrictive_wins() {
+        // reserved hole inside RAM, RAM starting inside reserved
+        // and ACPI partly covered by reserved
+        let smap = build(&[(0x000000, 0x10000, 1), (0x8000, 0x1000, 2),
+                           (0x0f0000, 0x20000, 2), (0x100000, 0x100000, 1),
+                           (0x300000, 0x20000, 3), (0x310000, 0x20000, 2)], NO_AREA);
+
+        assert_eq!(ranges(&smap), vec![(0x000000, 0x008000, Available),
+                                       (0x008000, 0x009000, Reserved),
+                                       (0x009000, 0x010000, Available),
+                                       (0x0f0000, 0x110000, Reserved),
+                                       (0x110000, 0x200000, Available),
+                                       (0x300000, 0x310000, ACPI),
+                                       (0x310000, 0x330000, Reserved)]);
+    }
+
+    #[test]
+    fn overlap_nested() {
+        // RAM inside reserved disappears, NVS inside ACPI splits it
+        let smap = build(&[(0x1000, 0x8000, 2), (0x2000, 0x1000, 1),
+                           (0x10000, 0x4000, 3), (0x11000, 0x1000, 4)], NO_AREA);
+
+        assert_eq!(ranges(&smap), vec![(0x01000, 0x09000, Reserved),
+                                       (0x10000, 0x11000, ACPI),
+                                       (0x11000, 0x12000, NVS),
+                                       (0x12000, 0x14000, ACPI)]);
+    }
+
+    #[test]
+    fn punch_vmm_area() {
+        let smap = build(&[(0x000000, 0x9fc00, 1), (0x100000, 0x7f00000, 1)],
+                         (0x7000000, 0x7400000));
+
+        assert_eq!(ranges(&smap), vec![(0x0000000, 0x009fc00, Available),
+                                       (0x0100000, 0x7000000, Available),
+                                       (0x7400000, 0x8000000, Available)]);
+    }
+
+    #[test]
+    fn punch_across_entries() {
+        // only available memory is removed
+        let smap = build(&[(0x0000, 0x4000, 1), (0x4000, 0x1000, 2),
+                           (0x5000, 0x4000, 1)], (0x2000, 0x7000));
+
+        assert_eq!(ranges(&smap), vec![(0x0000, 0x2000, Available),
+                                       (0x4000, 0x5000, Reserved),
+                                       (0x7000, 0x9000, Available)]);
+    }
+
+    #[test]
+    fn e820_records() {
+        let smap = build(&[(0x0, 0x9fc00, 1), (0x7fe0000, 0x10000, 3)], NO_AREA);
+        let recs: Vec<E820Entry> = smap.e820().collect();
+
+        assert_eq!(recs.len(), 2);
+        assert_eq!(mem::size_of::<E820Entry>(), 20);
+
+        let raw = recs[1].as_u8();
+        assert_eq!(&raw[0..8],   &[0x00, 0x00, 0xfe, 0x07, 0, 0, 0, 0]);
+        assert_eq!(&raw[8..16],  &[0x00, 0x00, 0x01, 0x00, 0, 0, 0, 0]);
+        assert_eq!(&raw[16..20], &[3, 0, 0, 0]);
+    }
 }
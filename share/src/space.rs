@@ -0,0 +1,159 @@
+// Guest address spaces seen through CR3 loads
+//
+// Every distinct CR3 value loaded by the guest is recorded with
+// the number of switches to it. Filters tell the VMM to stop on
+// a given CR3 or to ignore it. Ignored values become CR3-target
+// values: loading them no longer exits, so they are not counted.
+
+pub const SPACE_MAX: usize = 64;
+pub const CR3_FILTER_MAX: usize = 4;
+
+#[derive(Debug, Copy, Clone)]
+pub struct AddressSpace {
+    pub cr3:      u64,
+    pub switches: u64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Cr3Action {
+    Stop,   // hand over to the debugger
+    Ignore, // CR3-target value
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Cr3Filter {
+    pub cr3:    u64,
+    pub action: Cr3Action,
+}
+
+pub struct AddressSpaces {
+    count:         usize,
+    list:          [AddressSpace;SPACE_MAX],
+    nr_filters:    usize,
+    filters:       [Cr3Filter;CR3_FILTER_MAX],
+    pub untracked: u64,         // switches lost when the list is full
+    pub stop:      Option<u64>, // stop filter hit
+}
+
+impl AddressSpaces {
+    pub fn list(&self) -> &[AddressSpace] { &self.list[..self.count] }
+    pub fn filters(&self) -> &[Cr3Filter] { &self.filters[..self.nr_filters] }
+
+    pub fn find(&self, cr3: u64) -> Option<AddressSpace> {
+        self.list().iter().find(|s| s.cr3 == cr3).map(|s| *s)
+    }
+
+    // Account a switch to cr3
+    pub fn switch(&mut self, cr3: u64) {
+        for i in 0..self.count {
+            if self.list[i].cr3 == cr3 {
+                self.list[i].switches += 1;
+                return
+            }
+        }
+
+        if self.count >= SPACE_MAX {
+            self.untracked += 1;
+            return
+        }
+
+        self.list[self.count] = AddressSpace { cr3: cr3, switches: 1 };
+        self.count += 1;
+    }
+
+    pub fn filter(&self, cr3: u64) -> Option<Cr3Action> {
+        self.filters().iter().find(|f| f.cr3 == cr3).map(|f| f.action)
+    }
+
+    // Replaces any filter on the same CR3
+    pub fn add_filter(&mut self, filter: Cr3Filter) -> bool {
+        self.del_filter(filter.cr3);
+
+        if self.nr_filters >= CR3_FILTER_MAX {
+            return false
+        }
+
+        self.filters[self.nr_filters] = filter;
+        self.nr_filters += 1;
+        true
+    }
+
+    pub fn del_filter(&mut self, cr3: u64) -> Option<Cr3Filter> {
+        for i in 0..self.nr_filters {
+            if self.filters[i].cr3 == cr3 {
+                let filter = self.filters[i];
+                self.nr_filters -= 1;
+                self.filters[i] = self.filters[self.nr_filters];
+                return Some(filter)
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem;
+
+    fn filter(cr3: u64, action: Cr3Action) -> Cr3Filter {
+        Cr3Filter { cr3: cr3, action: action }
+    }
+
+    #[test]
+    fn switches() {
+        let mut spaces: AddressSpaces = unsafe { mem::zeroed() };
+
+        assert!(spaces.stop.is_none());
+        spaces.switch(0x1000);
+        spaces.switch(0x2000);
+        spaces.switch(0x1000);
+
+        assert_eq!(spaces.list().len(), 2);
+        assert_eq!(spaces.find(0x1000).map(|s| s.switches), Some(2));
+        assert_eq!(spaces.find(0x2000).map(|s| s.switches), Some(1));
+        assert!(spaces.find(0x3000).is_none());
+
+        for n in 3..SPACE_MAX as u64 + 3 {
+            spaces.switch(n << 12);
+        }
+
+        assert_eq!(spaces.list().len(), SPACE_MAX);
+        assert_eq!(spaces.untracked, 2);
+    }
+
+    #[test]
+    fn filters() {
+        let mut spaces: AddressSpaces = unsafe { mem::zeroed() };
+
+        assert!(spaces.add_filter(filter(0x1000, Cr3Action::Stop)));
+        assert!(spaces.add_filter(filter(0x2000, Cr3Action::Ignore)));
+        assert_eq!(spaces.filter(0x1000), Some(Cr3Action::Stop));
+        assert_eq!(spaces.filter(0x2000), Some(Cr3Action::Ignore));
+        assert!(spaces.filter(0x3000).is_none());
+
+        // replaced, not duplicated
+        assert!(spaces.add_filter(filter(0x1000, Cr3Action::Ignore)));
+        assert_eq!(spaces.filter(0x1000), Some(Cr3Action::Ignore));
+        assert_eq!(spaces.filters().len(), 2);
+
+        assert_eq!(spaces.del_filter(0x2000).map(|f| f.action), Some(Cr3Action::Ignore));
+        assert!(spaces.del_filter(0x2000).is_none());
+        assert_eq!(spaces.filters().len(), 1);
+    }
+
+    #[test]
+    fn filters_full() {
+        let mut spaces: AddressSpaces = unsafe { mem::zeroed() };
+
+        for n in 0..CR3_FILTER_MAX as u64 {
+            assert!(spaces.add_filter(filter(n << 12, Cr3Action::Stop)));
+        }
+
+        assert!(!spaces.add_filter(filter(0x100000, Cr3Action::Stop)));
+        // an existing one can still change
+        assert!(spaces.add_filter(filter(0, Cr3Action::Ignore)));
+        assert_eq!(spaces.filter(0), Some(Cr3Action::Ignore));
+    }
+}
@@ -4,7 +4,7 @@ use x86_64::instructions::port;
 #[repr(u16)]
 pub enum SerialPort {
     Com1 = 0x3f8,
-    // Com2 = 0x2f8,
+    Com2 = 0x2f8,
     // Com3 = 0x3e8,
     // Com4 = 0x2e8,
 }
@@ -91,6 +91,7 @@ pub mod SerialLsr {
 impl Serial {
     fn reg_lsr(&self)  -> u16 { self.base + 5 }
     fn reg_tx(&self)   -> u16 { self.base }
+    fn reg_rx(&self)   -> u16 { self.base }
 
     // i/o port access (unsafe)
     fn write_reg(&self, reg: u16, val: u8) {
@@ -117,9 +118,37 @@ impl Serial {
         false
     }
 
+    fn can_recv(&self) -> bool {
+        let raw = self.read_reg(self.reg_lsr());
+        let lsr = SerialLsr::Flags::from_bits_truncate(raw);
+
+        lsr.contains(SerialLsr::DATA)
+    }
+
+    pub fn send(&self, byte: u8) {
+        while ! self.write_byte(byte) {}
+    }
+
     pub fn write(&self, s: &str) {
         for byte in s.bytes() {
-            while ! self.write_byte(byte) {}
+            self.send(byte);
+        }
+    }
+
+    // Non blocking receive
+    pub fn read_byte(&self) -> Option<u8> {
+        if self.can_recv() {
+            return Some(self.read_reg(self.reg_rx()))
+        }
+
+        None
+    }
+
+    pub fn recv(&self) -> u8 {
+        loop {
+            if let Some(byte) = self.read_byte() {
+                return byte
+            }
         }
     }
 }
@@ -54,6 +54,11 @@ impl Raw64 {
         self.0 &= 0xffffffff00000000;
         self.0 |= v as u64;
     }
+
+    pub fn update_u16(&mut self, v: u16) {
+        self.0 &= 0xffffffffffff0000;
+        self.0 |= v as u64;
+    }
 }
 
 use core::fmt;
@@ -74,6 +79,7 @@ impl RawValue for Raw32 {
 }
 
 impl Raw32 {
+    pub fn as_u32(&self) -> u32 { self.0 }
     pub fn as_u16(&self) -> u16 { self.0 as u16 }
 }
 
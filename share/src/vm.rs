@@ -1,13 +1,46 @@
 use cpu;
 use smap;
+use port;
+use breakpoint;
+use space;
+use gdb;
 use vmx::vmcs;
+use vmx::ept;
 use vmx::ept::map as eptmap;
 use paging::ptb as pgptb;
 
+// Guest triple fault and INIT handling
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ResetPolicy {
+    Reboot,     // INT19 boot entry
+    PowerOn,    // architectural reset vector
+    Halt,       // dump the VMCS and park the guest
+    WaitSipi,   // park the guest until a SIPI
+}
+
+// Guest MONITOR/MWAIT handling
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MwaitPolicy {
+    Ud,         // raise #UD, hidden from CPUID
+    Emulate,    // MONITOR is a nop, MWAIT behaves as HLT
+}
+
+// Lives in the VMM area, zeroed at setup: but for what setup fills
+// in, every field must be valid when zeroed (empty lists, None
+// options, first enum variants).
 pub struct VM {
     pub cpu:  cpu::VirtualCPU,
     pub smap: smap::SystemMap,
     pub vmc:  &'static mut vmcs::VmHardwareVMCS,
     pub vmcs: vmcs::VMCS,
     pub pg:   pgptb::PagingEnv<'static, eptmap::PML4>,
+    pub ept:  ept::EPTRegions,
+    pub kbc:  port::KbcState,
+    pub brk:  breakpoint::Breakpoints,
+    pub wp:   breakpoint::Watchpoints,
+    pub spaces: space::AddressSpaces,
+    pub gdb:  gdb::GdbState,
+    pub reset: ResetPolicy,
+    pub mwait: MwaitPolicy,
+    pub native_hlt: bool, // VMM halts instead of the guest
 }
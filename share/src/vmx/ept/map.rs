@@ -8,6 +8,7 @@ use mmap::*;
 use cpu::CPUSkillz;
 use pool::PagePool;
 use info::info_data;
+use paging::walk::Translation;
 
 ///////////////// VMX EPT implementation of Page Table Traits
 
@@ -215,3 +216,70 @@ pub fn init() {
 
     info.vm.pg.unmap(info.hwmm.area.start, info.hwmm.area.end, &pgconf, pool);
 }
+
+// Guest physical to system physical translation
+//
+// Leaf entries without permissions (ie. protected pages) still
+// provide their page frame.
+impl<'a> PagingEnv<'a, PML4> {
+    // Final entry mapping gpa and its level shift
+    pub fn lookup(&self, gpa: u64) -> Option<(u64, usize)> {
+        let l4 = self.root.at(gpa);
+        if !PTBEntry::present(l4) {
+            return None
+        }
+
+        let l3 = l4.as_table().at(gpa);
+        if l3.is_page() {
+            return Some((l3.raw(), l3.shift()))
+        } else if !PTBEntry::present(l3) {
+            return None
+        }
+
+        let l2 = l3.as_table().at(gpa);
+        if l2.is_page() {
+            return Some((l2.raw(), l2.shift()))
+        } else if !PTBEntry::present(l2) {
+            return None
+        }
+
+        let l1 = l2.as_table().at(gpa);
+        if l1.raw() == 0 {
+            return None
+        }
+
+        Some((l1.raw(), l1.shift()))
+    }
+
+    pub fn translate(&self, gpa: u64) -> Option<Translation> {
+        self.lookup(gpa).map(|(entry, shift)| Translation {
+            paddr: (entry & addr_mask(shift)) + pg_offset(shift, gpa),
+            shift: shift,
+        })
+    }
+
+    // Show entries involved in gpa translation
+    pub fn dump(&self, gpa: u64) {
+        let l4 = self.root.at(gpa);
+        log!("EPT gpa {:#x}\n  pml4e @{:#x} {:#x}\n"
+             ,gpa, l4 as *const _ as u64, l4.raw());
+        if !PTBEntry::present(l4) {
+            return
+        }
+
+        let l3 = l4.as_table().at(gpa);
+        log!("  pdpe  @{:#x} {:#x}\n", l3 as *const _ as u64, l3.raw());
+        if l3.is_page() || !PTBEntry::present(l3) {
+            return
+        }
+
+        let l2 = l3.as_table().at(gpa);
+        log!("  pde   @{:#x} {:#x}\n", l2 as *const _ as u64, l2.raw());
+        if l2.is_page() || !PTBEntry::present(l2) {
+            return
+        }
+
+        let l1 = l2.as_table().at(gpa);
+        log!("  pte   @{:#x} {:#x}\n", l1 as *const _ as u64, l1.raw());
+    }
+}
@@ -2,9 +2,16 @@ pub mod map;
 
 use utils;
 use utils::RawValue;
-use info::InformationData;
+use info::{InformationData, info_data};
+use mmap::PageMapper;
+use paging::ptb::{PagingConfig, PG_OP_PVL, PG_OP_ADDR, PG_OP_OFF};
+use rmode;
+use paging::utils::*;
+use vmx::insn::invept;
+use vmx::vmcs::access::Access;
 
 // VPID Invalidation
+#[derive(Debug, Copy, Clone)]
 pub enum VPID_INV_TYPE {
     Addr = 0,
     SingleAll = 1,
@@ -18,6 +25,13 @@ impl Default for VPID_INV_TYPE {
     }
 }
 
+// EPT Invalidation
+#[derive(Debug, Copy, Clone)]
+pub enum EPT_INV_TYPE {
+    Single = 1,
+    All = 2,
+}
+
 
 // privilege
 pub const PVL_R:   u64 = 1;
@@ -64,3 +78,157 @@ impl utils::RawValue for EPTP {
     fn as_u64(&self) -> u64 { self.0 }
     fn update_u64(&mut self, v: u64) { self.0 = v; }
 }
+
+
+// Guest physical page permissions
+#[derive(Debug, Copy, Clone)]
+pub struct EPTPage {
+    pub addr:  u64,   // system physical page frame
+    pub shift: usize, // page size
+    pub pvl:   u64,
+    pub mmt:   u64,
+}
+
+pub fn query(gpa: u64) -> Option<EPTPage> {
+    let info = info_data();
+
+    info.vm.pg.lookup(gpa).map(|(entry, shift)| EPTPage {
+        addr:  entry & addr_mask(shift),
+        shift: shift,
+        pvl:   entry & attr_pvl_msk(),
+        mmt:  (entry & attr_mmt_msk()) >> 3,
+    })
+}
+
+// Change permissions of [start, end[, splitting large pages
+// partially covered. Range must be mapped.
+pub fn protect(start: u64, end: u64, pvl: u64) {
+    let info = info_data();
+    let mut pgconf = PagingConfig::for_vm(info);
+    let pool = &mut info.vmm.pool;
+
+    pgconf.modifier = PG_OP_PVL;
+    pgconf.pg_attr  = pvl & attr_pvl_msk();
+
+    let s = pg_align(PG_4K_SHIFT, start);
+    let e = pg_align_next(PG_4K_SHIFT, end);
+
+    info.vm.pg.remap(s, e, &pgconf, pool);
+
+    let eptp = info.vm.vmcs.ctrl.exec.eptp.as_ref().as_u64();
+    invept(EPT_INV_TYPE::Single as u64, eptp);
+}
+
+// Register a region and apply its permissions
+pub fn track(region: EPTRegion) -> bool {
+    let info = info_data();
+
+    if !info.vm.ept.add(region) {
+        return false
+    }
+
+    protect(region.start, region.end, region.pvl);
+    true
+}
+
+// A20 off: mirror [1MB ; 1MB+64KB [ to [ 0 ; 64KB [
+pub fn a20(enable: bool) {
+    let info = info_data();
+    let mut pgconf = PagingConfig::for_vm(info);
+    let pool = &mut info.vmm.pool;
+
+    pgconf.modifier = PG_OP_ADDR;
+
+    if !enable {
+        pgconf.modifier |= PG_OP_OFF;
+        pgconf.offset = 0u64.wrapping_sub(rmode::LIMIT);
+    }
+
+    info.vm.pg.remap(rmode::LIMIT, rmode::WRAP_LIMIT, &pgconf, pool);
+
+    let eptp = info.vm.vmcs.ctrl.exec.eptp.as_ref().as_u64();
+    invept(EPT_INV_TYPE::Single as u64, eptp);
+}
+
+// Guest physical regions with specific access policies
+pub const EPT_MAX_REGIONS: usize = 16;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum EPTRegionKind {
+    MMIO,       // emulated device memory
+    WriteTrack, // report writes
+    ExecTrack,  // report instruction fetches
+    WriteWatch, // pages covering write watchpoints
+    AccessWatch,// pages covering read/write watchpoints
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct EPTRegion {
+    pub start: u64,
+    pub end:   u64,
+    pub kind:  EPTRegionKind,
+    pub pvl:   u64, // permissions while tracked
+}
+
+impl EPTRegion {
+    pub fn new(start: u64, end: u64, kind: EPTRegionKind) -> EPTRegion {
+        let pvl = match kind {
+            EPTRegionKind::MMIO       => 0,
+            EPTRegionKind::WriteTrack => PVL_R|PVL_X,
+            EPTRegionKind::ExecTrack  => PVL_R|PVL_W,
+            EPTRegionKind::WriteWatch => PVL_R|PVL_X,
+            // write-only and execute-only are not always available
+            EPTRegionKind::AccessWatch => 0,
+        };
+
+        EPTRegion { start: start, end: end, kind: kind, pvl: pvl }
+    }
+
+    pub fn has(&self, gpa: u64) -> bool {
+        gpa >= self.start && gpa < self.end
+    }
+}
+
+pub struct EPTRegions {
+    count:   usize,
+    regions: [EPTRegion;EPT_MAX_REGIONS],
+}
+
+impl EPTRegions {
+    pub fn regions(&self) -> &[EPTRegion] { &self.regions[..self.count] }
+
+    pub fn add(&mut self, region: EPTRegion) -> bool {
+        if self.count >= EPT_MAX_REGIONS {
+            return false
+        }
+
+        self.regions[self.count] = region;
+        self.count += 1;
+        true
+    }
+
+    pub fn del(&mut self, start: u64, end: u64, kind: EPTRegionKind) -> Option<EPTRegion> {
+        for i in 0..self.count {
+            let r = &self.regions[i];
+
+            if r.start == start && r.end == end && r.kind == kind {
+                let region = self.regions[i];
+                self.count -= 1;
+                self.regions[i] = self.regions[self.count];
+                return Some(region)
+            }
+        }
+
+        None
+    }
+
+    pub fn find(&self, gpa: u64) -> Option<EPTRegion> {
+        self.regions().iter().find(|r| r.has(gpa)).map(|r| *r)
+    }
+
+    // Permissions of overlapping regions add up
+    pub fn pvl(&self, gpa: u64) -> Option<u64> {
+        self.regions().iter().filter(|r| r.has(gpa))
+            .fold(None, |pvl, r| Some(pvl.unwrap_or(PVL_RWX) & r.pvl))
+    }
+}
@@ -0,0 +1,123 @@
+// Events waiting for the guest to accept them
+//
+// External interrupts are kept in arrival order, a vector already
+// pending is not queued twice (as the local APIC IRR would do). At
+// most one NMI can be pending.
+
+pub const EVENT_QUEUE_LEN: usize = 32;
+
+pub struct EventQueue {
+    vectors: [u8; EVENT_QUEUE_LEN],
+    head: usize,
+    count: usize,
+    pub nmi: bool,
+}
+
+impl EventQueue {
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    fn contains(&self, vector: u8) -> bool {
+        (0..self.count).any(|i| self.vectors[(self.head + i) % EVENT_QUEUE_LEN] == vector)
+    }
+
+    // Returns false when the queue is full
+    pub fn push(&mut self, vector: u8) -> bool {
+        if self.contains(vector) {
+            return true
+        }
+
+        if self.count == EVENT_QUEUE_LEN {
+            return false
+        }
+
+        self.vectors[(self.head + self.count) % EVENT_QUEUE_LEN] = vector;
+        self.count += 1;
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.head  = 0;
+        self.count = 0;
+        self.nmi   = false;
+    }
+
+    pub fn peek(&self) -> Option<u8> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.vectors[self.head])
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<u8> {
+        let vector = self.peek();
+
+        if vector.is_some() {
+            self.head = (self.head + 1) % EVENT_QUEUE_LEN;
+            self.count -= 1;
+        }
+
+        vector
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem;
+
+    #[test]
+    fn duplicates() {
+        let mut queue: EventQueue = unsafe { mem::zeroed() };
+
+        assert!(queue.is_empty());
+        assert!(queue.push(0x20));
+        assert!(queue.push(0x21));
+        assert!(queue.push(0x20));
+        assert_eq!(queue.pop(), Some(0x20));
+        assert_eq!(queue.pop(), Some(0x21));
+        assert_eq!(queue.pop(), None);
+
+        // vector delivered, it can be queued again
+        assert!(queue.push(0x20));
+        assert_eq!(queue.peek(), Some(0x20));
+    }
+
+    #[test]
+    fn wrap_around() {
+        let mut queue: EventQueue = unsafe { mem::zeroed() };
+
+        for round in 0..3 {
+            for v in 0..EVENT_QUEUE_LEN - 1 {
+                assert!(queue.push((round + v) as u8));
+            }
+            for v in 0..EVENT_QUEUE_LEN - 1 {
+                assert_eq!(queue.pop(), Some((round + v) as u8));
+            }
+        }
+
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn full() {
+        let mut queue: EventQueue = unsafe { mem::zeroed() };
+
+        for v in 0..EVENT_QUEUE_LEN {
+            assert!(queue.push(0x20 + v as u8));
+        }
+
+        assert!(!queue.push(0x80));
+        // already pending, nothing to store
+        assert!(queue.push(0x20));
+
+        assert_eq!(queue.pop(), Some(0x20));
+        assert!(queue.push(0x80));
+
+        queue.nmi = true;
+        queue.clear();
+        assert!(queue.is_empty() && !queue.nmi);
+    }
+}
@@ -5,6 +5,15 @@ extern {
     fn __vmx_vmload(err: *mut u64, vmcs: *const u64) -> u8;
     fn __vmx_vmread(err: *mut u64, val: *mut u64, enc: u64) -> u8;
     fn __vmx_vmwrite(err: *mut u64, val: u64, enc: u64) -> u8;
+    fn __vmx_invvpid(err: *mut u64, kind: u64, desc: *const InvDesc) -> u8;
+    fn __vmx_invept(err: *mut u64, kind: u64, desc: *const InvDesc) -> u8;
+}
+
+// INVVPID/INVEPT 128 bits descriptor
+#[repr(C, packed)]
+struct InvDesc {
+    low:  u64,
+    high: u64,
 }
 
 pub fn vmxon(vmcs: u64) {
@@ -69,3 +78,23 @@ pub fn vmwrite(val: u64, enc: u64) {
         panic!("vmwrite(0x{:x}, 0x{:x}) err {}", enc, val, err);
     }
 }
+
+pub fn invvpid(kind: u64, vpid: u16, addr: u64) {
+    let mut err: u64 = 0;
+    let perr = &mut err as *mut _;
+    let desc = InvDesc { low: vpid as u64, high: addr };
+
+    if unsafe { __vmx_invvpid(perr, kind, &desc as *const _) } == 0 {
+        panic!("invvpid({}, {}, 0x{:x}) err {}", kind, vpid, addr, err);
+    }
+}
+
+pub fn invept(kind: u64, eptp: u64) {
+    let mut err: u64 = 0;
+    let perr = &mut err as *mut _;
+    let desc = InvDesc { low: eptp, high: 0 };
+
+    if unsafe { __vmx_invept(perr, kind, &desc as *const _) } == 0 {
+        panic!("invept({}, 0x{:x}) err {}", kind, eptp, err);
+    }
+}
@@ -3,6 +3,8 @@ pub mod insn;
 pub mod regs;
 pub mod vmcs;
 pub mod ept;
+pub mod event;
+pub mod step;
 
 pub enum ACTIVITY_STATE {
     Active = 0,
@@ -206,9 +206,9 @@ bitfield!{
 
     impl Debug;
 
-    pub eint,_:0;
-    pub nmi,_:3;
-    pub vnmi,_:5;
+    pub eint,set_eint:0;
+    pub nmi,set_nmi:3;
+    pub vnmi,set_vnmi:5;
     pub preempt,_:6;
     pub pint,_:7;
 }
@@ -219,11 +219,11 @@ bitfield!{
 
     impl Debug;
 
-    pub iwe,_:2;
+    pub iwe,set_iwe:2;
     pub tsc,set_tsc:3;
-    pub hlt,_:7;
+    pub hlt,set_hlt:7;
     pub invl,_:9;
-    pub mwait,_:10;
+    pub mwait,set_mwait:10;
     pub rdpmc,_:11;
     pub rdtsc,_:12;
     pub cr3l,set_cr3l:15;
@@ -231,13 +231,13 @@ bitfield!{
     pub cr8l,_:19;
     pub cr8s,_:20;
     pub tprs,_:21;
-    pub nwe,_:22;
-    pub mdr,_:23;
+    pub nwe,set_nwe:22;
+    pub mdr,set_mdr:23;
     pub ucio,_:24;
     pub usio,set_usio:25;
-    pub mtf,_:27;
+    pub mtf,set_mtf:27;
     pub umsr,set_umsr:28;
-    pub mon,_:29;
+    pub mon,set_mon:29;
     pub pause,_:30;
     pub proc2,set_proc2:31;
 }
@@ -279,7 +279,7 @@ bitfield!{
     impl Debug;
 
     pub load_dbgctl,set_load_dbgctl:2;
-    pub ia32e,_:9;
+    pub ia32e,set_ia32e:9;
     pub smm,_:10;
     pub dual,_:11;
     pub load_ia32_perf,set_load_ia32_perf:13;
@@ -358,11 +358,118 @@ bitfield!{
 
     impl Debug;
 
-    pub u8, vector,_:7,0;
-    pub u8, kind,_:10,8;
-    pub v_err,_:11;
+    pub u8, vector,set_vector:7,0;
+    pub u8, kind,set_kind:10,8;
+    pub v_err,set_v_err:11;
     pub nmi,_:12; // undefined for Exit Idt Vector, Entry Vector
-    pub v,_:31;
+    pub v,set_v:31;
+}
+
+// Guest interruptibility state
+bitfield!{
+    #[derive(Default, Copy, Clone)]
+    pub struct Interruptibility(u32);
+
+    impl Debug;
+
+    pub sti,set_sti:0;
+    pub mov_ss,set_mov_ss:1;
+    pub smi,_:2;
+    pub nmi,set_nmi:3;
+    pub enclave,_:4;
+}
+
+// Control-register access exit qualification
+#[derive(Debug,Copy,Clone)]
+pub enum CRAccessType {
+    MovToCr,
+    MovFromCr,
+    Clts,
+    Lmsw,
+}
+
+impl TryFrom<u8> for CRAccessType {
+    type Error = u8;
+    fn try_from(kind: u8) -> Result<Self, Self::Error> {
+        match kind {
+            0 => Ok(CRAccessType::MovToCr),
+            1 => Ok(CRAccessType::MovFromCr),
+            2 => Ok(CRAccessType::Clts),
+            3 => Ok(CRAccessType::Lmsw),
+            n => Err(n),
+        }
+    }
+}
+
+bitfield!{
+    #[derive(Default, Copy, Clone)]
+    pub struct ExitQualCR(u64);
+
+    impl Debug;
+
+    pub u8, nr,_:3,0;
+    pub u8, access,_:5,4;
+    pub lmsw_mem,_:6;
+    pub u8, gpr,_:11,8;
+    pub u16, lmsw_src,_:31,16;
+}
+
+// Debug register access exit qualification
+bitfield!{
+    #[derive(Default, Copy, Clone)]
+    pub struct ExitQualDR(u64);
+
+    impl Debug;
+
+    pub u8, nr,_:2,0;
+    pub read,_:4; // mov from DR
+    pub u8, gpr,_:11,8;
+}
+
+// I/O instruction exit qualification
+bitfield!{
+    #[derive(Default, Copy, Clone)]
+    pub struct ExitQualIO(u64);
+
+    impl Debug;
+
+    pub u8, size,_:2,0; // 0: 1 byte, 1: 2 bytes, 3: 4 bytes
+    pub input,_:3;
+    pub string,_:4;
+    pub rep,_:5;
+    pub imm,_:6;
+    pub u16, port,_:31,16;
+}
+
+impl ExitQualIO {
+    pub fn bytes(&self) -> usize { self.size() as usize + 1 }
+}
+
+// INS/OUTS exit instruction information
+bitfield!{
+    #[derive(Default, Copy, Clone)]
+    pub struct ExitInsnInfoIO(u32);
+
+    impl Debug;
+
+    pub u8, addr,_:9,7; // 0: 16 bits, 1: 32 bits, 2: 64 bits
+    pub u8, seg,_:17,15;
+}
+
+// EPT violation exit qualification
+bitfield!{
+    #[derive(Default, Copy, Clone)]
+    pub struct ExitQualEPT(u64);
+
+    impl Debug;
+
+    pub r,_:0;        // access attempted
+    pub w,_:1;
+    pub x,_:2;
+    pub u8, pvl,_:5,3; // entry permissions
+    pub gla_valid,_:7;
+    pub gla_xlat,_:8;  // final translation (not a guest page walk)
+    pub nmi_unblk,_:12;
 }
 
 
@@ -440,6 +547,12 @@ impl utils::RawValue for ExitInfoInterrupt {
     fn update_u64(&mut self, v: u64) { self.0 = v as u32; }
 }
 
+impl utils::RawValue for Interruptibility {
+    fn from_u32(x: u32) -> Interruptibility { Interruptibility(x) }
+    fn as_u64(&self) -> u64 { self.0 as u64 }
+    fn update_u64(&mut self, v: u64) { self.0 = v as u32; }
+}
+
 impl utils::RawValue for SegAttr {
     fn from_u32(x: u32) -> SegAttr { SegAttr(x) }
     fn as_u64(&self) -> u64 { self.0 as u64 }
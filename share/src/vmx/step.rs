@@ -0,0 +1,87 @@
+// Monitor trap flag single-stepping state
+//
+// The debugger and the EPT tracking (step over an instruction
+// with relaxed permissions) share the monitor trap flag. A single
+// instruction may fault on several tracked pages before the MTF
+// exit, each one stays relaxed until then.
+
+use paging::utils::{pg_align, PG_4K_SHIFT};
+
+pub const RELAXED_MAX: usize = 8;
+
+pub struct StepState {
+    pub user:   bool,  // debugger single-step
+    pub quiet:  bool,  // no event injection while stepping
+    nr_relaxed: usize,
+    relaxed:    [u64;RELAXED_MAX], // pages relaxed for EPT step-over
+}
+
+impl StepState {
+    pub fn active(&self) -> bool {
+        self.user || self.nr_relaxed != 0
+    }
+
+    pub fn relaxed(&self) -> &[u64] { &self.relaxed[..self.nr_relaxed] }
+
+    // Page of gpa is relaxed until the MTF exit
+    pub fn relax(&mut self, gpa: u64) -> bool {
+        let page = pg_align(PG_4K_SHIFT, gpa);
+
+        if self.relaxed().contains(&page) {
+            return true
+        }
+
+        if self.nr_relaxed >= RELAXED_MAX {
+            return false
+        }
+
+        self.relaxed[self.nr_relaxed] = page;
+        self.nr_relaxed += 1;
+        true
+    }
+
+    pub fn unrelax(&mut self) -> Option<u64> {
+        if self.nr_relaxed == 0 {
+            return None
+        }
+
+        self.nr_relaxed -= 1;
+        Some(self.relaxed[self.nr_relaxed])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem;
+
+    #[test]
+    fn relaxed_pages() {
+        let mut step: StepState = unsafe { mem::zeroed() };
+
+        assert!(!step.active());
+        assert!(step.relax(0x1234));
+        assert!(step.relax(0x1ff8));
+        assert!(step.relax(0x5000));
+        assert!(step.active());
+        assert_eq!(step.relaxed(), &[0x1000, 0x5000]);
+
+        assert_eq!(step.unrelax(), Some(0x5000));
+        assert_eq!(step.unrelax(), Some(0x1000));
+        assert_eq!(step.unrelax(), None);
+        assert!(!step.active());
+    }
+
+    #[test]
+    fn relaxed_full() {
+        let mut step: StepState = unsafe { mem::zeroed() };
+
+        for n in 0..RELAXED_MAX {
+            assert!(step.relax((n as u64) << 12));
+        }
+
+        assert!(!step.relax(0x100000));
+        assert!(step.relax(0));
+        assert_eq!(step.relaxed().len(), RELAXED_MAX);
+    }
+}
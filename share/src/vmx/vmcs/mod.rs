@@ -96,7 +96,7 @@ pub struct Guest {
 
     // Non register
     pub activity: Field<utils::Raw32>,
-    pub interrupt: Field<utils::Raw32>,
+    pub interrupt: Field<Interruptibility>,
     pub pending_dbg: Field<utils::Raw64>,
     pub vmcs_link_ptr: Field<utils::Raw64>,
     pub preempt_timer: Field<utils::Raw32>,
@@ -262,13 +262,173 @@ pub struct Bitmap([u8;pgutils::PG_4KB]);
 impl Bitmap {
     pub fn get_addr(&self) -> u64 { &self.0 as *const _ as u64 }
 
-    // XXX: implement IO/MSR bitmaps settings
-    pub fn deny(&mut self, index: u32) {
-        log!("IO/MSR bitmap deny no implemented !\n");
+    pub fn set(&mut self, bit: usize) {
+        self.0[bit/8] |= 1<<(bit%8);
     }
 
-    pub fn allow(&mut self, index: u32) {
-        log!("IO/MSR bitmap allow no implemented !\n");
+    pub fn clear(&mut self, bit: usize) {
+        self.0[bit/8] &= !(1<<(bit%8));
+    }
+
+    pub fn test(&self, bit: usize) -> bool {
+        self.0[bit/8] & (1<<(bit%8)) != 0
+    }
+}
+
+// I/O bitmaps: one bit per port, set bit means vm-exit
+#[repr(C, packed)]
+pub struct IoBitmap {
+    pub a: Bitmap, // ports 0x0000-0x7fff
+    pub b: Bitmap, // ports 0x8000-0xffff
+}
+
+const IO_BITMAP_PORTS: usize = pgutils::PG_4KB * 8;
+
+impl IoBitmap {
+    // Bitmap (B or not) and bit position of a port
+    fn index(port: u16) -> (bool, usize) {
+        let port = port as usize;
+        if port < IO_BITMAP_PORTS {
+            (false, port)
+        } else {
+            (true, port - IO_BITMAP_PORTS)
+        }
+    }
+
+    fn locate(&mut self, port: u16) -> (&mut Bitmap, usize) {
+        match IoBitmap::index(port) {
+            (false, bit) => (&mut self.a, bit),
+            (true,  bit) => (&mut self.b, bit),
+        }
+    }
+
+    pub fn deny(&mut self, port: u16) {
+        let (map, bit) = self.locate(port);
+        map.set(bit);
+    }
+
+    pub fn allow(&mut self, port: u16) {
+        let (map, bit) = self.locate(port);
+        map.clear(bit);
+    }
+
+    pub fn denied(&self, port: u16) -> bool {
+        match IoBitmap::index(port) {
+            (false, bit) => self.a.test(bit),
+            (true,  bit) => self.b.test(bit),
+        }
+    }
+
+    // Ranges are inclusive
+    pub fn deny_range(&mut self, start: u16, end: u16) {
+        for port in start..end {
+            self.deny(port);
+        }
+        self.deny(end);
+    }
+
+    pub fn allow_range(&mut self, start: u16, end: u16) {
+        for port in start..end {
+            self.allow(port);
+        }
+        self.allow(end);
+    }
+}
+
+// MSR bitmap: read low, read high, write low, write high 1KB quadrants
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MsrAccess {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+pub const MSR_BITMAP_LOW_START:  u32 = 0x00000000;
+pub const MSR_BITMAP_LOW_END:    u32 = 0x00001fff;
+pub const MSR_BITMAP_HIGH_START: u32 = 0xc0000000;
+pub const MSR_BITMAP_HIGH_END:   u32 = 0xc0001fff;
+
+const MSR_BITMAP_READ_LOW:   usize = 0;
+const MSR_BITMAP_READ_HIGH:  usize = 1024*8;
+const MSR_BITMAP_WRITE_LOW:  usize = 2048*8;
+const MSR_BITMAP_WRITE_HIGH: usize = 3072*8;
+
+#[repr(C, packed)]
+pub struct MsrBitmap(Bitmap);
+
+impl MsrBitmap {
+    pub fn get_addr(&self) -> u64 { self.0.get_addr() }
+
+    // Bit positions for read and write, None if the MSR is not
+    // covered by the bitmap (ie. always intercepted)
+    fn locate(msr: u32) -> Option<(usize, usize)> {
+        if msr <= MSR_BITMAP_LOW_END {
+            let idx = (msr - MSR_BITMAP_LOW_START) as usize;
+            Some((MSR_BITMAP_READ_LOW + idx, MSR_BITMAP_WRITE_LOW + idx))
+        } else if msr >= MSR_BITMAP_HIGH_START && msr <= MSR_BITMAP_HIGH_END {
+            let idx = (msr - MSR_BITMAP_HIGH_START) as usize;
+            Some((MSR_BITMAP_READ_HIGH + idx, MSR_BITMAP_WRITE_HIGH + idx))
+        } else {
+            None
+        }
+    }
+
+    fn update(&mut self, msr: u32, access: MsrAccess, deny: bool) -> bool {
+        let (rd, wr) = match MsrBitmap::locate(msr) {
+            Some(pos) => pos,
+            None => return false,
+        };
+
+        let bits = match access {
+            MsrAccess::Read      => [Some(rd), None],
+            MsrAccess::Write     => [None, Some(wr)],
+            MsrAccess::ReadWrite => [Some(rd), Some(wr)],
+        };
+
+        for bit in bits.iter() {
+            if let Some(bit) = *bit {
+                if deny { self.0.set(bit) } else { self.0.clear(bit) }
+            }
+        }
+
+        true
+    }
+
+    // Return false if the MSR is outside of the bitmap
+    pub fn deny(&mut self, msr: u32, access: MsrAccess) -> bool {
+        self.update(msr, access, true)
+    }
+
+    pub fn allow(&mut self, msr: u32, access: MsrAccess) -> bool {
+        self.update(msr, access, false)
+    }
+
+    pub fn denied(&self, msr: u32, access: MsrAccess) -> bool {
+        match MsrBitmap::locate(msr) {
+            None => true,
+            Some((rd, wr)) => match access {
+                MsrAccess::Read      => self.0.test(rd),
+                MsrAccess::Write     => self.0.test(wr),
+                MsrAccess::ReadWrite => self.0.test(rd) && self.0.test(wr),
+            },
+        }
+    }
+
+    // Ranges are inclusive
+    pub fn deny_range(&mut self, start: u32, end: u32, access: MsrAccess) -> bool {
+        let mut rc = true;
+        for msr in start..end {
+            rc &= self.deny(msr, access);
+        }
+        rc & self.deny(end, access)
+    }
+
+    pub fn allow_range(&mut self, start: u32, end: u32, access: MsrAccess) -> bool {
+        let mut rc = true;
+        for msr in start..end {
+            rc &= self.allow(msr, access);
+        }
+        rc & self.allow(end, access)
     }
 }
 
@@ -291,12 +451,115 @@ impl CtlMSRArea {
 pub struct VmHardwareVMCS {
     // 4KB aligned
     pub region:  Region,
-    pub ioA_map: Bitmap,
-    pub ioB_map: Bitmap,
-    pub msr_map: Bitmap,
+    pub io_map:  IoBitmap,
+    pub msr_map: MsrBitmap,
 
     // 16 bytes aligned
     pub exit_store: CtlMSRArea,
     pub exit_load: CtlMSRArea,
     pub entry_load: CtlMSRArea,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::mem;
+
+    fn io_map() -> Box<IoBitmap> { Box::new(unsafe { mem::zeroed() }) }
+    fn msr_map() -> Box<MsrBitmap> { Box::new(unsafe { mem::zeroed() }) }
+
+    // only the expected bit is set
+    fn only(map: &Bitmap, byte: usize, bit: u8) -> bool {
+        map.0.iter().enumerate().all(|(i, b)| *b == if i == byte { 1<<bit } else { 0 })
+    }
+
+    fn empty(map: &Bitmap) -> bool {
+        map.0.iter().all(|b| *b == 0)
+    }
+
+    #[test]
+    fn io_ports() {
+        for &(port, high, byte, bit) in [(0x0000, false, 0x000, 0),
+                                         (0x7fff, false, 0xfff, 7),
+                                         (0x8000, true,  0x000, 0),
+                                         (0xffff, true,  0xfff, 7)].iter() {
+            let mut map = io_map();
+
+            map.deny(port);
+            assert!(map.denied(port));
+
+            if high {
+                assert!(only(&map.b, byte, bit) && empty(&map.a), "port {:#x}", port);
+            } else {
+                assert!(only(&map.a, byte, bit) && empty(&map.b), "port {:#x}", port);
+            }
+
+            map.allow(port);
+            assert!(!map.denied(port));
+            assert!(empty(&map.a) && empty(&map.b));
+        }
+    }
+
+    #[test]
+    fn io_range() {
+        let mut map = io_map();
+
+        map.deny_range(0x7ffe, 0x8001);
+        assert!(!map.denied(0x7ffd));
+        assert!((0x7ffe..0x8002).all(|p| map.denied(p)));
+        assert!(!map.denied(0x8002));
+
+        map.allow_range(0x7ffe, 0x8001);
+        assert!(empty(&map.a) && empty(&map.b));
+    }
+
+    #[test]
+    fn msr_quadrants() {
+        let cases = [(MSR_BITMAP_LOW_START,     MsrAccess::Read,  0,        0),
+                     (MSR_BITMAP_LOW_END,       MsrAccess::Read,  1023,     7),
+                     (MSR_BITMAP_HIGH_START,    MsrAccess::Read,  1024,     0),
+                     (MSR_BITMAP_HIGH_END,      MsrAccess::Read,  2047,     7),
+                     (MSR_BITMAP_LOW_START,     MsrAccess::Write, 2048,     0),
+                     (msr::IA32_EFER & 0x1fff,  MsrAccess::Write, 2048+0x10, 0),
+                     (MSR_BITMAP_HIGH_START,    MsrAccess::Write, 3072,     0),
+                     (msr::IA32_EFER,           MsrAccess::Write, 3072+0x10, 0)];
+
+        for &(msr, access, byte, bit) in cases.iter() {
+            let mut map = msr_map();
+
+            assert!(map.deny(msr, access));
+            assert!(map.denied(msr, access));
+            assert!(only(&map.0, byte, bit), "msr {:#x} {:?}", msr, access);
+
+            assert!(map.allow(msr, access));
+            assert!(!map.denied(msr, access));
+            assert!(empty(&map.0));
+        }
+    }
+
+    #[test]
+    fn msr_read_write() {
+        let mut map = msr_map();
+
+        assert!(map.deny(msr::IA32_EFER, MsrAccess::ReadWrite));
+        assert!(map.denied(msr::IA32_EFER, MsrAccess::Read));
+        assert!(map.denied(msr::IA32_EFER, MsrAccess::Write));
+
+        assert!(map.allow(msr::IA32_EFER, MsrAccess::Read));
+        assert!(!map.denied(msr::IA32_EFER, MsrAccess::ReadWrite));
+        assert!(only(&map.0, 3072+0x10, 0));
+    }
+
+    #[test]
+    fn msr_outside() {
+        let mut map = msr_map();
+
+        for &msr in [MSR_BITMAP_LOW_END+1, MSR_BITMAP_HIGH_START-1, MSR_BITMAP_HIGH_END+1].iter() {
+            assert!(!map.deny(msr, MsrAccess::Read));
+            assert!(!map.allow(msr, MsrAccess::Read));
+            assert!(map.denied(msr, MsrAccess::Read));
+        }
+
+        assert!(empty(&map.0));
+    }
+}
@@ -0,0 +1,17 @@
+// libudis86 is the reference decoder of the vmm disassembler tests,
+// built for the host only.
+
+extern crate cc;
+
+fn main() {
+    let udis = "libudis86";
+
+    cc::Build::new()
+        .files(["decode.c", "input.c", "itab.c", "udis86.c"]
+               .iter().map(|f| format!("{}/{}", udis, f)))
+        .include(udis)
+        .warnings(false)
+        .compile("udis86");
+
+    println!("cargo:rerun-if-changed={}", udis);
+}
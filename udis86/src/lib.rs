@@ -0,0 +1,146 @@
+// udis86 bindings, reference decoder for the vmm host tests
+//
+// Only a dev-dependency of the vmm: libudis86 is never linked into
+// the VMM.
+
+use std::ffi::CStr;
+use std::mem;
+use std::os::raw::c_char;
+
+// enum ud_type
+pub const UD_NONE:     u32 = 0;
+pub const UD_R_AL:     u32 = 1;
+pub const UD_R_AH:     u32 = 5;
+pub const UD_R_AX:     u32 = 21;
+pub const UD_R_EAX:    u32 = 37;
+pub const UD_R_RAX:    u32 = 53;
+pub const UD_R_ES:     u32 = 69;
+pub const UD_R_CR0:    u32 = 75;
+pub const UD_R_DR0:    u32 = 91;
+pub const UD_R_RIP:    u32 = 139;
+pub const UD_OP_REG:   u32 = 140;
+pub const UD_OP_MEM:   u32 = 141;
+pub const UD_OP_PTR:   u32 = 142;
+pub const UD_OP_IMM:   u32 = 143;
+pub const UD_OP_JIMM:  u32 = 144;
+pub const UD_OP_CONST: u32 = 145;
+
+const UD_VENDOR_INTEL: u32 = 1;
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct ud_ptr {
+    pub seg: u16,
+    pub off: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub union ud_lval {
+    pub sbyte:  i8,
+    pub ubyte:  u8,
+    pub sword:  i16,
+    pub uword:  u16,
+    pub sdword: i32,
+    pub udword: u32,
+    pub sqword: i64,
+    pub uqword: u64,
+    pub ptr:    ud_ptr,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+pub struct ud_operand {
+    pub otype:  u32,
+    pub size:   u8,
+    pub lval:   ud_lval,
+    pub base:   u32,
+    pub index:  u32,
+    pub offset: u8,
+    pub scale:  u8,
+}
+
+// __UD_STANDALONE__ layout (no input file)
+#[repr(C)]
+pub struct ud {
+    pub inp_hook:      Option<unsafe extern "C" fn(*mut ud) -> i32>,
+    pub inp_curr:      u8,
+    pub inp_fill:      u8,
+    pub inp_ctr:       u8,
+    pub inp_buff:      *const u8,
+    pub inp_buff_end:  *const u8,
+    pub inp_end:       u8,
+    pub translator:    Option<unsafe extern "C" fn(*mut ud)>,
+    pub insn_offset:   u64,
+    pub insn_hexcode:  [c_char; 32],
+    pub insn_buffer:   [c_char; 64],
+    pub insn_fill:     u32,
+    pub dis_mode:      u8,
+    pub pc:            u64,
+    pub vendor:        u8,
+    pub mapen:         *const (),
+    pub mnemonic:      u32,
+    pub operand:       [ud_operand; 3],
+    pub error:         u8,
+    pub pfx_rex:       u8,
+    pub pfx_seg:       u8,
+    pub pfx_opr:       u8,
+    pub pfx_adr:       u8,
+    pub pfx_lock:      u8,
+    pub pfx_rep:       u8,
+    pub pfx_repe:      u8,
+    pub pfx_repne:     u8,
+    pub pfx_insn:      u8,
+    pub default64:     u8,
+    pub opr_mode:      u8,
+    pub adr_mode:      u8,
+    pub br_far:        u8,
+    pub br_near:       u8,
+    pub implicit_addr: u8,
+    pub c1:            u8,
+    pub c2:            u8,
+    pub c3:            u8,
+    pub inp_cache:     [u8; 256],
+    pub inp_sess:      [u8; 64],
+    pub itab_entry:    *const (),
+}
+
+extern "C" {
+    fn ud_init(ud: *mut ud);
+    fn ud_set_mode(ud: *mut ud, mode: u8);
+    fn ud_set_input_buffer(ud: *mut ud, data: *const u8, len: usize);
+    fn ud_set_vendor(ud: *mut ud, vendor: u32);
+    fn ud_disassemble(ud: *mut ud) -> u32;
+    fn ud_lookup_mnemonic(code: u32) -> *const c_char;
+}
+
+// Decode first instruction of bytes, mode is the code size (2, 4
+// or 8). None when udis86 finds it invalid.
+pub fn disassemble(bytes: &[u8], mode: usize) -> Option<Box<ud>> {
+    unsafe {
+        let mut u: Box<ud> = Box::new(mem::zeroed());
+
+        ud_init(&mut *u);
+        ud_set_input_buffer(&mut *u, bytes.as_ptr(), bytes.len());
+        ud_set_mode(&mut *u, (mode * 8) as u8);
+        ud_set_vendor(&mut *u, UD_VENDOR_INTEL);
+
+        if ud_disassemble(&mut *u) == 0 || u.error != 0 || mnemonic(&u) == "invalid" {
+            return None
+        }
+
+        Some(u)
+    }
+}
+
+pub fn mnemonic(u: &ud) -> &'static str {
+    unsafe {
+        let name = ud_lookup_mnemonic(u.mnemonic);
+
+        if name.is_null() {
+            return "invalid"
+        }
+
+        CStr::from_ptr(name).to_str().unwrap_or("invalid")
+    }
+}
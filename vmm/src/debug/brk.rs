@@ -0,0 +1,126 @@
+// Software breakpoints
+//
+// #BP is intercepted while the VMM owns breakpoints. On a hit the
+// debugger takes over, then the original byte is put back and the
+// instruction is stepped over, INT3 is written again on the MTF
+// exit. Breakpoints we do not own belong to the guest and are
+// reflected.
+
+use vm::mem;
+use inject;
+use step;
+use debug::gdb;
+use vmx::exit::VMMStatus;
+use share::info::InformationData;
+use share::breakpoint::{Breakpoint, BRK_INT3};
+use share::exceptions as excp;
+use share::vmx::regs::EventType;
+use share::vmx::vmcs::access::Access;
+use share::utils::RawValue;
+use core::convert::TryFrom;
+
+fn intercept(info: &mut InformationData, enable: bool) {
+    let bitmap = info.vm.vmcs.ctrl.exec.excp_bitmap.as_mut();
+    let value  = bitmap.as_u32();
+
+    if enable {
+        bitmap.update_u64((value | 1<<excp::BP) as u64);
+    } else {
+        bitmap.update_u64((value & !(1<<excp::BP)) as u64);
+    }
+}
+
+pub fn set(info: &mut InformationData, addr: u64) -> VMMStatus {
+    if info.vm.brk.find(addr).is_some() {
+        return VMMStatus::Done
+    }
+
+    let mut saved = [0u8; 1];
+    match mem::read_debug(info, addr, &mut saved) {
+        VMMStatus::Done => (),
+        rc @ _ => return rc,
+    }
+
+    if !info.vm.brk.add(Breakpoint { addr: addr, saved: saved[0] }) {
+        log!("no more breakpoint available for {:#x}\n", addr);
+        return VMMStatus::Fail
+    }
+
+    match mem::write_debug(info, addr, &[BRK_INT3]) {
+        VMMStatus::Done => (),
+        rc @ _ => {
+            info.vm.brk.del(addr);
+            return rc
+        },
+    }
+
+    intercept(info, true);
+    VMMStatus::Done
+}
+
+pub fn del(info: &mut InformationData, addr: u64) -> VMMStatus {
+    let brk = match info.vm.brk.del(addr) {
+        Some(brk) => brk,
+        None => return VMMStatus::Done,
+    };
+
+    if info.vm.brk.is_empty() {
+        intercept(info, false);
+    }
+
+    // already restored while being stepped over
+    if info.vm.brk.rearm == Some(addr) {
+        info.vm.brk.rearm = None;
+        return VMMStatus::Done
+    }
+
+    mem::write_debug(info, addr, &[brk.saved])
+}
+
+// Write INT3 back once the original instruction executed
+pub fn rearm(info: &mut InformationData) {
+    let addr = match info.vm.brk.rearm.take() {
+        Some(addr) => addr,
+        None => return,
+    };
+
+    if let VMMStatus::Done = mem::write_debug(info, addr, &[BRK_INT3]) {
+        return
+    }
+
+    log!("can't re-arm breakpoint at {:#x}\n", addr);
+    info.vm.brk.del(addr);
+}
+
+// #BP exit, rip points to the INT3 instruction
+pub fn handler(info: &mut InformationData) -> VMMStatus {
+    let rip  = info.vm.vmcs.guest.rip.as_ref().as_u64();
+    let addr = info.vm.vmcs.guest.cs.base.as_ref().as_u64().wrapping_add(rip);
+
+    let brk = match info.vm.brk.find(addr) {
+        Some(brk) => brk,
+        None => {
+            let kind = info.vm.vmcs.exit.int_info.as_ref().kind();
+            let len  = info.vm.vmcs.exit.insn_len.as_ref().as_u32();
+            let kind = EventType::try_from(kind).unwrap_or(EventType::SoftExcp);
+            return inject::software(info, kind, excp::BP as u8, len)
+        },
+    };
+
+    log!("breakpoint at {:#x}\n", addr);
+    gdb::stop(info, gdb::Stop::SwBreak);
+
+    // debugger removed the breakpoint or moved rip
+    if info.vm.brk.find(addr).is_none() || info.vm.vmcs.guest.rip.as_ref().as_u64() != rip {
+        return VMMStatus::DoneLetRip
+    }
+
+    match mem::write_debug(info, addr, &[brk.saved]) {
+        VMMStatus::Done => (),
+        rc @ _ => return rc,
+    }
+
+    info.vm.brk.rearm = Some(addr);
+    step::start(info, true);
+    VMMStatus::DoneLetRip
+}
@@ -0,0 +1,521 @@
+// GDB remote serial protocol stub on COM2
+//
+// The guest is stopped while the VMM serves the debugger from a
+// vm-exit and runs again once the debugger continues or steps.
+// Incoming bytes are polled on every vm-exit: a guest that never
+// exits can not be interrupted. Stops are ignored until a debugger
+// attaches, detaching drops what it set.
+//
+// Registers follow the gdb amd64 layout up to the segment
+// selectors, which are read-only. Memory is guest linear, read
+// with original bytes under software breakpoints.
+//
+// Monitor commands:
+//   cr3 stop|ignore <cr3>  filter an address space
+//   cr3 del <cr3>          remove its filter
+//   spaces                 log address spaces
+
+use vm::{gpr, mem, space};
+use step;
+use debug::{brk, hw, watch};
+use vmx::exit::VMMStatus;
+use share::info::InformationData;
+use share::gdb::{self, Event, Reply, PACKET_MAX};
+use share::breakpoint::BRK_INT3;
+use share::dr::{DrKind, DR_COUNT};
+use share::space::Cr3Action;
+use share::uart::{Serial, SerialPort};
+use share::vmx::vmcs::access::Access;
+use share::utils::RawValue;
+
+const SIGINT:  u8 = 2;
+const SIGTRAP: u8 = 5;
+
+// Why the guest stopped
+#[derive(Debug, Copy, Clone)]
+pub enum Stop {
+    Interrupt, // debugger asked for it
+    Trap,      // single-step done, address space filter or attach
+    SwBreak,
+    HwBreak,
+    Watch(u64),  // write to a watched range
+    AWatch(u64), // access to a watched range
+}
+
+const ERR_ARGS: &'static str = "E01";
+const ERR_MEM:  &'static str = "E02";
+
+// gdb amd64 register numbers: general purpose ones, rip, eflags
+// then cs, ss, ds, es, fs and gs
+const RIP:       usize = 16;
+const EFLAGS:    usize = 17;
+const REG_COUNT: usize = 24;
+
+// to instruction encoding index
+const GPR: [u8;16] = [gpr::RAX, gpr::RBX, gpr::RCX, gpr::RDX,
+                      gpr::RSI, gpr::RDI, gpr::RBP, gpr::RSP,
+                      8, 9, 10, 11, 12, 13, 14, 15];
+
+// Largest memory transfer fitting a packet
+const MEM_MAX: usize = PACKET_MAX / 2 - 32;
+
+fn uart() -> Serial {
+    Serial { base: SerialPort::Com2 as u16 }
+}
+
+// Send until acknowledged
+fn send(data: &[u8]) {
+    let uart = uart();
+
+    loop {
+        gdb::encode(data, |b| uart.send(b));
+
+        loop {
+            match uart.recv() {
+                gdb::ACK  => return,
+                gdb::NACK => break,
+                _ => (),
+            }
+        }
+    }
+}
+
+// Wait for the next packet
+fn receive(info: &mut InformationData) {
+    let uart = uart();
+
+    loop {
+        match info.vm.gdb.rx.feed(uart.recv()) {
+            Event::Packet => { uart.send(gdb::ACK); return },
+            Event::Bad    => uart.send(gdb::NACK),
+            _ => (),
+        }
+    }
+}
+
+fn reg_size(n: usize) -> usize {
+    if n < EFLAGS { 8 } else { 4 }
+}
+
+fn reg_read(info: &mut InformationData, n: usize) -> u64 {
+    if n < RIP {
+        return gpr::read(info, GPR[n])
+    }
+
+    let guest = &mut info.vm.vmcs.guest;
+
+    match n {
+        RIP    => guest.rip.as_ref().as_u64(),
+        EFLAGS => guest.rflags.as_ref().as_u64(),
+        18     => guest.cs.sel.as_ref().as_u64(),
+        19     => guest.ss.sel.as_ref().as_u64(),
+        20     => guest.ds.sel.as_ref().as_u64(),
+        21     => guest.es.sel.as_ref().as_u64(),
+        22     => guest.fs.sel.as_ref().as_u64(),
+        _      => guest.gs.sel.as_ref().as_u64(),
+    }
+}
+
+// Loading a selector would need its descriptor, only the current
+// value is accepted
+fn reg_write(info: &mut InformationData, n: usize, value: u64) -> bool {
+    match n {
+        0...15 => gpr::write(info, GPR[n], value),
+        RIP    => info.vm.vmcs.guest.rip.as_mut().update_u64(value),
+        EFLAGS => info.vm.vmcs.guest.rflags.as_mut().update_u64(value),
+        _      => return reg_read(info, n) == value,
+    }
+
+    true
+}
+
+fn read_regs(info: &mut InformationData, reply: &mut Reply) {
+    for n in 0..REG_COUNT {
+        let value = reg_read(info, n);
+        reply.push_le(value, reg_size(n));
+    }
+}
+
+fn write_regs(info: &mut InformationData, args: &[u8], reply: &mut Reply) {
+    let mut offset = 0;
+
+    for n in 0..REG_COUNT {
+        let end = offset + 2*reg_size(n);
+        if end > args.len() {
+            break
+        }
+
+        let done = match gdb::le(&args[offset..end]) {
+            Some(value) => reg_write(info, n, value),
+            None => false,
+        };
+
+        if !done {
+            reply.push_str(ERR_ARGS);
+            return
+        }
+
+        offset = end;
+    }
+
+    reply.push_str("OK");
+}
+
+fn read_reg(info: &mut InformationData, args: &[u8], reply: &mut Reply) {
+    match gdb::hex(args) {
+        Some(n) if (n as usize) < REG_COUNT => {
+            let value = reg_read(info, n as usize);
+            reply.push_le(value, reg_size(n as usize));
+        },
+        _ => { reply.push_str(ERR_ARGS); },
+    }
+}
+
+fn write_reg(info: &mut InformationData, args: &[u8], reply: &mut Reply) {
+    let (n, value) = match gdb::split(args, b'=') {
+        Some((n, value)) => (gdb::hex(n), gdb::le(value)),
+        None => (None, None),
+    };
+
+    let done = match (n, value) {
+        (Some(n), Some(value)) if (n as usize) < REG_COUNT => reg_write(info, n as usize, value),
+        _ => false,
+    };
+
+    reply.push_str(if done { "OK" } else { ERR_ARGS });
+}
+
+// addr,len
+fn range(args: &[u8]) -> Option<(u64, usize)> {
+    match gdb::split(args, b',') {
+        Some((addr, len)) => match (gdb::hex(addr), gdb::hex(len)) {
+            (Some(addr), Some(len)) if len as usize <= MEM_MAX => Some((addr, len as usize)),
+            _ => None,
+        },
+        None => None,
+    }
+}
+
+fn read_mem(info: &mut InformationData, args: &[u8], reply: &mut Reply) {
+    let mut buf = [0u8;MEM_MAX];
+
+    let (addr, len) = match range(args) {
+        Some(range) => range,
+        None => { reply.push_str(ERR_ARGS); return },
+    };
+
+    match mem::read_debug(info, addr, &mut buf[..len]) {
+        VMMStatus::Done => (),
+        _ => { reply.push_str(ERR_MEM); return },
+    }
+
+    // the debugger sees original bytes under breakpoints
+    for b in info.vm.brk.list() {
+        let off = b.addr.wrapping_sub(addr);
+        if off < len as u64 && buf[off as usize] == BRK_INT3 {
+            buf[off as usize] = b.saved;
+        }
+    }
+
+    reply.push_hex(&buf[..len]);
+}
+
+fn write_mem(info: &mut InformationData, args: &[u8], reply: &mut Reply) {
+    let mut buf = [0u8;MEM_MAX];
+
+    let (addr, len, data) = match gdb::split(args, b':') {
+        Some((head, data)) => match range(head) {
+            Some((addr, len)) => (addr, len, data),
+            None => { reply.push_str(ERR_ARGS); return },
+        },
+        None => { reply.push_str(ERR_ARGS); return },
+    };
+
+    if gdb::unhex(data, &mut buf) != Some(len) {
+        reply.push_str(ERR_ARGS);
+        return
+    }
+
+    match mem::write_debug(info, addr, &buf[..len]) {
+        VMMStatus::Done => (),
+        _ => { reply.push_str(ERR_MEM); return },
+    }
+
+    reply.push_str("OK");
+}
+
+// Z/z type,addr,kind
+fn point(info: &mut InformationData, args: &[u8], insert: bool, reply: &mut Reply) {
+    let mut fields = args.split(|&c| c == b',' || c == b';');
+
+    let (kind, addr, len) = match (fields.next(), fields.next(), fields.next()) {
+        (Some(kind), Some(addr), Some(len)) => match (gdb::hex(kind), gdb::hex(addr), gdb::hex(len)) {
+            (Some(kind), Some(addr), Some(len)) => (kind, addr, len as usize),
+            _ => { reply.push_str(ERR_ARGS); return },
+        },
+        _ => { reply.push_str(ERR_ARGS); return },
+    };
+
+    let rc = match kind {
+        0 if insert => brk::set(info, addr),
+        0 => brk::del(info, addr),
+        1 if insert => match (hw::find(info, addr), hw::free(info)) {
+            (Some(_), _)    => VMMStatus::Done,
+            (None, Some(n)) => hw::set(info, n, addr, DrKind::Exec, 1),
+            (None, None)    => VMMStatus::Fail,
+        },
+        1 => {
+            if let Some(n) = hw::find(info, addr) {
+                hw::del(info, n);
+            }
+            VMMStatus::Done
+        },
+        // EPT watchpoints on writes or on any access, not on reads only
+        2 | 4 if insert => watch::set_linear(info, addr, len, kind == 2),
+        2 | 4 => watch::del(info, addr, kind == 2),
+        _ => return, // unsupported, empty reply
+    };
+
+    reply.push_str(match rc { VMMStatus::Done => "OK", _ => ERR_ARGS });
+}
+
+// Optional 0x prefix
+fn number(word: &[u8]) -> Option<u64> {
+    gdb::hex(if word.starts_with(b"0x") { &word[2..] } else { word })
+}
+
+fn monitor(info: &mut InformationData, args: &[u8], reply: &mut Reply) {
+    let mut buf = [0u8;MEM_MAX];
+
+    let len = match gdb::unhex(args, &mut buf) {
+        Some(len) => len,
+        None => { reply.push_str(ERR_ARGS); return },
+    };
+
+    let mut words = buf[..len].split(|&c| c == b' ').filter(|w| !w.is_empty());
+    let (cmd, op, arg) = (words.next().unwrap_or(&[]), words.next().unwrap_or(&[]), words.next());
+
+    let rc = match (cmd, op, arg.and_then(number)) {
+        (b"spaces", b"", None) => { space::dump(info); VMMStatus::Done },
+        (b"cr3", b"stop", Some(cr3))   => space::filter(info, cr3, Cr3Action::Stop),
+        (b"cr3", b"ignore", Some(cr3)) => space::filter(info, cr3, Cr3Action::Ignore),
+        (b"cr3", b"del", Some(cr3))    => space::unfilter(info, cr3),
+        _ => VMMStatus::Fail,
+    };
+
+    reply.push_str(match rc { VMMStatus::Done => "OK", _ => ERR_ARGS });
+}
+
+fn query(info: &mut InformationData, args: &[u8], reply: &mut Reply) {
+    if args.starts_with(b"Rcmd,") {
+        monitor(info, &args[5..], reply);
+    } else if args.starts_with(b"Supported") {
+        reply.push_str("PacketSize=");
+        reply.push_num(PACKET_MAX as u64);
+        reply.push_str(";swbreak+;hwbreak+");
+    } else if args == &b"Attached"[..] {
+        reply.push_str("1");
+    }
+}
+
+fn stopped(stop: Stop, reply: &mut Reply) {
+    let (reason, addr) = match stop {
+        Stop::Interrupt => { reply.push(b'S'); reply.push_hex(&[SIGINT]); return },
+        Stop::Trap      => { reply.push(b'S'); reply.push_hex(&[SIGTRAP]); return },
+        Stop::SwBreak      => ("swbreak", None),
+        Stop::HwBreak      => ("hwbreak", None),
+        Stop::Watch(addr)  => ("watch", Some(addr)),
+        Stop::AWatch(addr) => ("awatch", Some(addr)),
+    };
+
+    // T05reason:[addr];
+    reply.push(b'T');
+    reply.push_hex(&[SIGTRAP]);
+    reply.push_str(reason);
+    reply.push(b':');
+    if let Some(addr) = addr {
+        reply.push_num(addr);
+    }
+    reply.push(b';');
+}
+
+// Continue or step, optionally from a new rip
+fn resume(info: &mut InformationData, args: &[u8], step: bool) -> bool {
+    if !args.is_empty() {
+        match gdb::hex(args) {
+            Some(rip) => info.vm.vmcs.guest.rip.as_mut().update_u64(rip),
+            None => return false,
+        }
+    }
+
+    if step {
+        info.vm.gdb.stepping = true;
+        step::start(info, true);
+    }
+
+    true
+}
+
+// MTF exit, report a watchpoint hit once the access is done, the
+// end of a debugger single-step or the first instruction run in
+// an address space with a stop filter
+pub fn stepped(info: &mut InformationData, space: bool) {
+    let stepping = info.vm.gdb.stepping;
+    info.vm.gdb.stepping = false;
+
+    if !watched(info) && (stepping || space) {
+        stop(info, Stop::Trap);
+    }
+}
+
+fn watched(info: &mut InformationData) -> bool {
+    match (info.vm.gdb.watch.take(), info.vm.gdb.write) {
+        (Some(addr), true)  => stop(info, Stop::Watch(addr)),
+        (Some(addr), false) => stop(info, Stop::AWatch(addr)),
+        (None, _) => return false,
+    }
+
+    true
+}
+
+// Nothing the debugger set may stop the guest once it is gone
+fn detach(info: &mut InformationData) {
+    loop {
+        let addr = match info.vm.brk.list().first() {
+            Some(b) => b.addr,
+            None => break,
+        };
+        brk::del(info, addr);
+    }
+
+    for n in 0..DR_COUNT {
+        hw::del(info, n);
+    }
+
+    loop {
+        let (addr, write) = match info.vm.wp.list().first() {
+            Some(w) => (w.addr, w.write),
+            None => break,
+        };
+        watch::del(info, addr, write);
+    }
+
+    loop {
+        let cr3 = match info.vm.spaces.filters().iter().find(|f| f.action == Cr3Action::Stop) {
+            Some(f) => f.cr3,
+            None => break,
+        };
+        space::unfilter(info, cr3);
+    }
+
+    info.vm.spaces.stop = None;
+    info.vm.gdb.stepping = false;
+    info.vm.gdb.watch = None;
+    info.vm.gdb.attached = false;
+    log!("debugger detached\n");
+}
+
+// Handle one packet, true when the guest runs again
+fn command(info: &mut InformationData, pkt: &[u8], stop: Stop, reply: &mut Reply) -> bool {
+    let (cmd, args) = match pkt.split_first() {
+        Some((&cmd, args)) => (cmd, args),
+        None => return false,
+    };
+
+    match cmd {
+        b'?' => stopped(stop, reply),
+        b'g' => read_regs(info, reply),
+        b'G' => write_regs(info, args, reply),
+        b'p' => read_reg(info, args, reply),
+        b'P' => write_reg(info, args, reply),
+        b'm' => read_mem(info, args, reply),
+        b'M' => write_mem(info, args, reply),
+        b'c' | b's' => {
+            if resume(info, args, cmd == b's') {
+                return true
+            }
+            reply.push_str(ERR_ARGS);
+        },
+        b'D' => {
+            send(b"OK");
+            detach(info);
+            return true
+        },
+        // the guest can not be killed, leave it running
+        b'k' => {
+            detach(info);
+            return true
+        },
+        b'H' | b'T' => { reply.push_str("OK"); },
+        b'Z' | b'z' => point(info, args, cmd == b'Z', reply),
+        b'q' => query(info, args, reply),
+        _ => (), // unsupported, empty reply
+    }
+
+    false
+}
+
+// Serve packets until the guest resumes, one is pending
+fn serve(info: &mut InformationData, stop: Stop) {
+    let mut pkt   = [0u8;PACKET_MAX];
+    let mut reply = Reply::new();
+
+    loop {
+        let len = {
+            let data = info.vm.gdb.rx.packet();
+            pkt[..data.len()].copy_from_slice(data);
+            data.len()
+        };
+
+        reply.clear();
+        if command(info, &pkt[..len], stop, &mut reply) {
+            return
+        }
+
+        send(reply.data());
+        receive(info);
+    }
+}
+
+// Guest stopped, report why and hand over to the debugger
+pub fn stop(info: &mut InformationData, stop: Stop) {
+    if !info.vm.gdb.attached {
+        return
+    }
+
+    {
+        let mut reply = Reply::new();
+        stopped(stop, &mut reply);
+        send(reply.data());
+    }
+
+    receive(info);
+    serve(info, stop);
+}
+
+// Debugger activity while the guest runs
+pub fn poll(info: &mut InformationData) {
+    let uart = uart();
+
+    // emulated access, no MTF exit to wait for
+    if !info.vm.cpu.step.active() && watched(info) {
+        return
+    }
+
+    while let Some(byte) = uart.read_byte() {
+        match info.vm.gdb.rx.feed(byte) {
+            Event::Interrupt => {
+                info.vm.gdb.attached = true;
+                return stop(info, Stop::Interrupt)
+            },
+            Event::Packet => {
+                // debugger attached, the guest is stopped meanwhile
+                info.vm.gdb.attached = true;
+                uart.send(gdb::ACK);
+                return serve(info, Stop::Trap)
+            },
+            Event::Bad => uart.send(gdb::NACK),
+            _ => (),
+        }
+    }
+}
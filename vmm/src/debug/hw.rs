@@ -0,0 +1,249 @@
+// Hardware breakpoints and watchpoints
+//
+// The VMM takes the debug registers from the guest on its first
+// hardware breakpoint and gives them back with the last one.
+// Meanwhile mov-DR exits and #DB are intercepted: the guest works
+// on shadow registers, its breakpoints in the slots we do not own
+// stay armed and #DB not caused by our breakpoints are reflected.
+// Our hits hand over to the debugger.
+
+use inject;
+use debug::gdb;
+use vm::gpr;
+use vmx::exit::VMMStatus;
+use cpumode::CPUState;
+use share::info::InformationData;
+use share::dr::{self, Dr6, Dr7, DrKind, DR_COUNT, DR6_CAUSE_MSK};
+use share::exceptions as excp;
+use share::vmx::regs::{EventType, ExitQualDR};
+use share::vmx::vmcs::access::Access;
+use share::utils::RawValue;
+use core::convert::TryFrom;
+
+fn intercept(info: &mut InformationData, enable: bool) {
+    info.vm.vmcs.ctrl.exec.proc1.as_mut().set_mdr(enable);
+
+    let bitmap = info.vm.vmcs.ctrl.exec.excp_bitmap.as_mut();
+    let value  = bitmap.as_u32();
+
+    if enable {
+        bitmap.update_u64((value | 1<<excp::DB) as u64);
+    } else {
+        bitmap.update_u64((value & !(1<<excp::DB)) as u64);
+    }
+}
+
+// Hardware DR7 runs our slots and the guest enabled ones left
+fn merge(info: &mut InformationData) {
+    let owned = info.vm.cpu.dr.owned;
+    let mut ours = 0;
+    let mut slots = 3<<8; // LE and GE
+
+    for n in 0..DR_COUNT {
+        slots |= Dr7::mask(n);
+        if owned & 1<<n != 0 {
+            ours |= Dr7::mask(n);
+        }
+    }
+
+    let hw    = info.vm.vmcs.guest.dr7.as_ref().as_u64() & ours;
+    let guest = info.vm.cpu.dr.dr7 & slots & !ours;
+    info.vm.vmcs.guest.dr7.as_mut().update_u64(dr::DR7_DFT | hw | guest);
+}
+
+// Save guest debug registers into the shadow ones
+fn take(info: &mut InformationData) {
+    for n in 0..DR_COUNT {
+        info.vm.cpu.dr.guest[n] = dr::dr_read(n);
+    }
+
+    info.vm.cpu.dr.dr6 = info.vm.vmcs.guest.dr6.as_ref().as_u64();
+    info.vm.cpu.dr.dr7 = info.vm.vmcs.guest.dr7.as_ref().as_u64();
+    intercept(info, true);
+}
+
+fn release(info: &mut InformationData) {
+    for n in 0..DR_COUNT {
+        dr::dr_write(n, info.vm.cpu.dr.guest[n]);
+    }
+
+    let (dr6, dr7) = (info.vm.cpu.dr.dr6, info.vm.cpu.dr.dr7);
+    info.vm.vmcs.guest.dr6.as_mut().update_u64(dr6);
+    info.vm.vmcs.guest.dr7.as_mut().update_u64(dr7);
+    intercept(info, false);
+}
+
+// Use DRn to break on size bytes accesses at linear addr
+pub fn set(info: &mut InformationData, n: usize, addr: u64,
+           kind: DrKind, size: usize) -> VMMStatus {
+    let len = match dr::dr_len(size) {
+        Some(len) if kind != DrKind::Exec || size == 1 => len,
+        _ => {
+            log!("invalid hardware breakpoint size {}\n", size);
+            return VMMStatus::Fail
+        },
+    };
+
+    if n >= DR_COUNT || addr & (size as u64 - 1) != 0 {
+        log!("invalid hardware breakpoint DR{} at {:#x}\n", n, addr);
+        return VMMStatus::Fail
+    }
+
+    if info.vm.cpu.dr.owned == 0 {
+        take(info);
+    }
+
+    info.vm.cpu.dr.owned |= 1<<n;
+    dr::dr_write(n, addr);
+
+    let mut dr7 = Dr7(info.vm.vmcs.guest.dr7.as_ref().as_u64());
+    dr7.enable(n, kind, len);
+    info.vm.vmcs.guest.dr7.as_mut().update_u64(dr7.0);
+    merge(info);
+    VMMStatus::Done
+}
+
+pub fn del(info: &mut InformationData, n: usize) {
+    if n >= DR_COUNT || info.vm.cpu.dr.owned & 1<<n == 0 {
+        return
+    }
+
+    let mut dr7 = Dr7(info.vm.vmcs.guest.dr7.as_ref().as_u64());
+    dr7.disable(n);
+    info.vm.vmcs.guest.dr7.as_mut().update_u64(dr7.0);
+
+    info.vm.cpu.dr.owned &= !(1<<n);
+    if info.vm.cpu.dr.owned == 0 {
+        release(info);
+        return
+    }
+
+    // slot back to the guest
+    dr::dr_write(n, info.vm.cpu.dr.guest[n]);
+    merge(info);
+}
+
+// Our debug register watching addr
+pub fn find(info: &mut InformationData, addr: u64) -> Option<usize> {
+    let owned = info.vm.cpu.dr.owned;
+    (0..DR_COUNT).find(|&n| owned & 1<<n != 0 && dr::dr_read(n) == addr)
+}
+
+pub fn free(info: &mut InformationData) -> Option<usize> {
+    let owned = info.vm.cpu.dr.owned;
+    (0..DR_COUNT).find(|&n| owned & 1<<n == 0)
+}
+
+// Guest view of debug registers
+pub fn read_dr(info: &mut InformationData, n: usize) -> u64 {
+    let owned = info.vm.cpu.dr.owned != 0;
+
+    match n {
+        0...3 if owned => info.vm.cpu.dr.guest[n],
+        0...3 => dr::dr_read(n),
+        6 if owned => info.vm.cpu.dr.dr6,
+        6 => info.vm.vmcs.guest.dr6.as_ref().as_u64(),
+        _ if owned => info.vm.cpu.dr.dr7,
+        _ => info.vm.vmcs.guest.dr7.as_ref().as_u64(),
+    }
+}
+
+pub fn write_dr(info: &mut InformationData, n: usize, value: u64) {
+    let mask  = info.vm.cpu.dr.owned;
+    let owned = mask != 0;
+
+    match n {
+        0...3 if owned => {
+            info.vm.cpu.dr.guest[n] = value;
+            if mask & 1<<n == 0 {
+                dr::dr_write(n, value);
+            }
+        },
+        0...3 => dr::dr_write(n, value),
+        6 if owned => info.vm.cpu.dr.dr6 = value,
+        6 => info.vm.vmcs.guest.dr6.as_mut().update_u64(value),
+        _ if owned => {
+            info.vm.cpu.dr.dr7 = value;
+            merge(info);
+        },
+        _ => info.vm.vmcs.guest.dr7.as_mut().update_u64(value),
+    }
+}
+
+// mov-DR exit
+pub fn access_handler(info: &mut InformationData) -> VMMStatus {
+    let qual = ExitQualDR(info.vm.vmcs.exit.qualification.as_ref().as_u64());
+    let mut n = qual.nr() as usize;
+
+    // DR4/DR5 alias DR6/DR7 unless debug extensions are enabled
+    if n == 4 || n == 5 {
+        if info.vm.vmcs.guest.cr4.as_ref().de() {
+            return inject::exception(info, excp::UD, None)
+        }
+        n += 2;
+    }
+
+    let long = CPUState::init(info).is_long64();
+
+    if qual.read() {
+        let mut value = read_dr(info, n);
+        if !long {
+            value &= 0xffffffff;
+        }
+        gpr::write(info, qual.gpr(), value);
+    } else {
+        let mut value = gpr::read(info, qual.gpr());
+        if !long {
+            value &= 0xffffffff;
+        }
+        if n >= 6 && value>>32 != 0 {
+            return inject::exception(info, excp::GP, Some(0))
+        }
+        write_dr(info, n, value);
+    }
+
+    VMMStatus::Done
+}
+
+// Instruction breakpoints are faults
+fn exec_hit(dr7: u64, hits: u64) -> bool {
+    (0..DR_COUNT).any(|n| hits & 1<<n != 0 && (dr7>>(16 + 4*n)) & 3 == DrKind::Exec as u64)
+}
+
+// #DB exit, qualification holds the would-be DR6 bits
+pub fn handler(info: &mut InformationData) -> VMMStatus {
+    let kind = info.vm.vmcs.exit.int_info.as_ref().kind();
+
+    // INT1 (ICEBP) always belongs to the guest
+    if let Ok(EventType::PSExcp) = EventType::try_from(kind) {
+        let len = info.vm.vmcs.exit.insn_len.as_ref().as_u32();
+        return inject::software(info, EventType::PSExcp, excp::DB as u8, len)
+    }
+
+    let qual  = Dr6(info.vm.vmcs.exit.qualification.as_ref().as_u64());
+    let cause = qual.0 & DR6_CAUSE_MSK;
+    let ours  = cause & info.vm.cpu.dr.owned as u64;
+    let guest = cause & !ours;
+
+    #[cfg(feature = "debug_excp")]
+    log!("#DB {:?}\n", qual);
+
+    if ours != 0 {
+        let rip = info.vm.vmcs.guest.rip.as_ref().as_u64();
+        log!("hardware breakpoint hit (DR mask {:#x}) at rip {:#x}\n", ours, rip);
+        gdb::stop(info, gdb::Stop::HwBreak);
+
+        let dr7 = info.vm.vmcs.guest.dr7.as_ref().as_u64();
+        if exec_hit(dr7, ours) {
+            info.vm.vmcs.guest.rflags.as_mut().set_rf(true);
+        }
+
+        if guest == 0 {
+            return VMMStatus::DoneLetRip
+        }
+    }
+
+    let dr6 = (read_dr(info, 6) & !0xf) | guest;
+    write_dr(info, 6, dr6);
+    inject::exception(info, excp::DB, None)
+}
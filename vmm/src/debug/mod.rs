@@ -0,0 +1,5 @@
+// Guest debugging facilities
+pub mod brk;
+pub mod hw;
+pub mod watch;
+pub mod gdb;
@@ -0,0 +1,142 @@
+// Invisible watchpoints
+//
+// Pages covering a watched range lose EPT write (or read/write)
+// permission. Every access to the page is single-stepped with
+// permissions restored, those inside the range stop the guest
+// after the step.
+
+use vm::mem;
+use vmx::exit::VMMStatus;
+use vmx::exit::ept::{EPTAction, set_pvl};
+use share::info::InformationData;
+use share::breakpoint::Watchpoint;
+use share::vmx::regs::ExitQualEPT;
+use share::vmx::vmcs::access::Access;
+use share::vmx::ept::{EPTRegion, EPTRegionKind, attr_pvl_dft};
+use share::utils::RawValue;
+use share::utils;
+use share::paging::utils::*;
+
+fn kind(write: bool) -> EPTRegionKind {
+    if write { EPTRegionKind::WriteWatch } else { EPTRegionKind::AccessWatch }
+}
+
+// Overlapping regions decide of page permissions
+fn apply(info: &mut InformationData, start: u64, end: u64) {
+    let mut page = pg_align(PG_4K_SHIFT, start);
+
+    while page < end {
+        set_pvl(page, info.vm.ept.pvl(page).unwrap_or(attr_pvl_dft()));
+        page += PG_4KB as u64;
+    }
+}
+
+fn add(info: &mut InformationData, wp: Watchpoint) -> bool {
+    let start  = pg_align(PG_4K_SHIFT, wp.start);
+    let end    = pg_align(PG_4K_SHIFT, wp.end + PG_4KB as u64 - 1);
+    let region = EPTRegion::new(start, end, kind(wp.write));
+
+    if !info.vm.ept.add(region) {
+        return false
+    }
+
+    if !info.vm.wp.add(wp) {
+        info.vm.ept.del(start, end, region.kind);
+        return false
+    }
+
+    apply(info, start, end);
+    true
+}
+
+fn remove(info: &mut InformationData, addr: u64, write: bool) {
+    while let Some(wp) = info.vm.wp.del(addr, write) {
+        let start = pg_align(PG_4K_SHIFT, wp.start);
+        let end   = pg_align(PG_4K_SHIFT, wp.end + PG_4KB as u64 - 1);
+
+        info.vm.ept.del(start, end, kind(write));
+        apply(info, start, end);
+    }
+}
+
+// Watch [addr, addr+len[ guest physical
+pub fn set_physical(info: &mut InformationData, addr: u64, len: usize, write: bool) -> VMMStatus {
+    let wp = Watchpoint { addr: addr, start: addr, end: addr + len as u64, write: write };
+
+    if len == 0 || !add(info, wp) {
+        log!("can not watch physical {:#x} ({} bytes)\n", addr, len);
+        return VMMStatus::Fail
+    }
+
+    VMMStatus::Done
+}
+
+// Watch [addr, addr+len[ guest linear, translated now: the guest
+// remapping the range is not followed
+pub fn set_linear(info: &mut InformationData, addr: u64, len: usize, write: bool) -> VMMStatus {
+    let mut done = 0;
+
+    // a rollback must not drop the existing one
+    if info.vm.wp.find(addr, write).is_some() {
+        return VMMStatus::Done
+    }
+
+    while done < len {
+        let vaddr = addr.wrapping_add(done as u64);
+
+        let (paddr, left) = match mem::physical(info, vaddr) {
+            Some(tr) => tr,
+            None => {
+                log!("can not watch unmapped linear {:#x}\n", vaddr);
+                remove(info, addr, write);
+                return VMMStatus::Fail
+            },
+        };
+
+        let sz = utils::min(left, len - done);
+        let wp = Watchpoint { addr: addr, start: paddr, end: paddr + sz as u64, write: write };
+
+        if !add(info, wp) {
+            log!("can not watch linear {:#x} ({} bytes)\n", addr, len);
+            remove(info, addr, write);
+            return VMMStatus::Fail
+        }
+
+        done += sz;
+    }
+
+    VMMStatus::Done
+}
+
+pub fn del(info: &mut InformationData, addr: u64) -> VMMStatus {
+    remove(info, addr, write);
+    VMMStatus::Done
+}
+
+// Access to a watched range, checked on every violation as the
+// page may belong to regions of other kinds too
+pub fn hit(info: &mut InformationData, qual: &ExitQualEPT, gpa: u64) -> bool {
+    if !qual.r() && !qual.w() {
+        return false
+    }
+
+    let wp = match info.vm.wp.hit(gpa, qual.w()) {
+        Some(wp) => wp,
+        None => return false,
+    };
+
+    log!("watchpoint {:#x} {} at {:#x} from rip {:#x}\n"
+         ,wp.addr, if qual.w() {"write"} else {"read"}
+         ,gpa, info.vm.vmcs.guest.rip.as_ref().as_u64());
+
+    // reported once the access is done
+    info.vm.gdb.watch = Some(wp.addr);
+    info.vm.gdb.write = wp.write;
+    true
+}
+
+// Violation on a page covering a watchpoint
+pub fn violation(_info: &mut InformationData, _region: &EPTRegion,
+                 _qual: &ExitQualEPT, _gpa: u64) -> EPTAction {
+    EPTAction::SingleStep
+}
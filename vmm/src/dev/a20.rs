@@ -0,0 +1,92 @@
+// A20 gate emulation
+//
+// Hardware A20 stays enabled (VMM runs above 1MB). Guest A20 state
+// is virtual: when off, EPT mirrors the 1MB wrap-around area.
+//
+// Guest can toggle it through system control port A, the keyboard
+// controller output port or BIOS INT 0x15.
+
+use vmx::exit::VMMStatus;
+use share::info::InformationData;
+use share::vmx::ept;
+use share::port;
+
+pub fn set(info: &mut InformationData, enable: bool) {
+    if info.vm.cpu.a20 == enable {
+        return
+    }
+
+    #[cfg(feature = "debug_a20")]
+    log!("a20 {}\n", if enable {"on"} else {"off"});
+
+    info.vm.cpu.a20 = enable;
+    ept::a20(enable);
+}
+
+fn bit(value: u8, msk: u8, on: bool) -> u8 {
+    if on { value | msk } else { value & !msk }
+}
+
+// System control port A
+pub fn sys_ctrl_read(info: &mut InformationData, p: u16, _size: usize, value: &mut u32) -> VMMStatus {
+    let native = port::read(p, 1) as u8;
+    *value = bit(native, port::SYS_CTRL_A_A20, info.vm.cpu.a20) as u32;
+    VMMStatus::Done
+}
+
+pub fn sys_ctrl_write(info: &mut InformationData, p: u16, _size: usize, value: u32) -> VMMStatus {
+    let value = value as u8;
+
+    set(info, value & port::SYS_CTRL_A_A20 != 0);
+
+    // keep hardware A20 enabled, forward fast reset
+    port::write(p, 1, bit(value, port::SYS_CTRL_A_A20, true) as u32);
+    VMMStatus::Done
+}
+
+// Keyboard controller
+pub fn kbc_data_read(info: &mut InformationData, p: u16, size: usize, value: &mut u32) -> VMMStatus {
+    let native = port::read(p, size);
+
+    *value = if info.vm.kbc.cmd == port::KBC_CMD_READ_OUTPUT {
+        info.vm.kbc.cmd = 0;
+        bit(native as u8, port::KBC_OUTPUT_A20, info.vm.cpu.a20) as u32
+    } else {
+        native
+    };
+
+    VMMStatus::Done
+}
+
+pub fn kbc_data_write(info: &mut InformationData, p: u16, size: usize, value: u32) -> VMMStatus {
+    if info.vm.kbc.cmd == port::KBC_CMD_WRITE_OUTPUT {
+        // XXX: output port reset bit is ignored
+        info.vm.kbc.cmd = 0;
+        set(info, value as u8 & port::KBC_OUTPUT_A20 != 0);
+    } else {
+        port::write(p, size, value);
+    }
+
+    VMMStatus::Done
+}
+
+pub fn kbc_cmd_read(_info: &mut InformationData, p: u16, size: usize, value: &mut u32) -> VMMStatus {
+    *value = port::read(p, size);
+    VMMStatus::Done
+}
+
+pub fn kbc_cmd_write(info: &mut InformationData, p: u16, size: usize, value: u32) -> VMMStatus {
+    let cmd = value as u8;
+
+    match cmd {
+        port::KBC_CMD_WRITE_OUTPUT => info.vm.kbc.cmd = cmd,
+        port::KBC_CMD_A20_OFF      => set(info, false),
+        port::KBC_CMD_A20_ON       => set(info, true),
+        _ => {
+            info.vm.kbc.cmd = cmd;
+            port::write(p, size, value);
+        },
+    }
+
+    VMMStatus::Done
+}
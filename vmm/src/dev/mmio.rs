@@ -0,0 +1,91 @@
+// Emulated memory mapped devices
+//
+// Devices claim an inclusive guest physical range and are reached
+// by instructions emulated on MMIO regions. Accesses no device
+// claims go straight to hardware with the access size.
+
+use vmx::exit::VMMStatus;
+use share::info::InformationData;
+use share::rmode;
+use core::ptr;
+
+pub struct MmioDevice {
+    pub name:  &'static str,
+    pub start: u64,
+    pub end:   u64,
+    pub read:  fn(&mut InformationData, u64, usize, &mut u64) -> VMMStatus,
+    pub write: fn(&mut InformationData, u64, usize, u64) -> VMMStatus,
+}
+
+static DEVICES: &'static [MmioDevice] = &[
+    // legacy VGA window, never backed by RAM
+    MmioDevice { name: "vga", start: rmode::VGA_START, end: rmode::VGA_END - 1,
+                 read: native_read, write: native_write },
+];
+
+pub fn lookup(gpa: u64) -> Option<&'static MmioDevice> {
+    DEVICES.iter().find(|dev| dev.start <= gpa && gpa <= dev.end)
+}
+
+// Host physical behind gpa
+fn native(info: &mut InformationData, gpa: u64) -> Option<u64> {
+    info.vm.pg.translate(gpa).map(|tr| tr.paddr)
+}
+
+pub fn native_read(info: &mut InformationData, gpa: u64, size: usize, value: &mut u64) -> VMMStatus {
+    let hpa = match native(info, gpa) {
+        Some(hpa) => hpa,
+        None => return VMMStatus::Unmapped,
+    };
+
+    *value = unsafe {
+        match size {
+            1 => ptr::read_volatile(hpa as *const u8)  as u64,
+            2 => ptr::read_volatile(hpa as *const u16) as u64,
+            4 => ptr::read_volatile(hpa as *const u32) as u64,
+            _ => ptr::read_volatile(hpa as *const u64),
+        }
+    };
+
+    VMMStatus::Done
+}
+
+pub fn native_write(info: &mut InformationData, gpa: u64, size: usize, value: u64) -> VMMStatus {
+    let hpa = match native(info, gpa) {
+        Some(hpa) => hpa,
+        None => return VMMStatus::Unmapped,
+    };
+
+    unsafe {
+        match size {
+            1 => ptr::write_volatile(hpa as *mut u8,  value as u8),
+            2 => ptr::write_volatile(hpa as *mut u16, value as u16),
+            4 => ptr::write_volatile(hpa as *mut u32, value as u32),
+            _ => ptr::write_volatile(hpa as *mut u64, value),
+        }
+    }
+
+    VMMStatus::Done
+}
+
+pub fn read(info: &mut InformationData, gpa: u64, size: usize, value: &mut u64) -> VMMStatus {
+    match lookup(gpa) {
+        Some(dev) => {
+            #[cfg(feature = "debug_io")]
+            log!("{} read {:#x}\n", dev.name, gpa);
+            (dev.read)(info, gpa, size, value)
+        },
+        None => native_read(info, gpa, size, value),
+    }
+}
+
+pub fn write(info: &mut InformationData, gpa: u64, size: usize, value: u64) -> VMMStatus {
+    match lookup(gpa) {
+        Some(dev) => {
+            #[cfg(feature = "debug_io")]
+            log!("{} write {:#x} {:#x}\n", dev.name, gpa, value);
+            (dev.write)(info, gpa, size, value)
+        },
+        None => native_write(info, gpa, size, value),
+    }
+}
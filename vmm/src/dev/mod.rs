@@ -0,0 +1,60 @@
+// Emulated I/O port devices
+//
+// Devices claim an inclusive port range. Accesses to intercepted
+// ports no device claims go straight to hardware.
+
+use vmx::exit::VMMStatus;
+use share::info::InformationData;
+use share::port;
+
+pub mod a20;
+pub mod mmio;
+
+pub struct PortDevice {
+    pub name:  &'static str,
+    pub start: u16,
+    pub end:   u16,
+    pub read:  fn(&mut InformationData, u16, usize, &mut u32) -> VMMStatus,
+    pub write: fn(&mut InformationData, u16, usize, u32) -> VMMStatus,
+}
+
+static DEVICES: &'static [PortDevice] = &[
+    PortDevice { name: "kbc data", start: port::KBC_DATA, end: port::KBC_DATA,
+                 read: a20::kbc_data_read, write: a20::kbc_data_write },
+    PortDevice { name: "kbc cmd", start: port::KBC_CMD, end: port::KBC_CMD,
+                 read: a20::kbc_cmd_read, write: a20::kbc_cmd_write },
+    PortDevice { name: "sys ctrl a", start: port::SYS_CTRL_A, end: port::SYS_CTRL_A,
+                 read: a20::sys_ctrl_read, write: a20::sys_ctrl_write },
+];
+
+fn lookup(port: u16) -> Option<&'static PortDevice> {
+    DEVICES.iter().find(|dev| dev.start <= port && port <= dev.end)
+}
+
+pub fn read(info: &mut InformationData, port: u16, size: usize, value: &mut u32) -> VMMStatus {
+    match lookup(port) {
+        Some(dev) => {
+            #[cfg(feature = "debug_io")]
+            log!("{} in {:#x}\n", dev.name, port);
+            (dev.read)(info, port, size, value)
+        },
+        None => {
+            *value = port::read(port, size);
+            VMMStatus::Done
+        },
+    }
+}
+
+pub fn write(info: &mut InformationData, port: u16, size: usize, value: u32) -> VMMStatus {
+    match lookup(port) {
+        Some(dev) => {
+            #[cfg(feature = "debug_io")]
+            log!("{} out {:#x} {:#x}\n", dev.name, port, value);
+            (dev.write)(info, port, size, value)
+        },
+        None => {
+            port::write(port, size, value);
+            VMMStatus::Done
+        },
+    }
+}
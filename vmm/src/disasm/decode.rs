@@ -0,0 +1,1145 @@
+// x86 instruction decoder
+//
+// Handles legacy prefixes, REX, ModRM/SIB, displacements and
+// immediates for 16, 32 and 64 bits code. General purpose and
+// system instructions are decoded, SSE/AVX/x87 specifics are not
+// (x87 is only sized).
+
+use disasm::insn::*;
+use vm::seg;
+use share::cpu::INSN_MAX_LEN;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DecodeError {
+    Truncated,         // more bytes needed
+    TooLong,           // more than INSN_MAX_LEN bytes
+    Unsupported(u8),   // opcode (0x0f map: second byte)
+}
+
+type DecodeResult<T> = Result<T, DecodeError>;
+
+// REX bits
+const REX_W: u8 = 1<<3;
+const REX_R: u8 = 1<<2;
+const REX_X: u8 = 1<<1;
+const REX_B: u8 = 1<<0;
+
+const RAX: u8 = 0;
+const RCX: u8 = 1;
+const RDX: u8 = 2;
+const RBX: u8 = 3;
+const RSP: u8 = 4;
+const RBP: u8 = 5;
+const RSI: u8 = 6;
+const RDI: u8 = 7;
+
+const ALU: [Mnemonic; 8] = [
+    Mnemonic::Add, Mnemonic::Or,  Mnemonic::Adc, Mnemonic::Sbb,
+    Mnemonic::And, Mnemonic::Sub, Mnemonic::Xor, Mnemonic::Cmp,
+];
+
+const SHIFT: [Mnemonic; 8] = [
+    Mnemonic::Rol, Mnemonic::Ror, Mnemonic::Rcl, Mnemonic::Rcr,
+    Mnemonic::Shl, Mnemonic::Shr, Mnemonic::Shl, Mnemonic::Sar,
+];
+
+// Operand encodings (Intel SDM appendix A notation)
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Spec {
+    Eb, Ev, Ew, Ed, Gb, Gv,
+    M(usize),   // memory only, given size
+    Mp,         // far pointer in memory
+    Ib, Ibs, Iw, Iz, Iv,
+    AL, AX, DX, CL, One,
+    Zb, Zv,     // register in opcode low bits
+    Sw, Cd, Dd, Rd,
+    Ob, Ov, Xb, Xv, Yb, Yv,
+    Jb, Jz, Ap,
+}
+
+#[derive(Copy, Clone)]
+struct ModRM {
+    md:  u8,
+    reg: u8,
+    rm:  u8,
+}
+
+struct Decoder<'a> {
+    bytes:    &'a [u8],
+    pos:      usize,
+    mode:     usize,
+    opsz:     usize,
+    adsz:     usize,
+    opsz_pfx: bool,
+    seg:      Option<u8>,
+    rep:      Repeat,
+    lock:     bool,
+    rex:      u8,
+    modrm:    Option<ModRM>,
+}
+
+impl<'a> Decoder<'a> {
+    fn byte(&mut self) -> DecodeResult<u8> {
+        if self.pos >= INSN_MAX_LEN {
+            return Err(DecodeError::TooLong)
+        }
+
+        match self.bytes.get(self.pos) {
+            Some(b) => {
+                self.pos += 1;
+                Ok(*b)
+            },
+            None => Err(DecodeError::Truncated),
+        }
+    }
+
+    // little endian, not sign extended
+    fn raw(&mut self, size: usize) -> DecodeResult<u64> {
+        let mut value = 0;
+
+        for n in 0..size {
+            value |= (self.byte()? as u64) << (n*8);
+        }
+
+        Ok(value)
+    }
+
+    fn signed(&mut self, size: usize) -> DecodeResult<i64> {
+        let shift = 64 - size*8;
+        Ok(((self.raw(size)? << shift) as i64) >> shift)
+    }
+
+    fn prefixes(&mut self) -> DecodeResult<u8> {
+        loop {
+            let b = self.byte()?;
+
+            if self.mode == 8 && b & 0xf0 == 0x40 {
+                self.rex = b;
+                continue
+            }
+
+            match b {
+                0x26 => self.seg = Some(seg::ES),
+                0x2e => self.seg = Some(seg::CS),
+                0x36 => self.seg = Some(seg::SS),
+                0x3e => self.seg = Some(seg::DS),
+                0x64 => self.seg = Some(seg::FS),
+                0x65 => self.seg = Some(seg::GS),
+                0x66 => self.opsz_pfx = true,
+                0x67 => self.adsz = match self.mode { 2 => 4, 4 => 2, _ => 4 },
+                0xf0 => self.lock = true,
+                0xf2 => self.rep = Repeat::Repne,
+                0xf3 => self.rep = Repeat::Rep,
+                _ => return Ok(b),
+            }
+
+            // REX must immediately precede the opcode
+            self.rex = 0;
+        }
+    }
+
+    fn operand_size(&mut self) {
+        self.opsz = if self.rex & REX_W != 0 {
+            8
+        } else if self.opsz_pfx {
+            if self.mode == 2 { 4 } else { 2 }
+        } else {
+            if self.mode == 2 { 2 } else { 4 }
+        };
+    }
+
+    // Near branches, push/pop and friends
+    fn default64(&mut self) {
+        if self.mode == 8 && self.rex & REX_W == 0 {
+            self.opsz = if self.opsz_pfx { 2 } else { 8 };
+        }
+    }
+
+    fn no64(&self, op: u8) -> DecodeResult<()> {
+        if self.mode == 8 { Err(DecodeError::Unsupported(op)) } else { Ok(()) }
+    }
+
+    fn modrm(&mut self) -> DecodeResult<ModRM> {
+        if let Some(m) = self.modrm {
+            return Ok(m)
+        }
+
+        let b = self.byte()?;
+        let m = ModRM { md: b >> 6, reg: (b >> 3) & 7, rm: b & 7 };
+        self.modrm = Some(m);
+        Ok(m)
+    }
+
+    fn gpr(&self, idx: u8, size: usize) -> Register {
+        if size == 1 && self.rex == 0 && idx >= 4 && idx < 8 {
+            Register::GprHigh(idx - 4)
+        } else {
+            Register::Gpr(idx, size)
+        }
+    }
+
+    fn seg_or(&self, dft: u8) -> u8 {
+        self.seg.unwrap_or(dft)
+    }
+
+    fn memory16(&mut self, m: ModRM) -> DecodeResult<Memory> {
+        let (base, index) = match m.rm {
+            0 => (Some(RBX), Some(RSI)),
+            1 => (Some(RBX), Some(RDI)),
+            2 => (Some(RBP), Some(RSI)),
+            3 => (Some(RBP), Some(RDI)),
+            4 => (None,      Some(RSI)),
+            5 => (None,      Some(RDI)),
+            6 => if m.md == 0 { (None, None) } else { (Some(RBP), None) },
+            _ => (Some(RBX), None),
+        };
+
+        let disp = match m.md {
+            0 if m.rm == 6 => self.signed(2)?,
+            1 => self.signed(1)?,
+            2 => self.signed(2)?,
+            _ => 0,
+        };
+
+        let dft = if base == Some(RBP) { seg::SS } else { seg::DS };
+
+        Ok(Memory {
+            seg:   self.seg_or(dft),
+            base:  base.map(|r| Register::Gpr(r, 2)),
+            index: index.map(|r| Register::Gpr(r, 2)),
+            scale: 1,
+            disp:  disp,
+        })
+    }
+
+    fn memory(&mut self, m: ModRM) -> DecodeResult<Memory> {
+        if self.adsz == 2 {
+            return self.memory16(m)
+        }
+
+        let rex_b = if self.rex & REX_B != 0 { 8 } else { 0 };
+        let rex_x = if self.rex & REX_X != 0 { 8 } else { 0 };
+
+        let mut base  = None;
+        let mut index = None;
+        let mut scale = 1;
+        let mut disp  = 0;
+        let mut rip   = false;
+
+        if m.rm == 4 {
+            let sib = self.byte()?;
+            let idx = (sib >> 3) & 7 | rex_x;
+
+            scale = 1 << (sib >> 6);
+
+            if idx != RSP {
+                index = Some(idx);
+            }
+
+            if sib & 7 == RBP && m.md == 0 {
+                disp = self.signed(4)?;
+            } else {
+                base = Some(sib & 7 | rex_b);
+            }
+        } else if m.rm == 5 && m.md == 0 {
+            rip  = self.mode == 8;
+            disp = self.signed(4)?;
+        } else {
+            base = Some(m.rm | rex_b);
+        }
+
+        match m.md {
+            1 => disp = self.signed(1)?,
+            2 => disp = self.signed(4)?,
+            _ => (),
+        }
+
+        let dft = match base {
+            Some(RSP) | Some(RBP) => seg::SS,
+            _ => seg::DS,
+        };
+
+        let adsz = self.adsz;
+
+        Ok(Memory {
+            seg:   self.seg_or(dft),
+            base:  if rip { Some(Register::Rip) } else { base.map(|r| Register::Gpr(r, adsz)) },
+            index: index.map(|r| Register::Gpr(r, adsz)),
+            scale: scale,
+            disp:  disp,
+        })
+    }
+
+    fn rm(&mut self, size: usize) -> DecodeResult<OperandKind> {
+        let m = self.modrm()?;
+
+        if m.md == 3 {
+            let idx = m.rm | if self.rex & REX_B != 0 { 8 } else { 0 };
+            Ok(OperandKind::Reg(self.gpr(idx, size)))
+        } else {
+            Ok(OperandKind::Mem(self.memory(m)?))
+        }
+    }
+
+    fn mem_only(&mut self, op: u8) -> DecodeResult<OperandKind> {
+        let m = self.modrm()?;
+
+        if m.md == 3 {
+            return Err(DecodeError::Unsupported(op))
+        }
+
+        Ok(OperandKind::Mem(self.memory(m)?))
+    }
+
+    fn reg_field(&mut self) -> DecodeResult<u8> {
+        let m = self.modrm()?;
+        Ok(m.reg | if self.rex & REX_R != 0 { 8 } else { 0 })
+    }
+
+    fn string(&self, src: bool) -> Memory {
+        let (seg, reg) = if src {
+            (self.seg_or(seg::DS), RSI)
+        } else {
+            (seg::ES, RDI)
+        };
+
+        Memory {
+            seg:   seg,
+            base:  Some(Register::Gpr(reg, self.adsz)),
+            index: None,
+            scale: 1,
+            disp:  0,
+        }
+    }
+
+    fn operand(&mut self, spec: Spec, op: u8) -> DecodeResult<Operand> {
+        let opsz = self.opsz;
+        let gp_cr = if self.mode == 8 { 8 } else { 4 };
+
+        let (kind, size) = match spec {
+            Spec::Eb   => (self.rm(1)?, 1),
+            Spec::Ev   => (self.rm(opsz)?, opsz),
+            Spec::Ew   => (self.rm(2)?, 2),
+            Spec::Ed   => (self.rm(4)?, 4),
+            Spec::Gb   => { let r = self.reg_field()?; (OperandKind::Reg(self.gpr(r, 1)), 1) },
+            Spec::Gv   => { let r = self.reg_field()?; (OperandKind::Reg(self.gpr(r, opsz)), opsz) },
+            Spec::M(n) => (self.mem_only(op)?, n),
+            Spec::Mp   => (self.mem_only(op)?, opsz + 2),
+
+            Spec::Ib   => (OperandKind::Imm(self.raw(1)?), 1),
+            Spec::Iw   => (OperandKind::Imm(self.raw(2)?), 2),
+            Spec::Ibs  => (OperandKind::Imm(self.signed(1)? as u64), opsz),
+            Spec::Iz   => {
+                let isz = if opsz == 2 { 2 } else { 4 };
+                (OperandKind::Imm(self.signed(isz)? as u64), opsz)
+            },
+            Spec::Iv   => (OperandKind::Imm(self.raw(opsz)?), opsz),
+
+            Spec::AL   => (OperandKind::Reg(Register::Gpr(RAX, 1)), 1),
+            Spec::AX   => (OperandKind::Reg(Register::Gpr(RAX, opsz)), opsz),
+            Spec::DX   => (OperandKind::Reg(Register::Gpr(RDX, 2)), 2),
+            Spec::CL   => (OperandKind::Reg(Register::Gpr(RCX, 1)), 1),
+            Spec::One  => (OperandKind::Imm(1), 1),
+
+            Spec::Zb | Spec::Zv => {
+                let size = if spec == Spec::Zb { 1 } else { opsz };
+                let idx  = op & 7 | if self.rex & REX_B != 0 { 8 } else { 0 };
+                (OperandKind::Reg(self.gpr(idx, size)), size)
+            },
+
+            Spec::Sw   => {
+                let m = self.modrm()?;
+                if m.reg > seg::GS {
+                    return Err(DecodeError::Unsupported(op))
+                }
+                (OperandKind::Reg(Register::Seg(m.reg)), 2)
+            },
+            Spec::Cd   => { let r = self.reg_field()?; (OperandKind::Reg(Register::Cr(r)), gp_cr) },
+            Spec::Dd   => { let r = self.reg_field()?; (OperandKind::Reg(Register::Dr(r)), gp_cr) },
+            Spec::Rd   => {
+                // mod field is ignored
+                let m   = self.modrm()?;
+                let idx = m.rm | if self.rex & REX_B != 0 { 8 } else { 0 };
+                (OperandKind::Reg(Register::Gpr(idx, gp_cr)), gp_cr)
+            },
+
+            Spec::Ob | Spec::Ov => {
+                let size = if spec == Spec::Ob { 1 } else { opsz };
+                let adsz = self.adsz;
+                let mem  = Memory {
+                    seg:   self.seg_or(seg::DS),
+                    base:  None,
+                    index: None,
+                    scale: 1,
+                    disp:  self.raw(adsz)? as i64,
+                };
+                (OperandKind::Mem(mem), size)
+            },
+
+            Spec::Xb => (OperandKind::Mem(self.string(true)), 1),
+            Spec::Xv => (OperandKind::Mem(self.string(true)), opsz),
+            Spec::Yb => (OperandKind::Mem(self.string(false)), 1),
+            Spec::Yv => (OperandKind::Mem(self.string(false)), opsz),
+
+            Spec::Jb => (OperandKind::Rel(self.signed(1)?), opsz),
+            Spec::Jz => {
+                let isz = if opsz == 2 { 2 } else { 4 };
+                (OperandKind::Rel(self.signed(isz)?), opsz)
+            },
+            Spec::Ap => {
+                let isz = if opsz == 2 { 2 } else { 4 };
+                let off = self.raw(isz)? as u32;
+                let sel = self.raw(2)? as u16;
+                (OperandKind::Far(sel, off), isz + 2)
+            },
+        };
+
+        Ok(Operand { kind: kind, size: size })
+    }
+
+    fn operands(&mut self, specs: &[Spec], op: u8) -> DecodeResult<[Operand; 3]> {
+        let mut ops = [Operand::none(); 3];
+
+        for (n, spec) in specs.iter().enumerate() {
+            ops[n] = self.operand(*spec, op)?;
+        }
+
+        Ok(ops)
+    }
+
+    fn one_byte(&mut self, op: u8) -> DecodeResult<(Mnemonic, [Operand; 3])> {
+        use self::Spec::*;
+
+        let (mnemonic, specs): (Mnemonic, &[Spec]) = match op {
+            0x00...0x3f if op & 7 < 6 => {
+                let mnemonic = ALU[(op >> 3) as usize];
+                let specs: &[Spec] = match op & 7 {
+                    0 => &[Eb, Gb],
+                    1 => &[Ev, Gv],
+                    2 => &[Gb, Eb],
+                    3 => &[Gv, Ev],
+                    4 => &[AL, Ib],
+                    _ => &[AX, Iz],
+                };
+                (mnemonic, specs)
+            },
+
+            0x06 | 0x0e | 0x16 | 0x1e | 0x07 | 0x17 | 0x1f => {
+                self.no64(op)?;
+                let sreg = Operand {
+                    kind: OperandKind::Reg(Register::Seg(op >> 3)),
+                    size: 2,
+                };
+                let mnemonic = if op & 1 == 0 { Mnemonic::Push } else { Mnemonic::Pop };
+                return Ok((mnemonic, [sreg, Operand::none(), Operand::none()]))
+            },
+
+            0x40...0x47 => (Mnemonic::Inc, &[Zv]),
+            0x48...0x4f => (Mnemonic::Dec, &[Zv]),
+            0x50...0x57 => { self.default64(); (Mnemonic::Push, &[Zv]) },
+            0x58...0x5f => { self.default64(); (Mnemonic::Pop, &[Zv]) },
+            0x60 => { self.no64(op)?; (Mnemonic::Pusha, &[]) },
+            0x61 => { self.no64(op)?; (Mnemonic::Popa, &[]) },
+            0x63 if self.mode == 8 => (Mnemonic::Movsxd, &[Gv, Ed]),
+            0x68 => { self.default64(); (Mnemonic::Push, &[Iz]) },
+            0x69 => (Mnemonic::Imul, &[Gv, Ev, Iz]),
+            0x6a => { self.default64(); (Mnemonic::Push, &[Ibs]) },
+            0x6b => (Mnemonic::Imul, &[Gv, Ev, Ibs]),
+            0x6c => (Mnemonic::Ins, &[Yb, DX]),
+            0x6d => { self.io_size(); (Mnemonic::Ins, &[Yv, DX]) },
+            0x6e => (Mnemonic::Outs, &[DX, Xb]),
+            0x6f => { self.io_size(); (Mnemonic::Outs, &[DX, Xv]) },
+            0x70...0x7f => { self.default64(); (Mnemonic::Jcc(op & 0xf), &[Jb]) },
+
+            0x80 => (self.group(&ALU)?, &[Eb, Ib]),
+            0x81 => (self.group(&ALU)?, &[Ev, Iz]),
+            0x82 => { self.no64(op)?; (self.group(&ALU)?, &[Eb, Ib]) },
+            0x83 => (self.group(&ALU)?, &[Ev, Ibs]),
+            0x84 => (Mnemonic::Test, &[Eb, Gb]),
+            0x85 => (Mnemonic::Test, &[Ev, Gv]),
+            0x86 => (Mnemonic::Xchg, &[Eb, Gb]),
+            0x87 => (Mnemonic::Xchg, &[Ev, Gv]),
+            0x88 => (Mnemonic::Mov, &[Eb, Gb]),
+            0x89 => (Mnemonic::Mov, &[Ev, Gv]),
+            0x8a => (Mnemonic::Mov, &[Gb, Eb]),
+            0x8b => (Mnemonic::Mov, &[Gv, Ev]),
+            0x8c => { self.store16()?; (Mnemonic::Mov, &[Ev, Sw]) },
+            0x8d => (Mnemonic::Lea, &[Gv, M(0)]),
+            0x8e => (Mnemonic::Mov, &[Sw, Ew]),
+            0x8f => {
+                self.default64();
+                if self.modrm()?.reg != 0 {
+                    return Err(DecodeError::Unsupported(op))
+                }
+                (Mnemonic::Pop, &[Ev])
+            },
+
+            0x90 if self.rex & REX_B == 0 => (Mnemonic::Nop, &[]),
+            0x90...0x97 => (Mnemonic::Xchg, &[Zv, AX]),
+            0x98 => (Mnemonic::Cbw, &[]),
+            0x99 => (Mnemonic::Cwd, &[]),
+            0x9a => { self.no64(op)?; (Mnemonic::CallFar, &[Ap]) },
+            0x9b => (Mnemonic::Wait, &[]),
+            0x9c => { self.default64(); (Mnemonic::Pushf, &[]) },
+            0x9d => { self.default64(); (Mnemonic::Popf, &[]) },
+            0x9e => (Mnemonic::Sahf, &[]),
+            0x9f => (Mnemonic::Lahf, &[]),
+
+            0xa0 => (Mnemonic::Mov, &[AL, Ob]),
+            0xa1 => (Mnemonic::Mov, &[AX, Ov]),
+            0xa2 => (Mnemonic::Mov, &[Ob, AL]),
+            0xa3 => (Mnemonic::Mov, &[Ov, AX]),
+            0xa4 => (Mnemonic::Movs, &[Yb, Xb]),
+            0xa5 => (Mnemonic::Movs, &[Yv, Xv]),
+            0xa6 => (Mnemonic::Cmps, &[Xb, Yb]),
+            0xa7 => (Mnemonic::Cmps, &[Xv, Yv]),
+            0xa8 => (Mnemonic::Test, &[AL, Ib]),
+            0xa9 => (Mnemonic::Test, &[AX, Iz]),
+            0xaa => (Mnemonic::Stos, &[Yb, AL]),
+            0xab => (Mnemonic::Stos, &[Yv, AX]),
+            0xac => (Mnemonic::Lods, &[AL, Xb]),
+            0xad => (Mnemonic::Lods, &[AX, Xv]),
+            0xae => (Mnemonic::Scas, &[AL, Yb]),
+            0xaf => (Mnemonic::Scas, &[AX, Yv]),
+
+            0xb0...0xb7 => (Mnemonic::Mov, &[Zb, Ib]),
+            0xb8...0xbf => (Mnemonic::Mov, &[Zv, Iv]),
+
+            0xc0 => (self.group(&SHIFT)?, &[Eb, Ib]),
+            0xc1 => (self.group(&SHIFT)?, &[Ev, Ib]),
+            0xc2 => { self.default64(); (Mnemonic::Ret, &[Iw]) },
+            0xc3 => { self.default64(); (Mnemonic::Ret, &[]) },
+            0xc4 => { self.no64(op)?; (Mnemonic::Les, &[Gv, Mp]) },
+            0xc5 => { self.no64(op)?; (Mnemonic::Lds, &[Gv, Mp]) },
+            0xc6 | 0xc7 => {
+                if self.modrm()?.reg != 0 {
+                    return Err(DecodeError::Unsupported(op))
+                }
+                if op == 0xc6 {
+                    (Mnemonic::Mov, &[Eb, Ib])
+                } else {
+                    (Mnemonic::Mov, &[Ev, Iz])
+                }
+            },
+            0xc8 => { self.default64(); (Mnemonic::Enter, &[Iw, Ib]) },
+            0xc9 => { self.default64(); (Mnemonic::Leave, &[]) },
+            0xca => (Mnemonic::RetFar, &[Iw]),
+            0xcb => (Mnemonic::RetFar, &[]),
+            0xcc => (Mnemonic::Int3, &[]),
+            0xcd => (Mnemonic::Int, &[Ib]),
+            0xce => { self.no64(op)?; (Mnemonic::Into, &[]) },
+            0xcf => (Mnemonic::Iret, &[]),
+
+            0xd0 => (self.group(&SHIFT)?, &[Eb, One]),
+            0xd1 => (self.group(&SHIFT)?, &[Ev, One]),
+            0xd2 => (self.group(&SHIFT)?, &[Eb, CL]),
+            0xd3 => (self.group(&SHIFT)?, &[Ev, CL]),
+            0xd7 => (Mnemonic::Xlat, &[]),
+            0xd8...0xdf => {
+                // only sized: modrm and displacement
+                let m = self.modrm()?;
+                if m.md != 3 {
+                    self.memory(m)?;
+                }
+                (Mnemonic::Fpu, &[])
+            },
+
+            0xe0 => { self.default64(); (Mnemonic::Loopne, &[Jb]) },
+            0xe1 => { self.default64(); (Mnemonic::Loope, &[Jb]) },
+            0xe2 => { self.default64(); (Mnemonic::Loop, &[Jb]) },
+            0xe3 => { self.default64(); (Mnemonic::Jcxz, &[Jb]) },
+            0xe4 => (Mnemonic::In, &[AL, Ib]),
+            0xe5 => { self.io_size(); (Mnemonic::In, &[AX, Ib]) },
+            0xe6 => (Mnemonic::Out, &[Ib, AL]),
+            0xe7 => { self.io_size(); (Mnemonic::Out, &[Ib, AX]) },
+            0xe8 => { self.default64(); (Mnemonic::Call, &[Jz]) },
+            0xe9 => { self.default64(); (Mnemonic::Jmp, &[Jz]) },
+            0xea => { self.no64(op)?; (Mnemonic::JmpFar, &[Ap]) },
+            0xeb => { self.default64(); (Mnemonic::Jmp, &[Jb]) },
+            0xec => (Mnemonic::In, &[AL, DX]),
+            0xed => { self.io_size(); (Mnemonic::In, &[AX, DX]) },
+            0xee => (Mnemonic::Out, &[DX, AL]),
+            0xef => { self.io_size(); (Mnemonic::Out, &[DX, AX]) },
+
+            0xf4 => (Mnemonic::Hlt, &[]),
+            0xf5 => (Mnemonic::Cmc, &[]),
+            0xf6 | 0xf7 => {
+                let reg = self.modrm()?.reg;
+                let byte = op == 0xf6;
+                let mnemonic = match reg {
+                    0 | 1 => Mnemonic::Test,
+                    2 => Mnemonic::Not,
+                    3 => Mnemonic::Neg,
+                    4 => Mnemonic::Mul,
+                    5 => Mnemonic::Imul,
+                    6 => Mnemonic::Div,
+                    _ => Mnemonic::Idiv,
+                };
+                let specs: &[Spec] = match (reg < 2, byte) {
+                    (true, true)   => &[Eb, Ib],
+                    (true, false)  => &[Ev, Iz],
+                    (false, true)  => &[Eb],
+                    (false, false) => &[Ev],
+                };
+                (mnemonic, specs)
+            },
+            0xf8 => (Mnemonic::Clc, &[]),
+            0xf9 => (Mnemonic::Stc, &[]),
+            0xfa => (Mnemonic::Cli, &[]),
+            0xfb => (Mnemonic::Sti, &[]),
+            0xfc => (Mnemonic::Cld, &[]),
+            0xfd => (Mnemonic::Std, &[]),
+            0xfe => match self.modrm()?.reg {
+                0 => (Mnemonic::Inc, &[Eb]),
+                1 => (Mnemonic::Dec, &[Eb]),
+                _ => return Err(DecodeError::Unsupported(op)),
+            },
+            0xff => match self.modrm()?.reg {
+                0 => (Mnemonic::Inc, &[Ev]),
+                1 => (Mnemonic::Dec, &[Ev]),
+                2 => { self.default64(); (Mnemonic::Call, &[Ev]) },
+                3 => (Mnemonic::CallFar, &[Mp]),
+                4 => { self.default64(); (Mnemonic::Jmp, &[Ev]) },
+                5 => (Mnemonic::JmpFar, &[Mp]),
+                6 => { self.default64(); (Mnemonic::Push, &[Ev]) },
+                _ => return Err(DecodeError::Unsupported(op)),
+            },
+
+            _ => return Err(DecodeError::Unsupported(op)),
+        };
+
+        Ok((mnemonic, self.operands(specs, op)?))
+    }
+
+    fn two_bytes(&mut self, op: u8) -> DecodeResult<(Mnemonic, [Operand; 3])> {
+        use self::Spec::*;
+
+        let dtr = if self.mode == 8 { 10 } else { 6 };
+
+        let (mnemonic, specs): (Mnemonic, &[Spec]) = match op {
+            0x00 => match self.modrm()?.reg {
+                0 => { self.store16()?; (Mnemonic::Sldt, &[Ev]) },
+                1 => { self.store16()?; (Mnemonic::Str, &[Ev]) },
+                2 => (Mnemonic::Lldt, &[Ew]),
+                3 => (Mnemonic::Ltr, &[Ew]),
+                4 => (Mnemonic::Verr, &[Ew]),
+                5 => (Mnemonic::Verw, &[Ew]),
+                _ => return Err(DecodeError::Unsupported(op)),
+            },
+            0x01 => {
+                let m = self.modrm()?;
+                match (m.reg, m.md == 3) {
+                    (0, false) => (Mnemonic::Sgdt, &[M(dtr)]),
+                    (1, false) => (Mnemonic::Sidt, &[M(dtr)]),
+                    (2, false) => (Mnemonic::Lgdt, &[M(dtr)]),
+                    (3, false) => (Mnemonic::Lidt, &[M(dtr)]),
+                    (4, _)     => { self.store16()?; (Mnemonic::Smsw, &[Ev]) },
+                    (6, _)     => (Mnemonic::Lmsw, &[Ew]),
+                    (7, false) => (Mnemonic::Invlpg, &[M(1)]),
+                    _ => return Err(DecodeError::Unsupported(op)),
+                }
+            },
+            0x06 => (Mnemonic::Clts, &[]),
+            0x08 => (Mnemonic::Invd, &[]),
+            0x09 => (Mnemonic::Wbinvd, &[]),
+            0x0b => (Mnemonic::Ud2, &[]),
+            0x1f => (Mnemonic::Nop, &[Ev]),
+            0x20 => (Mnemonic::Mov, &[Rd, Cd]),
+            0x21 => (Mnemonic::Mov, &[Rd, Dd]),
+            0x22 => (Mnemonic::Mov, &[Cd, Rd]),
+            0x23 => (Mnemonic::Mov, &[Dd, Rd]),
+            0x30 => (Mnemonic::Wrmsr, &[]),
+            0x31 => (Mnemonic::Rdtsc, &[]),
+            0x32 => (Mnemonic::Rdmsr, &[]),
+            0x40...0x4f => (Mnemonic::Cmovcc(op & 0xf), &[Gv, Ev]),
+            0x80...0x8f => { self.default64(); (Mnemonic::Jcc(op & 0xf), &[Jz]) },
+            0x90...0x9f => (Mnemonic::Setcc(op & 0xf), &[Eb]),
+
+            0xa0 | 0xa1 | 0xa8 | 0xa9 => {
+                self.default64();
+                let sreg = Operand {
+                    kind: OperandKind::Reg(Register::Seg((op >> 3) & 7)),
+                    size: 2,
+                };
+                let mnemonic = if op & 1 == 0 { Mnemonic::Push } else { Mnemonic::Pop };
+                return Ok((mnemonic, [sreg, Operand::none(), Operand::none()]))
+            },
+
+            0xa2 => (Mnemonic::Cpuid, &[]),
+            0xa3 => (Mnemonic::Bt, &[Ev, Gv]),
+            0xa4 => (Mnemonic::Shld, &[Ev, Gv, Ib]),
+            0xa5 => (Mnemonic::Shld, &[Ev, Gv, CL]),
+            0xab => (Mnemonic::Bts, &[Ev, Gv]),
+            0xac => (Mnemonic::Shrd, &[Ev, Gv, Ib]),
+            0xad => (Mnemonic::Shrd, &[Ev, Gv, CL]),
+            0xaf => (Mnemonic::Imul, &[Gv, Ev]),
+            0xb0 => (Mnemonic::Cmpxchg, &[Eb, Gb]),
+            0xb1 => (Mnemonic::Cmpxchg, &[Ev, Gv]),
+            0xb2 => (Mnemonic::Lss, &[Gv, Mp]),
+            0xb3 => (Mnemonic::Btr, &[Ev, Gv]),
+            0xb4 => (Mnemonic::Lfs, &[Gv, Mp]),
+            0xb5 => (Mnemonic::Lgs, &[Gv, Mp]),
+            0xb6 => (Mnemonic::Movzx, &[Gv, Eb]),
+            0xb7 => (Mnemonic::Movzx, &[Gv, Ew]),
+            0xba => match self.modrm()?.reg {
+                4 => (Mnemonic::Bt, &[Ev, Ib]),
+                5 => (Mnemonic::Bts, &[Ev, Ib]),
+                6 => (Mnemonic::Btr, &[Ev, Ib]),
+                7 => (Mnemonic::Btc, &[Ev, Ib]),
+                _ => return Err(DecodeError::Unsupported(op)),
+            },
+            0xbb => (Mnemonic::Btc, &[Ev, Gv]),
+            0xbe => (Mnemonic::Movsx, &[Gv, Eb]),
+            0xbf => (Mnemonic::Movsx, &[Gv, Ew]),
+            0xc0 => (Mnemonic::Xadd, &[Eb, Gb]),
+            0xc1 => (Mnemonic::Xadd, &[Ev, Gv]),
+            0xc8...0xcf => (Mnemonic::Bswap, &[Zv]),
+
+            _ => return Err(DecodeError::Unsupported(op)),
+        };
+
+        Ok((mnemonic, self.operands(specs, op)?))
+    }
+
+    // Selector and status word stores: a memory destination is
+    // always 16 bits, a register one has the operand size
+    fn store16(&mut self) -> DecodeResult<()> {
+        if self.modrm()?.md != 3 {
+            self.opsz = 2;
+        }
+        Ok(())
+    }
+
+    // I/O accesses are at most 32 bits
+    fn io_size(&mut self) {
+        if self.opsz == 8 {
+            self.opsz = 4;
+        }
+    }
+
+    fn group(&mut self, table: &[Mnemonic; 8]) -> DecodeResult<Mnemonic> {
+        Ok(table[self.modrm()?.reg as usize])
+    }
+}
+
+// Decode first instruction of bytes, mode is the code size (2, 4 or 8)
+pub fn decode(bytes: &[u8], mode: usize) -> Result<Instruction, DecodeError> {
+    let mut dec = Decoder {
+        bytes:    bytes,
+        pos:      0,
+        mode:     mode,
+        opsz:     0,
+        adsz:     mode,
+        opsz_pfx: false,
+        seg:      None,
+        rep:      Repeat::None,
+        lock:     false,
+        rex:      0,
+        modrm:    None,
+    };
+
+    let op = dec.prefixes()?;
+    dec.operand_size();
+
+    let (mnemonic, ops) = if op == 0x0f {
+        let op2 = dec.byte()?;
+        dec.two_bytes(op2)?
+    } else {
+        dec.one_byte(op)?
+    };
+
+    Ok(Instruction {
+        mnemonic: mnemonic,
+        len:      dec.pos,
+        opsz:     dec.opsz,
+        adsz:     dec.adsz,
+        seg:      dec.seg,
+        rep:      dec.rep,
+        lock:     dec.lock,
+        ops:      ops,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use udis86::{self, ud, ud_operand};
+    use udis86::*;
+
+    const SAMPLES: usize = 200000;
+
+    const LEGACY: [u8; 11] = [0x26, 0x2e, 0x36, 0x3e, 0x64, 0x65,
+                              0x66, 0x67, 0xf0, 0xf2, 0xf3];
+
+    const CC: [&'static str; 16] = ["o", "no", "b", "ae", "z", "nz", "be", "a",
+                                    "s", "ns", "p", "np", "l", "ge", "le", "g"];
+
+    // Deterministic xorshift corpus biased towards prefixes and REX
+    struct Corpus(u64);
+
+    impl Corpus {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn bytes(&mut self, mode: usize) -> [u8; INSN_MAX_LEN] {
+            let mut bytes = [0u8; INSN_MAX_LEN];
+            let mut n = 0;
+
+            for _ in 0..self.next() % 4 {
+                bytes[n] = LEGACY[(self.next() % LEGACY.len() as u64) as usize];
+                n += 1;
+            }
+
+            if mode == 8 && self.next() % 2 == 0 {
+                bytes[n] = 0x40 | (self.next() & 0xf) as u8;
+                n += 1;
+            }
+
+            if self.next() % 3 == 0 {
+                bytes[n] = 0x0f;
+                n += 1;
+            }
+
+            for b in bytes[n..].iter_mut() {
+                *b = self.next() as u8;
+            }
+
+            bytes
+        }
+    }
+
+    fn mask(size: usize) -> u64 {
+        if size >= 8 { !0 } else { (1 << (size*8)) - 1 }
+    }
+
+    // udis86 value of bits size, sign extended
+    fn lval(op: &ud_operand, bits: u8) -> u64 {
+        unsafe {
+            match bits {
+                8  => op.lval.sbyte as u64,
+                16 => op.lval.sword as u64,
+                32 => op.lval.sdword as u64,
+                64 => op.lval.uqword,
+                _  => 0,
+            }
+        }
+    }
+
+    fn reg(r: Register) -> u32 {
+        match r {
+            Register::Gpr(i, 1) if i < 4 => UD_R_AL + i as u32,
+            Register::Gpr(i, 1) => UD_R_AL + 4 + i as u32,
+            Register::Gpr(i, 2) => UD_R_AX + i as u32,
+            Register::Gpr(i, 4) => UD_R_EAX + i as u32,
+            Register::Gpr(i, _) => UD_R_RAX + i as u32,
+            Register::GprHigh(i) => UD_R_AH + i as u32,
+            Register::Seg(s) => UD_R_ES + s as u32,
+            Register::Cr(n) => UD_R_CR0 + n as u32,
+            Register::Dr(n) => UD_R_DR0 + n as u32,
+            Register::Rip => UD_R_RIP,
+        }
+    }
+
+    fn opt_reg(r: Option<Register>) -> u32 {
+        r.map(reg).unwrap_or(UD_NONE)
+    }
+
+    // udis86 name of the instruction
+    fn name(insn: &Instruction) -> String {
+        let sized = |names: [&str; 4], size: usize| match size {
+            1 => names[0], 2 => names[1], 4 => names[2], _ => names[3],
+        }.to_string();
+
+        let strsz = match insn.mnemonic {
+            Mnemonic::Outs => insn.size(1),
+            _ => insn.size(0),
+        };
+
+        match insn.mnemonic {
+            Mnemonic::Jcc(cc)    => format!("j{}", CC[cc as usize]),
+            Mnemonic::Cmovcc(cc) => format!("cmov{}", CC[cc as usize]),
+            Mnemonic::Setcc(3)   => "setnb".to_string(),
+            Mnemonic::Setcc(cc)  => format!("set{}", CC[cc as usize]),
+            Mnemonic::Movs => sized(["movsb", "movsw", "movsd", "movsq"], strsz),
+            Mnemonic::Cmps => sized(["cmpsb", "cmpsw", "cmpsd", "cmpsq"], strsz),
+            Mnemonic::Stos => sized(["stosb", "stosw", "stosd", "stosq"], strsz),
+            Mnemonic::Lods => sized(["lodsb", "lodsw", "lodsd", "lodsq"], strsz),
+            Mnemonic::Scas => sized(["scasb", "scasw", "scasd", "scasq"], strsz),
+            Mnemonic::Ins  => sized(["insb",  "insw",  "insd",  "insd"],  strsz),
+            Mnemonic::Outs => sized(["outsb", "outsw", "outsd", "outsd"], strsz),
+            Mnemonic::Pusha => sized(["", "pusha", "pushad", ""], insn.opsz),
+            Mnemonic::Popa  => sized(["", "popa",  "popad",  ""], insn.opsz),
+            Mnemonic::Pushf => sized(["", "pushfw", "pushfd", "pushfq"], insn.opsz),
+            Mnemonic::Popf  => sized(["", "popfw",  "popfd",  "popfq"],  insn.opsz),
+            Mnemonic::Cbw   => sized(["", "cbw",   "cwde",   "cdqe"],   insn.opsz),
+            Mnemonic::Cwd   => sized(["", "cwd",   "cdq",    "cqo"],    insn.opsz),
+            Mnemonic::Iret  => sized(["", "iretw", "iretd",  "iretq"],  insn.opsz),
+            Mnemonic::Jcxz  => sized(["", "jcxz",  "jecxz",  "jrcxz"],  insn.adsz),
+            Mnemonic::Nop if insn.rep == Repeat::Rep && insn.kind(0) == OperandKind::None
+                => "pause".to_string(),
+            Mnemonic::Loopne  => "loopnz".to_string(),
+            Mnemonic::Xlat    => "xlatb".to_string(),
+            Mnemonic::JmpFar  => "jmp".to_string(),
+            Mnemonic::CallFar => "call".to_string(),
+            Mnemonic::RetFar  => "retf".to_string(),
+            m @ _ => format!("{:?}", m).to_lowercase(),
+        }
+    }
+
+    fn operand(ours: &Operand, theirs: &ud_operand) -> bool {
+        match (ours.kind, theirs.otype) {
+            (_, UD_NONE) => true, // implicit operand
+            (OperandKind::Reg(r), UD_OP_REG) => reg(r) == theirs.base,
+            (OperandKind::Mem(m), UD_OP_MEM) => {
+                // udis86 takes [si] and [di] as bases
+                let (base, index) = match (m.base, m.index) {
+                    (None, Some(r)) if m.scale == 1 && theirs.index == UD_NONE => (Some(r), None),
+                    (base, index) => (base, index),
+                };
+
+                let disp = if theirs.offset == 0 {
+                    m.disp == 0
+                } else {
+                    let sz = theirs.offset as usize / 8;
+                    m.disp as u64 & mask(sz) == lval(theirs, theirs.offset) & mask(sz)
+                };
+
+                disp && opt_reg(base) == theirs.base
+                     && opt_reg(index) == theirs.index
+                     && (index.is_none() || m.scale == theirs.scale.max(1))
+            },
+            (OperandKind::Imm(v), UD_OP_IMM) =>
+                v & mask(ours.size) == lval(theirs, theirs.size) & mask(ours.size),
+            (OperandKind::Imm(v), UD_OP_CONST) =>
+                v == unsafe { theirs.lval.udword as u64 },
+            (OperandKind::Rel(d), UD_OP_JIMM) => d as u64 == lval(theirs, theirs.size),
+            (OperandKind::Far(sel, off), UD_OP_PTR) => unsafe {
+                sel == theirs.lval.ptr.seg && off == theirs.lval.ptr.off
+            },
+            _ => false,
+        }
+    }
+
+    fn prefixes(insn: &Instruction, u: &ud) -> bool {
+        let seg = insn.seg.map(|s| UD_R_ES as u8 + s).unwrap_or(0);
+
+        let rep = match insn.rep {
+            Repeat::None  => u.pfx_rep == 0 && u.pfx_repne == 0,
+            Repeat::Rep   => u.pfx_rep != 0,
+            Repeat::Repne => u.pfx_repne != 0,
+        };
+
+        rep && seg == u.pfx_seg && insn.lock == (u.pfx_lock != 0)
+            && insn.adsz * 8 == u.adr_mode as usize
+    }
+
+    fn agree(insn: &Instruction, u: &ud) -> bool {
+        if insn.len != u.inp_ctr as usize || !prefixes(insn, u) {
+            return false
+        }
+
+        // x87 is only sized
+        if insn.mnemonic == Mnemonic::Fpu {
+            return udis86::mnemonic(u).starts_with('f')
+        }
+
+        name(insn) == udis86::mnemonic(u)
+            && insn.ops.iter().zip(u.operand.iter()).all(|(o, t)| operand(o, t))
+    }
+
+    // Position of the opcode, REX and operand size prefix
+    fn opcode(bytes: &[u8], mode: usize) -> (usize, u8, bool) {
+        let mut rex = 0;
+        let mut p66 = false;
+
+        for (n, b) in bytes.iter().enumerate() {
+            if mode == 8 && b & 0xf0 == 0x40 {
+                rex = *b;
+            } else if LEGACY.contains(b) {
+                p66 |= *b == 0x66;
+                rex = 0;
+            } else {
+                return (n, rex, p66)
+            }
+        }
+
+        (bytes.len(), rex, p66)
+    }
+
+    // Known udis86 divergences, such samples are not compared
+    fn quirk(bytes: &[u8], mode: usize, insn: &Instruction) -> bool {
+        let (n, rex, p66) = opcode(bytes, mode);
+        let two   = bytes[n] == 0x0f;
+        let modrm = bytes.get(n + if two { 2 } else { 1 }).map(|b| b >> 6);
+
+        let eip = insn.ops.iter().any(|o| match o.kind {
+            OperandKind::Mem(m) => m.base == Some(Register::Rip) && insn.adsz == 4,
+            _ => false,
+        });
+
+        let default64 = match insn.mnemonic {
+            Mnemonic::Push | Mnemonic::Pop | Mnemonic::Pushf | Mnemonic::Popf |
+            Mnemonic::Call | Mnemonic::Jmp | Mnemonic::Jcc(_) | Mnemonic::Ret |
+            Mnemonic::Enter | Mnemonic::Leave => true,
+            _ => false,
+        };
+
+        let rex_w_dropped = match insn.mnemonic {
+            Mnemonic::In | Mnemonic::Out | Mnemonic::Ins | Mnemonic::Outs |
+            Mnemonic::Lss | Mnemonic::Lfs | Mnemonic::Lgs => true,
+            _ => default64 && p66,
+        };
+
+        let cr_dr = insn.ops.iter().any(|o| match o.kind {
+            OperandKind::Reg(Register::Cr(_)) | OperandKind::Reg(Register::Dr(_)) => true,
+            _ => false,
+        });
+
+        let system = match insn.mnemonic {
+            Mnemonic::Sldt | Mnemonic::Str | Mnemonic::Smsw | Mnemonic::Lmsw => true,
+            _ => cr_dr || bytes[n] == 0x8c || bytes[n] == 0x8e,
+        };
+
+        // x87 is only sized
+        insn.mnemonic == Mnemonic::Fpu
+        // 0x67 RIP-relative addressing is EIP-relative, not absolute
+            || eip
+        // REX.W ignored on I/O, far pointer loads, and with 0x66 on
+        // default 64 bits operand size instructions
+            || (rex & REX_W != 0 && rex_w_dropped)
+        // 0x66 ignored on default 64 bits operand size instructions
+            || (mode == 8 && p66 && default64)
+        // REX masked on system register moves and stores
+            || (rex != 0 && system)
+        // mov to/from CR/DR ignores the mod field
+            || (cr_dr && modrm != Some(3))
+        // lmsw/smsw memory forms
+            || ((insn.mnemonic == Mnemonic::Lmsw || insn.mnemonic == Mnemonic::Smsw)
+                && modrm != Some(3))
+        // register form of 0f 1f nop
+            || (insn.mnemonic == Mnemonic::Nop && two && modrm == Some(3))
+        // 66/f2/f3 taken as mandatory SSE prefixes
+            || (insn.rep != Repeat::None && (two || insn.mnemonic == Mnemonic::Nop))
+            || (insn.mnemonic == Mnemonic::Xadd && p66)
+        // register width of mov to Sreg
+            || (insn.mnemonic == Mnemonic::Mov && insn.opsz != 2 && bytes[n] == 0x8e)
+    }
+
+    // Opcodes we deliberately do not decode while udis86 does: BCD
+    // and other legacy instructions, INT1, SIMD, fast system calls,
+    // cache and prefetch hints. Plus invalid encodings udis86 takes.
+    const LEFT_OUT: [u8; 10] = [0x27, 0x2f, 0x37, 0x3f, 0x62, 0x63, 0xd4, 0xd5, 0xd6, 0xf1];
+
+    fn left_out(bytes: &[u8], mode: usize) -> bool {
+        let (n, _, _) = opcode(bytes, mode);
+        let modrm = |at: usize| bytes.get(at).map(|b| (b >> 6, (b >> 3) & 7));
+
+        if bytes[n] != 0x0f {
+            return match (bytes[n], modrm(n + 1)) {
+                // segment registers 6 and 7, far call/jmp to a register
+                (0x8c, Some((_, reg))) | (0x8e, Some((_, reg))) => reg > 5,
+                (0xff, Some((3, 3))) | (0xff, Some((3, 5))) => true,
+                (op, _) => LEFT_OUT.contains(&op),
+            }
+        }
+
+        match (bytes.get(n + 1), modrm(n + 2)) {
+            (Some(&0x01), _) => bytes.get(n + 2) == Some(&0xc9), // mwait
+            (Some(&0xc7), Some((_, 1))) => true,                 // cmpxchg8b/16b
+            (Some(&0x02...0x03), _) | (Some(&0x05), _) | (Some(&0x07), _) |
+            (Some(&0x0d...0x0e), _) | (Some(&0x18...0x1e), _) |
+            (Some(&0x33...0x35), _) | (Some(&0xaa), _) | (Some(&0xae), _) |
+            (Some(&0xbc...0xbd), _) => true,
+            // MMX/SSE
+            (Some(&0x10...0x17), _) | (Some(&0x28...0x2f), _) |
+            (Some(&0x50...0x77), _) | (Some(&0x7c...0x7f), _) |
+            (Some(&0xc2...0xc6), _) | (Some(&0xd0...0xff), _) => true,
+            _ => false,
+        }
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn differential(mode: usize, seed: u64) {
+        let mut corpus = Corpus(seed);
+        let mut compared = 0;
+        let mut seen = [0; 4]; // prefixes, REX, SIB, immediates
+
+        for _ in 0..SAMPLES {
+            let bytes = corpus.bytes(mode);
+
+            let insn = match decode(&bytes, mode) {
+                Ok(insn) => insn,
+                Err(e) => {
+                    if let Some(u) = udis86::disassemble(&bytes, mode) {
+                        let bytes = &bytes[..u.inp_ctr as usize];
+                        let known = match e {
+                            DecodeError::Unsupported(_) => left_out(bytes, mode),
+                            _ => false,
+                        };
+
+                        assert!(known, "{}-bit {}: {:?} valid for udis86 {} {:?}",
+                                mode*8, hex(bytes), e, udis86::mnemonic(&u), ud_dump(&u));
+                    }
+                    continue
+                },
+            };
+
+            let bytes = &bytes[..insn.len];
+
+            if quirk(bytes, mode, &insn) {
+                continue
+            }
+
+            let u = match udis86::disassemble(bytes, mode) {
+                Some(u) => u,
+                None => panic!("{}-bit {}: {:?} invalid for udis86",
+                               mode*8, hex(bytes), insn),
+            };
+
+            assert!(agree(&insn, &u), "{}-bit {}: {:?} udis86 {} {:?}",
+                    mode*8, hex(bytes), insn, udis86::mnemonic(&u), ud_dump(&u));
+            compared += 1;
+
+            let (n, rex, _) = opcode(bytes, mode);
+            let legacy = bytes[..n].iter().any(|b| LEGACY.contains(b));
+            let sib = insn.ops.iter().any(|o| match o.kind {
+                OperandKind::Mem(m) => m.index.is_some() && insn.adsz != 2,
+                _ => false,
+            });
+            let imm = insn.ops.iter().any(|o| match o.kind {
+                OperandKind::Imm(_) | OperandKind::Rel(_) | OperandKind::Far(..) => true,
+                _ => false,
+            });
+
+            for (c, hit) in seen.iter_mut().zip([legacy, rex != 0, sib, imm].iter()) {
+                *c += *hit as usize;
+            }
+        }
+
+        // most samples are general purpose instructions
+        assert!(compared > SAMPLES / 4, "{}-bit: {} compared", mode*8, compared);
+        assert!(seen[0] > 0 && seen[2] > 0 && seen[3] > 0 && (mode != 8 || seen[1] > 0),
+                "{}-bit: coverage {:?}", mode*8, seen);
+    }
+
+    fn ud_dump(u: &ud) -> Vec<(u32, u8, u32, u32, u8, u8, u64)> {
+        u.operand.iter().map(|o| (o.otype, o.size, o.base, o.index,
+                                  o.offset, o.scale, unsafe { o.lval.uqword })).collect()
+    }
+
+    #[test]
+    fn udis86_16() { differential(2, 0x1234567); }
+
+    #[test]
+    fn udis86_32() { differential(4, 0x89abcdef); }
+
+    #[test]
+    fn udis86_64() { differential(8, 0xdeadbeef); }
+}
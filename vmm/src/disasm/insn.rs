@@ -0,0 +1,90 @@
+// Decoded instruction model
+//
+// Sizes are in bytes. Memory operands carry their resolved segment
+// (override or default one), immediates are already sign extended
+// to the operand size when the encoding says so.
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Mnemonic {
+    Add, Or, Adc, Sbb, And, Sub, Xor, Cmp, Test,
+    Inc, Dec, Not, Neg, Mul, Imul, Div, Idiv,
+    Rol, Ror, Rcl, Rcr, Shl, Shr, Sar, Shld, Shrd,
+    Bt, Bts, Btr, Btc, Bswap, Cmpxchg, Xadd,
+    Mov, Movzx, Movsx, Movsxd, Lea, Xchg, Cmovcc(u8), Setcc(u8),
+    Push, Pop, Pusha, Popa, Pushf, Popf, Lahf, Sahf,
+    Cbw, Cwd, Xlat, Enter, Leave,
+    Movs, Stos, Lods, Cmps, Scas, Ins, Outs, In, Out,
+    Jcc(u8), Jmp, JmpFar, Call, CallFar, Ret, RetFar,
+    Loop, Loope, Loopne, Jcxz,
+    Int, Int3, Into, Iret, Hlt, Nop, Wait, Fpu,
+    Clc, Stc, Cmc, Cli, Sti, Cld, Std,
+    Lgdt, Lidt, Sgdt, Sidt, Lldt, Sldt, Ltr, Str, Lmsw, Smsw,
+    Invlpg, Clts, Invd, Wbinvd, Verr, Verw,
+    Cpuid, Rdmsr, Wrmsr, Rdtsc, Ud2,
+    Les, Lds, Lss, Lfs, Lgs,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Register {
+    Gpr(u8, usize),  // index, size
+    GprHigh(u8),     // AH, CH, DH, BH
+    Seg(u8),
+    Cr(u8),
+    Dr(u8),
+    Rip,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Memory {
+    pub seg:   u8,
+    pub base:  Option<Register>,
+    pub index: Option<Register>,
+    pub scale: u8,
+    pub disp:  i64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum OperandKind {
+    None,
+    Reg(Register),
+    Mem(Memory),
+    Imm(u64),
+    Rel(i64),        // relative to next instruction
+    Far(u16, u32),   // selector, offset
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Operand {
+    pub kind: OperandKind,
+    pub size: usize,
+}
+
+impl Operand {
+    pub fn none() -> Operand {
+        Operand { kind: OperandKind::None, size: 0 }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Repeat {
+    None,
+    Rep,    // rep/repe
+    Repne,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Instruction {
+    pub mnemonic: Mnemonic,
+    pub len:      usize,
+    pub opsz:     usize,
+    pub adsz:     usize,
+    pub seg:      Option<u8>,  // segment override
+    pub rep:      Repeat,
+    pub lock:     bool,
+    pub ops:      [Operand; 3],
+}
+
+impl Instruction {
+    pub fn size(&self, n: usize) -> usize { self.ops[n].size }
+    pub fn kind(&self, n: usize) -> OperandKind { self.ops[n].kind }
+}
@@ -1,1062 +0,0 @@
-#[repr(u32)]
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
-pub enum ud_table_type {
-    UD_TAB__OPC_VEX = 0,
-    UD_TAB__OPC_TABLE = 1,
-    UD_TAB__OPC_X87 = 2,
-    UD_TAB__OPC_MOD = 3,
-    UD_TAB__OPC_RM = 4,
-    UD_TAB__OPC_OSIZE = 5,
-    UD_TAB__OPC_MODE = 6,
-    UD_TAB__OPC_VEX_L = 7,
-    UD_TAB__OPC_3DNOW = 8,
-    UD_TAB__OPC_REG = 9,
-    UD_TAB__OPC_ASIZE = 10,
-    UD_TAB__OPC_VEX_W = 11,
-    UD_TAB__OPC_SSE = 12,
-    UD_TAB__OPC_VENDOR = 13,
-}
-
-#[repr(u32)]
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
-pub enum ud_mnemonic_code {
-    UD_Iaaa = 0,
-    UD_Iaad = 1,
-    UD_Iaam = 2,
-    UD_Iaas = 3,
-    UD_Iadc = 4,
-    UD_Iadd = 5,
-    UD_Iaddpd = 6,
-    UD_Iaddps = 7,
-    UD_Iaddsd = 8,
-    UD_Iaddss = 9,
-    UD_Iaddsubpd = 10,
-    UD_Iaddsubps = 11,
-    UD_Iaesdec = 12,
-    UD_Iaesdeclast = 13,
-    UD_Iaesenc = 14,
-    UD_Iaesenclast = 15,
-    UD_Iaesimc = 16,
-    UD_Iaeskeygenassist = 17,
-    UD_Iand = 18,
-    UD_Iandnpd = 19,
-    UD_Iandnps = 20,
-    UD_Iandpd = 21,
-    UD_Iandps = 22,
-    UD_Iarpl = 23,
-    UD_Iblendpd = 24,
-    UD_Iblendps = 25,
-    UD_Iblendvpd = 26,
-    UD_Iblendvps = 27,
-    UD_Ibound = 28,
-    UD_Ibsf = 29,
-    UD_Ibsr = 30,
-    UD_Ibswap = 31,
-    UD_Ibt = 32,
-    UD_Ibtc = 33,
-    UD_Ibtr = 34,
-    UD_Ibts = 35,
-    UD_Icall = 36,
-    UD_Icbw = 37,
-    UD_Icdq = 38,
-    UD_Icdqe = 39,
-    UD_Iclc = 40,
-    UD_Icld = 41,
-    UD_Iclflush = 42,
-    UD_Iclgi = 43,
-    UD_Icli = 44,
-    UD_Iclts = 45,
-    UD_Icmc = 46,
-    UD_Icmova = 47,
-    UD_Icmovae = 48,
-    UD_Icmovb = 49,
-    UD_Icmovbe = 50,
-    UD_Icmovg = 51,
-    UD_Icmovge = 52,
-    UD_Icmovl = 53,
-    UD_Icmovle = 54,
-    UD_Icmovno = 55,
-    UD_Icmovnp = 56,
-    UD_Icmovns = 57,
-    UD_Icmovnz = 58,
-    UD_Icmovo = 59,
-    UD_Icmovp = 60,
-    UD_Icmovs = 61,
-    UD_Icmovz = 62,
-    UD_Icmp = 63,
-    UD_Icmppd = 64,
-    UD_Icmpps = 65,
-    UD_Icmpsb = 66,
-    UD_Icmpsd = 67,
-    UD_Icmpsq = 68,
-    UD_Icmpss = 69,
-    UD_Icmpsw = 70,
-    UD_Icmpxchg = 71,
-    UD_Icmpxchg16b = 72,
-    UD_Icmpxchg8b = 73,
-    UD_Icomisd = 74,
-    UD_Icomiss = 75,
-    UD_Icpuid = 76,
-    UD_Icqo = 77,
-    UD_Icrc32 = 78,
-    UD_Icvtdq2pd = 79,
-    UD_Icvtdq2ps = 80,
-    UD_Icvtpd2dq = 81,
-    UD_Icvtpd2pi = 82,
-    UD_Icvtpd2ps = 83,
-    UD_Icvtpi2pd = 84,
-    UD_Icvtpi2ps = 85,
-    UD_Icvtps2dq = 86,
-    UD_Icvtps2pd = 87,
-    UD_Icvtps2pi = 88,
-    UD_Icvtsd2si = 89,
-    UD_Icvtsd2ss = 90,
-    UD_Icvtsi2sd = 91,
-    UD_Icvtsi2ss = 92,
-    UD_Icvtss2sd = 93,
-    UD_Icvtss2si = 94,
-    UD_Icvttpd2dq = 95,
-    UD_Icvttpd2pi = 96,
-    UD_Icvttps2dq = 97,
-    UD_Icvttps2pi = 98,
-    UD_Icvttsd2si = 99,
-    UD_Icvttss2si = 100,
-    UD_Icwd = 101,
-    UD_Icwde = 102,
-    UD_Idaa = 103,
-    UD_Idas = 104,
-    UD_Idec = 105,
-    UD_Idiv = 106,
-    UD_Idivpd = 107,
-    UD_Idivps = 108,
-    UD_Idivsd = 109,
-    UD_Idivss = 110,
-    UD_Idppd = 111,
-    UD_Idpps = 112,
-    UD_Iemms = 113,
-    UD_Ienter = 114,
-    UD_Iextractps = 115,
-    UD_If2xm1 = 116,
-    UD_Ifabs = 117,
-    UD_Ifadd = 118,
-    UD_Ifaddp = 119,
-    UD_Ifbld = 120,
-    UD_Ifbstp = 121,
-    UD_Ifchs = 122,
-    UD_Ifclex = 123,
-    UD_Ifcmovb = 124,
-    UD_Ifcmovbe = 125,
-    UD_Ifcmove = 126,
-    UD_Ifcmovnb = 127,
-    UD_Ifcmovnbe = 128,
-    UD_Ifcmovne = 129,
-    UD_Ifcmovnu = 130,
-    UD_Ifcmovu = 131,
-    UD_Ifcom = 132,
-    UD_Ifcom2 = 133,
-    UD_Ifcomi = 134,
-    UD_Ifcomip = 135,
-    UD_Ifcomp = 136,
-    UD_Ifcomp3 = 137,
-    UD_Ifcomp5 = 138,
-    UD_Ifcompp = 139,
-    UD_Ifcos = 140,
-    UD_Ifdecstp = 141,
-    UD_Ifdiv = 142,
-    UD_Ifdivp = 143,
-    UD_Ifdivr = 144,
-    UD_Ifdivrp = 145,
-    UD_Ifemms = 146,
-    UD_Iffree = 147,
-    UD_Iffreep = 148,
-    UD_Ifiadd = 149,
-    UD_Ificom = 150,
-    UD_Ificomp = 151,
-    UD_Ifidiv = 152,
-    UD_Ifidivr = 153,
-    UD_Ifild = 154,
-    UD_Ifimul = 155,
-    UD_Ifincstp = 156,
-    UD_Ifist = 157,
-    UD_Ifistp = 158,
-    UD_Ifisttp = 159,
-    UD_Ifisub = 160,
-    UD_Ifisubr = 161,
-    UD_Ifld = 162,
-    UD_Ifld1 = 163,
-    UD_Ifldcw = 164,
-    UD_Ifldenv = 165,
-    UD_Ifldl2e = 166,
-    UD_Ifldl2t = 167,
-    UD_Ifldlg2 = 168,
-    UD_Ifldln2 = 169,
-    UD_Ifldpi = 170,
-    UD_Ifldz = 171,
-    UD_Ifmul = 172,
-    UD_Ifmulp = 173,
-    UD_Ifndisi = 174,
-    UD_Ifneni = 175,
-    UD_Ifninit = 176,
-    UD_Ifnop = 177,
-    UD_Ifnsave = 178,
-    UD_Ifnsetpm = 179,
-    UD_Ifnstcw = 180,
-    UD_Ifnstenv = 181,
-    UD_Ifnstsw = 182,
-    UD_Ifpatan = 183,
-    UD_Ifprem = 184,
-    UD_Ifprem1 = 185,
-    UD_Ifptan = 186,
-    UD_Ifrndint = 187,
-    UD_Ifrstor = 188,
-    UD_Ifrstpm = 189,
-    UD_Ifscale = 190,
-    UD_Ifsin = 191,
-    UD_Ifsincos = 192,
-    UD_Ifsqrt = 193,
-    UD_Ifst = 194,
-    UD_Ifstp = 195,
-    UD_Ifstp1 = 196,
-    UD_Ifstp8 = 197,
-    UD_Ifstp9 = 198,
-    UD_Ifsub = 199,
-    UD_Ifsubp = 200,
-    UD_Ifsubr = 201,
-    UD_Ifsubrp = 202,
-    UD_Iftst = 203,
-    UD_Ifucom = 204,
-    UD_Ifucomi = 205,
-    UD_Ifucomip = 206,
-    UD_Ifucomp = 207,
-    UD_Ifucompp = 208,
-    UD_Ifxam = 209,
-    UD_Ifxch = 210,
-    UD_Ifxch4 = 211,
-    UD_Ifxch7 = 212,
-    UD_Ifxrstor = 213,
-    UD_Ifxsave = 214,
-    UD_Ifxtract = 215,
-    UD_Ifyl2x = 216,
-    UD_Ifyl2xp1 = 217,
-    UD_Igetsec = 218,
-    UD_Ihaddpd = 219,
-    UD_Ihaddps = 220,
-    UD_Ihlt = 221,
-    UD_Ihsubpd = 222,
-    UD_Ihsubps = 223,
-    UD_Iidiv = 224,
-    UD_Iimul = 225,
-    UD_Iin = 226,
-    UD_Iinc = 227,
-    UD_Iinsb = 228,
-    UD_Iinsd = 229,
-    UD_Iinsertps = 230,
-    UD_Iinsw = 231,
-    UD_Iint = 232,
-    UD_Iint1 = 233,
-    UD_Iint3 = 234,
-    UD_Iinto = 235,
-    UD_Iinvd = 236,
-    UD_Iinvept = 237,
-    UD_Iinvlpg = 238,
-    UD_Iinvlpga = 239,
-    UD_Iinvvpid = 240,
-    UD_Iiretd = 241,
-    UD_Iiretq = 242,
-    UD_Iiretw = 243,
-    UD_Ija = 244,
-    UD_Ijae = 245,
-    UD_Ijb = 246,
-    UD_Ijbe = 247,
-    UD_Ijcxz = 248,
-    UD_Ijecxz = 249,
-    UD_Ijg = 250,
-    UD_Ijge = 251,
-    UD_Ijl = 252,
-    UD_Ijle = 253,
-    UD_Ijmp = 254,
-    UD_Ijno = 255,
-    UD_Ijnp = 256,
-    UD_Ijns = 257,
-    UD_Ijnz = 258,
-    UD_Ijo = 259,
-    UD_Ijp = 260,
-    UD_Ijrcxz = 261,
-    UD_Ijs = 262,
-    UD_Ijz = 263,
-    UD_Ilahf = 264,
-    UD_Ilar = 265,
-    UD_Ilddqu = 266,
-    UD_Ildmxcsr = 267,
-    UD_Ilds = 268,
-    UD_Ilea = 269,
-    UD_Ileave = 270,
-    UD_Iles = 271,
-    UD_Ilfence = 272,
-    UD_Ilfs = 273,
-    UD_Ilgdt = 274,
-    UD_Ilgs = 275,
-    UD_Ilidt = 276,
-    UD_Illdt = 277,
-    UD_Ilmsw = 278,
-    UD_Ilock = 279,
-    UD_Ilodsb = 280,
-    UD_Ilodsd = 281,
-    UD_Ilodsq = 282,
-    UD_Ilodsw = 283,
-    UD_Iloop = 284,
-    UD_Iloope = 285,
-    UD_Iloopne = 286,
-    UD_Ilsl = 287,
-    UD_Ilss = 288,
-    UD_Iltr = 289,
-    UD_Imaskmovdqu = 290,
-    UD_Imaskmovq = 291,
-    UD_Imaxpd = 292,
-    UD_Imaxps = 293,
-    UD_Imaxsd = 294,
-    UD_Imaxss = 295,
-    UD_Imfence = 296,
-    UD_Iminpd = 297,
-    UD_Iminps = 298,
-    UD_Iminsd = 299,
-    UD_Iminss = 300,
-    UD_Imonitor = 301,
-    UD_Imontmul = 302,
-    UD_Imov = 303,
-    UD_Imovapd = 304,
-    UD_Imovaps = 305,
-    UD_Imovbe = 306,
-    UD_Imovd = 307,
-    UD_Imovddup = 308,
-    UD_Imovdq2q = 309,
-    UD_Imovdqa = 310,
-    UD_Imovdqu = 311,
-    UD_Imovhlps = 312,
-    UD_Imovhpd = 313,
-    UD_Imovhps = 314,
-    UD_Imovlhps = 315,
-    UD_Imovlpd = 316,
-    UD_Imovlps = 317,
-    UD_Imovmskpd = 318,
-    UD_Imovmskps = 319,
-    UD_Imovntdq = 320,
-    UD_Imovntdqa = 321,
-    UD_Imovnti = 322,
-    UD_Imovntpd = 323,
-    UD_Imovntps = 324,
-    UD_Imovntq = 325,
-    UD_Imovq = 326,
-    UD_Imovq2dq = 327,
-    UD_Imovsb = 328,
-    UD_Imovsd = 329,
-    UD_Imovshdup = 330,
-    UD_Imovsldup = 331,
-    UD_Imovsq = 332,
-    UD_Imovss = 333,
-    UD_Imovsw = 334,
-    UD_Imovsx = 335,
-    UD_Imovsxd = 336,
-    UD_Imovupd = 337,
-    UD_Imovups = 338,
-    UD_Imovzx = 339,
-    UD_Impsadbw = 340,
-    UD_Imul = 341,
-    UD_Imulpd = 342,
-    UD_Imulps = 343,
-    UD_Imulsd = 344,
-    UD_Imulss = 345,
-    UD_Imwait = 346,
-    UD_Ineg = 347,
-    UD_Inop = 348,
-    UD_Inot = 349,
-    UD_Ior = 350,
-    UD_Iorpd = 351,
-    UD_Iorps = 352,
-    UD_Iout = 353,
-    UD_Ioutsb = 354,
-    UD_Ioutsd = 355,
-    UD_Ioutsw = 356,
-    UD_Ipabsb = 357,
-    UD_Ipabsd = 358,
-    UD_Ipabsw = 359,
-    UD_Ipackssdw = 360,
-    UD_Ipacksswb = 361,
-    UD_Ipackusdw = 362,
-    UD_Ipackuswb = 363,
-    UD_Ipaddb = 364,
-    UD_Ipaddd = 365,
-    UD_Ipaddq = 366,
-    UD_Ipaddsb = 367,
-    UD_Ipaddsw = 368,
-    UD_Ipaddusb = 369,
-    UD_Ipaddusw = 370,
-    UD_Ipaddw = 371,
-    UD_Ipalignr = 372,
-    UD_Ipand = 373,
-    UD_Ipandn = 374,
-    UD_Ipavgb = 375,
-    UD_Ipavgusb = 376,
-    UD_Ipavgw = 377,
-    UD_Ipblendvb = 378,
-    UD_Ipblendw = 379,
-    UD_Ipclmulqdq = 380,
-    UD_Ipcmpeqb = 381,
-    UD_Ipcmpeqd = 382,
-    UD_Ipcmpeqq = 383,
-    UD_Ipcmpeqw = 384,
-    UD_Ipcmpestri = 385,
-    UD_Ipcmpestrm = 386,
-    UD_Ipcmpgtb = 387,
-    UD_Ipcmpgtd = 388,
-    UD_Ipcmpgtq = 389,
-    UD_Ipcmpgtw = 390,
-    UD_Ipcmpistri = 391,
-    UD_Ipcmpistrm = 392,
-    UD_Ipextrb = 393,
-    UD_Ipextrd = 394,
-    UD_Ipextrq = 395,
-    UD_Ipextrw = 396,
-    UD_Ipf2id = 397,
-    UD_Ipf2iw = 398,
-    UD_Ipfacc = 399,
-    UD_Ipfadd = 400,
-    UD_Ipfcmpeq = 401,
-    UD_Ipfcmpge = 402,
-    UD_Ipfcmpgt = 403,
-    UD_Ipfmax = 404,
-    UD_Ipfmin = 405,
-    UD_Ipfmul = 406,
-    UD_Ipfnacc = 407,
-    UD_Ipfpnacc = 408,
-    UD_Ipfrcp = 409,
-    UD_Ipfrcpit1 = 410,
-    UD_Ipfrcpit2 = 411,
-    UD_Ipfrsqit1 = 412,
-    UD_Ipfrsqrt = 413,
-    UD_Ipfsub = 414,
-    UD_Ipfsubr = 415,
-    UD_Iphaddd = 416,
-    UD_Iphaddsw = 417,
-    UD_Iphaddw = 418,
-    UD_Iphminposuw = 419,
-    UD_Iphsubd = 420,
-    UD_Iphsubsw = 421,
-    UD_Iphsubw = 422,
-    UD_Ipi2fd = 423,
-    UD_Ipi2fw = 424,
-    UD_Ipinsrb = 425,
-    UD_Ipinsrd = 426,
-    UD_Ipinsrq = 427,
-    UD_Ipinsrw = 428,
-    UD_Ipmaddubsw = 429,
-    UD_Ipmaddwd = 430,
-    UD_Ipmaxsb = 431,
-    UD_Ipmaxsd = 432,
-    UD_Ipmaxsw = 433,
-    UD_Ipmaxub = 434,
-    UD_Ipmaxud = 435,
-    UD_Ipmaxuw = 436,
-    UD_Ipminsb = 437,
-    UD_Ipminsd = 438,
-    UD_Ipminsw = 439,
-    UD_Ipminub = 440,
-    UD_Ipminud = 441,
-    UD_Ipminuw = 442,
-    UD_Ipmovmskb = 443,
-    UD_Ipmovsxbd = 444,
-    UD_Ipmovsxbq = 445,
-    UD_Ipmovsxbw = 446,
-    UD_Ipmovsxdq = 447,
-    UD_Ipmovsxwd = 448,
-    UD_Ipmovsxwq = 449,
-    UD_Ipmovzxbd = 450,
-    UD_Ipmovzxbq = 451,
-    UD_Ipmovzxbw = 452,
-    UD_Ipmovzxdq = 453,
-    UD_Ipmovzxwd = 454,
-    UD_Ipmovzxwq = 455,
-    UD_Ipmuldq = 456,
-    UD_Ipmulhrsw = 457,
-    UD_Ipmulhrw = 458,
-    UD_Ipmulhuw = 459,
-    UD_Ipmulhw = 460,
-    UD_Ipmulld = 461,
-    UD_Ipmullw = 462,
-    UD_Ipmuludq = 463,
-    UD_Ipop = 464,
-    UD_Ipopa = 465,
-    UD_Ipopad = 466,
-    UD_Ipopcnt = 467,
-    UD_Ipopfd = 468,
-    UD_Ipopfq = 469,
-    UD_Ipopfw = 470,
-    UD_Ipor = 471,
-    UD_Iprefetch = 472,
-    UD_Iprefetchnta = 473,
-    UD_Iprefetcht0 = 474,
-    UD_Iprefetcht1 = 475,
-    UD_Iprefetcht2 = 476,
-    UD_Ipsadbw = 477,
-    UD_Ipshufb = 478,
-    UD_Ipshufd = 479,
-    UD_Ipshufhw = 480,
-    UD_Ipshuflw = 481,
-    UD_Ipshufw = 482,
-    UD_Ipsignb = 483,
-    UD_Ipsignd = 484,
-    UD_Ipsignw = 485,
-    UD_Ipslld = 486,
-    UD_Ipslldq = 487,
-    UD_Ipsllq = 488,
-    UD_Ipsllw = 489,
-    UD_Ipsrad = 490,
-    UD_Ipsraw = 491,
-    UD_Ipsrld = 492,
-    UD_Ipsrldq = 493,
-    UD_Ipsrlq = 494,
-    UD_Ipsrlw = 495,
-    UD_Ipsubb = 496,
-    UD_Ipsubd = 497,
-    UD_Ipsubq = 498,
-    UD_Ipsubsb = 499,
-    UD_Ipsubsw = 500,
-    UD_Ipsubusb = 501,
-    UD_Ipsubusw = 502,
-    UD_Ipsubw = 503,
-    UD_Ipswapd = 504,
-    UD_Iptest = 505,
-    UD_Ipunpckhbw = 506,
-    UD_Ipunpckhdq = 507,
-    UD_Ipunpckhqdq = 508,
-    UD_Ipunpckhwd = 509,
-    UD_Ipunpcklbw = 510,
-    UD_Ipunpckldq = 511,
-    UD_Ipunpcklqdq = 512,
-    UD_Ipunpcklwd = 513,
-    UD_Ipush = 514,
-    UD_Ipusha = 515,
-    UD_Ipushad = 516,
-    UD_Ipushfd = 517,
-    UD_Ipushfq = 518,
-    UD_Ipushfw = 519,
-    UD_Ipxor = 520,
-    UD_Ircl = 521,
-    UD_Ircpps = 522,
-    UD_Ircpss = 523,
-    UD_Ircr = 524,
-    UD_Irdmsr = 525,
-    UD_Irdpmc = 526,
-    UD_Irdrand = 527,
-    UD_Irdtsc = 528,
-    UD_Irdtscp = 529,
-    UD_Irep = 530,
-    UD_Irepne = 531,
-    UD_Iret = 532,
-    UD_Iretf = 533,
-    UD_Irol = 534,
-    UD_Iror = 535,
-    UD_Iroundpd = 536,
-    UD_Iroundps = 537,
-    UD_Iroundsd = 538,
-    UD_Iroundss = 539,
-    UD_Irsm = 540,
-    UD_Irsqrtps = 541,
-    UD_Irsqrtss = 542,
-    UD_Isahf = 543,
-    UD_Isalc = 544,
-    UD_Isar = 545,
-    UD_Isbb = 546,
-    UD_Iscasb = 547,
-    UD_Iscasd = 548,
-    UD_Iscasq = 549,
-    UD_Iscasw = 550,
-    UD_Iseta = 551,
-    UD_Isetae = 552,
-    UD_Isetb = 553,
-    UD_Isetbe = 554,
-    UD_Isetg = 555,
-    UD_Isetge = 556,
-    UD_Isetl = 557,
-    UD_Isetle = 558,
-    UD_Isetno = 559,
-    UD_Isetnp = 560,
-    UD_Isetns = 561,
-    UD_Isetnz = 562,
-    UD_Iseto = 563,
-    UD_Isetp = 564,
-    UD_Isets = 565,
-    UD_Isetz = 566,
-    UD_Isfence = 567,
-    UD_Isgdt = 568,
-    UD_Ishl = 569,
-    UD_Ishld = 570,
-    UD_Ishr = 571,
-    UD_Ishrd = 572,
-    UD_Ishufpd = 573,
-    UD_Ishufps = 574,
-    UD_Isidt = 575,
-    UD_Iskinit = 576,
-    UD_Isldt = 577,
-    UD_Ismsw = 578,
-    UD_Isqrtpd = 579,
-    UD_Isqrtps = 580,
-    UD_Isqrtsd = 581,
-    UD_Isqrtss = 582,
-    UD_Istc = 583,
-    UD_Istd = 584,
-    UD_Istgi = 585,
-    UD_Isti = 586,
-    UD_Istmxcsr = 587,
-    UD_Istosb = 588,
-    UD_Istosd = 589,
-    UD_Istosq = 590,
-    UD_Istosw = 591,
-    UD_Istr = 592,
-    UD_Isub = 593,
-    UD_Isubpd = 594,
-    UD_Isubps = 595,
-    UD_Isubsd = 596,
-    UD_Isubss = 597,
-    UD_Iswapgs = 598,
-    UD_Isyscall = 599,
-    UD_Isysenter = 600,
-    UD_Isysexit = 601,
-    UD_Isysret = 602,
-    UD_Itest = 603,
-    UD_Iucomisd = 604,
-    UD_Iucomiss = 605,
-    UD_Iud2 = 606,
-    UD_Iunpckhpd = 607,
-    UD_Iunpckhps = 608,
-    UD_Iunpcklpd = 609,
-    UD_Iunpcklps = 610,
-    UD_Ivaddpd = 611,
-    UD_Ivaddps = 612,
-    UD_Ivaddsd = 613,
-    UD_Ivaddss = 614,
-    UD_Ivaddsubpd = 615,
-    UD_Ivaddsubps = 616,
-    UD_Ivaesdec = 617,
-    UD_Ivaesdeclast = 618,
-    UD_Ivaesenc = 619,
-    UD_Ivaesenclast = 620,
-    UD_Ivaesimc = 621,
-    UD_Ivaeskeygenassist = 622,
-    UD_Ivandnpd = 623,
-    UD_Ivandnps = 624,
-    UD_Ivandpd = 625,
-    UD_Ivandps = 626,
-    UD_Ivblendpd = 627,
-    UD_Ivblendps = 628,
-    UD_Ivblendvpd = 629,
-    UD_Ivblendvps = 630,
-    UD_Ivbroadcastsd = 631,
-    UD_Ivbroadcastss = 632,
-    UD_Ivcmppd = 633,
-    UD_Ivcmpps = 634,
-    UD_Ivcmpsd = 635,
-    UD_Ivcmpss = 636,
-    UD_Ivcomisd = 637,
-    UD_Ivcomiss = 638,
-    UD_Ivcvtdq2pd = 639,
-    UD_Ivcvtdq2ps = 640,
-    UD_Ivcvtpd2dq = 641,
-    UD_Ivcvtpd2ps = 642,
-    UD_Ivcvtps2dq = 643,
-    UD_Ivcvtps2pd = 644,
-    UD_Ivcvtsd2si = 645,
-    UD_Ivcvtsd2ss = 646,
-    UD_Ivcvtsi2sd = 647,
-    UD_Ivcvtsi2ss = 648,
-    UD_Ivcvtss2sd = 649,
-    UD_Ivcvtss2si = 650,
-    UD_Ivcvttpd2dq = 651,
-    UD_Ivcvttps2dq = 652,
-    UD_Ivcvttsd2si = 653,
-    UD_Ivcvttss2si = 654,
-    UD_Ivdivpd = 655,
-    UD_Ivdivps = 656,
-    UD_Ivdivsd = 657,
-    UD_Ivdivss = 658,
-    UD_Ivdppd = 659,
-    UD_Ivdpps = 660,
-    UD_Iverr = 661,
-    UD_Iverw = 662,
-    UD_Ivextractf128 = 663,
-    UD_Ivextractps = 664,
-    UD_Ivhaddpd = 665,
-    UD_Ivhaddps = 666,
-    UD_Ivhsubpd = 667,
-    UD_Ivhsubps = 668,
-    UD_Ivinsertf128 = 669,
-    UD_Ivinsertps = 670,
-    UD_Ivlddqu = 671,
-    UD_Ivmaskmovdqu = 672,
-    UD_Ivmaskmovpd = 673,
-    UD_Ivmaskmovps = 674,
-    UD_Ivmaxpd = 675,
-    UD_Ivmaxps = 676,
-    UD_Ivmaxsd = 677,
-    UD_Ivmaxss = 678,
-    UD_Ivmcall = 679,
-    UD_Ivmclear = 680,
-    UD_Ivminpd = 681,
-    UD_Ivminps = 682,
-    UD_Ivminsd = 683,
-    UD_Ivminss = 684,
-    UD_Ivmlaunch = 685,
-    UD_Ivmload = 686,
-    UD_Ivmmcall = 687,
-    UD_Ivmovapd = 688,
-    UD_Ivmovaps = 689,
-    UD_Ivmovd = 690,
-    UD_Ivmovddup = 691,
-    UD_Ivmovdqa = 692,
-    UD_Ivmovdqu = 693,
-    UD_Ivmovhlps = 694,
-    UD_Ivmovhpd = 695,
-    UD_Ivmovhps = 696,
-    UD_Ivmovlhps = 697,
-    UD_Ivmovlpd = 698,
-    UD_Ivmovlps = 699,
-    UD_Ivmovmskpd = 700,
-    UD_Ivmovmskps = 701,
-    UD_Ivmovntdq = 702,
-    UD_Ivmovntdqa = 703,
-    UD_Ivmovntpd = 704,
-    UD_Ivmovntps = 705,
-    UD_Ivmovq = 706,
-    UD_Ivmovsd = 707,
-    UD_Ivmovshdup = 708,
-    UD_Ivmovsldup = 709,
-    UD_Ivmovss = 710,
-    UD_Ivmovupd = 711,
-    UD_Ivmovups = 712,
-    UD_Ivmpsadbw = 713,
-    UD_Ivmptrld = 714,
-    UD_Ivmptrst = 715,
-    UD_Ivmread = 716,
-    UD_Ivmresume = 717,
-    UD_Ivmrun = 718,
-    UD_Ivmsave = 719,
-    UD_Ivmulpd = 720,
-    UD_Ivmulps = 721,
-    UD_Ivmulsd = 722,
-    UD_Ivmulss = 723,
-    UD_Ivmwrite = 724,
-    UD_Ivmxoff = 725,
-    UD_Ivmxon = 726,
-    UD_Ivorpd = 727,
-    UD_Ivorps = 728,
-    UD_Ivpabsb = 729,
-    UD_Ivpabsd = 730,
-    UD_Ivpabsw = 731,
-    UD_Ivpackssdw = 732,
-    UD_Ivpacksswb = 733,
-    UD_Ivpackusdw = 734,
-    UD_Ivpackuswb = 735,
-    UD_Ivpaddb = 736,
-    UD_Ivpaddd = 737,
-    UD_Ivpaddq = 738,
-    UD_Ivpaddsb = 739,
-    UD_Ivpaddsw = 740,
-    UD_Ivpaddusb = 741,
-    UD_Ivpaddusw = 742,
-    UD_Ivpaddw = 743,
-    UD_Ivpalignr = 744,
-    UD_Ivpand = 745,
-    UD_Ivpandn = 746,
-    UD_Ivpavgb = 747,
-    UD_Ivpavgw = 748,
-    UD_Ivpblendvb = 749,
-    UD_Ivpblendw = 750,
-    UD_Ivpclmulqdq = 751,
-    UD_Ivpcmpeqb = 752,
-    UD_Ivpcmpeqd = 753,
-    UD_Ivpcmpeqq = 754,
-    UD_Ivpcmpeqw = 755,
-    UD_Ivpcmpestri = 756,
-    UD_Ivpcmpestrm = 757,
-    UD_Ivpcmpgtb = 758,
-    UD_Ivpcmpgtd = 759,
-    UD_Ivpcmpgtq = 760,
-    UD_Ivpcmpgtw = 761,
-    UD_Ivpcmpistri = 762,
-    UD_Ivpcmpistrm = 763,
-    UD_Ivperm2f128 = 764,
-    UD_Ivpermilpd = 765,
-    UD_Ivpermilps = 766,
-    UD_Ivpextrb = 767,
-    UD_Ivpextrd = 768,
-    UD_Ivpextrq = 769,
-    UD_Ivpextrw = 770,
-    UD_Ivphaddd = 771,
-    UD_Ivphaddsw = 772,
-    UD_Ivphaddw = 773,
-    UD_Ivphminposuw = 774,
-    UD_Ivphsubd = 775,
-    UD_Ivphsubsw = 776,
-    UD_Ivphsubw = 777,
-    UD_Ivpinsrb = 778,
-    UD_Ivpinsrd = 779,
-    UD_Ivpinsrq = 780,
-    UD_Ivpinsrw = 781,
-    UD_Ivpmaddubsw = 782,
-    UD_Ivpmaddwd = 783,
-    UD_Ivpmaxsb = 784,
-    UD_Ivpmaxsd = 785,
-    UD_Ivpmaxsw = 786,
-    UD_Ivpmaxub = 787,
-    UD_Ivpmaxud = 788,
-    UD_Ivpmaxuw = 789,
-    UD_Ivpminsb = 790,
-    UD_Ivpminsd = 791,
-    UD_Ivpminsw = 792,
-    UD_Ivpminub = 793,
-    UD_Ivpminud = 794,
-    UD_Ivpminuw = 795,
-    UD_Ivpmovmskb = 796,
-    UD_Ivpmovsxbd = 797,
-    UD_Ivpmovsxbq = 798,
-    UD_Ivpmovsxbw = 799,
-    UD_Ivpmovsxwd = 800,
-    UD_Ivpmovsxwq = 801,
-    UD_Ivpmovzxbd = 802,
-    UD_Ivpmovzxbq = 803,
-    UD_Ivpmovzxbw = 804,
-    UD_Ivpmovzxdq = 805,
-    UD_Ivpmovzxwd = 806,
-    UD_Ivpmovzxwq = 807,
-    UD_Ivpmuldq = 808,
-    UD_Ivpmulhrsw = 809,
-    UD_Ivpmulhuw = 810,
-    UD_Ivpmulhw = 811,
-    UD_Ivpmulld = 812,
-    UD_Ivpmullw = 813,
-    UD_Ivpor = 814,
-    UD_Ivpsadbw = 815,
-    UD_Ivpshufb = 816,
-    UD_Ivpshufd = 817,
-    UD_Ivpshufhw = 818,
-    UD_Ivpshuflw = 819,
-    UD_Ivpsignb = 820,
-    UD_Ivpsignd = 821,
-    UD_Ivpsignw = 822,
-    UD_Ivpslld = 823,
-    UD_Ivpslldq = 824,
-    UD_Ivpsllq = 825,
-    UD_Ivpsllw = 826,
-    UD_Ivpsrad = 827,
-    UD_Ivpsraw = 828,
-    UD_Ivpsrld = 829,
-    UD_Ivpsrldq = 830,
-    UD_Ivpsrlq = 831,
-    UD_Ivpsrlw = 832,
-    UD_Ivpsubb = 833,
-    UD_Ivpsubd = 834,
-    UD_Ivpsubq = 835,
-    UD_Ivpsubsb = 836,
-    UD_Ivpsubsw = 837,
-    UD_Ivpsubusb = 838,
-    UD_Ivpsubusw = 839,
-    UD_Ivpsubw = 840,
-    UD_Ivptest = 841,
-    UD_Ivpunpckhbw = 842,
-    UD_Ivpunpckhdq = 843,
-    UD_Ivpunpckhqdq = 844,
-    UD_Ivpunpckhwd = 845,
-    UD_Ivpunpcklbw = 846,
-    UD_Ivpunpckldq = 847,
-    UD_Ivpunpcklqdq = 848,
-    UD_Ivpunpcklwd = 849,
-    UD_Ivpxor = 850,
-    UD_Ivrcpps = 851,
-    UD_Ivrcpss = 852,
-    UD_Ivroundpd = 853,
-    UD_Ivroundps = 854,
-    UD_Ivroundsd = 855,
-    UD_Ivroundss = 856,
-    UD_Ivrsqrtps = 857,
-    UD_Ivrsqrtss = 858,
-    UD_Ivshufpd = 859,
-    UD_Ivshufps = 860,
-    UD_Ivsqrtpd = 861,
-    UD_Ivsqrtps = 862,
-    UD_Ivsqrtsd = 863,
-    UD_Ivsqrtss = 864,
-    UD_Ivstmxcsr = 865,
-    UD_Ivsubpd = 866,
-    UD_Ivsubps = 867,
-    UD_Ivsubsd = 868,
-    UD_Ivsubss = 869,
-    UD_Ivtestpd = 870,
-    UD_Ivtestps = 871,
-    UD_Ivucomisd = 872,
-    UD_Ivucomiss = 873,
-    UD_Ivunpckhpd = 874,
-    UD_Ivunpckhps = 875,
-    UD_Ivunpcklpd = 876,
-    UD_Ivunpcklps = 877,
-    UD_Ivxorpd = 878,
-    UD_Ivxorps = 879,
-    UD_Ivzeroall = 880,
-    UD_Ivzeroupper = 881,
-    UD_Iwait = 882,
-    UD_Iwbinvd = 883,
-    UD_Iwrmsr = 884,
-    UD_Ixadd = 885,
-    UD_Ixchg = 886,
-    UD_Ixcryptcbc = 887,
-    UD_Ixcryptcfb = 888,
-    UD_Ixcryptctr = 889,
-    UD_Ixcryptecb = 890,
-    UD_Ixcryptofb = 891,
-    UD_Ixgetbv = 892,
-    UD_Ixlatb = 893,
-    UD_Ixor = 894,
-    UD_Ixorpd = 895,
-    UD_Ixorps = 896,
-    UD_Ixrstor = 897,
-    UD_Ixsave = 898,
-    UD_Ixsetbv = 899,
-    UD_Ixsha1 = 900,
-    UD_Ixsha256 = 901,
-    UD_Ixstore = 902,
-    UD_Iinvalid = 903,
-    UD_I3dnow = 904,
-    UD_Inone = 905,
-    UD_Idb = 906,
-    UD_Ipause = 907,
-    UD_MAX_MNEMONIC_CODE = 908,
-}
-
-extern "C" {
-    #[no_mangle]
-    pub static mut ud_mnemonics_str: *mut *const u8;
-}
-
-#[repr(u32)]
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub enum ud_operand_code {
-    OP_NONE = 0,
-    OP_A = 1,
-    OP_E = 2,
-    OP_M = 3,
-    OP_G = 4,
-    OP_I = 5,
-    OP_AL = 6,
-    OP_CL = 7,
-    OP_DL = 8,
-    OP_BL = 9,    
-    OP_AH = 10,
-    OP_CH = 11,
-    OP_DH = 12,
-    OP_BH = 13,
-    OP_ALr8b = 14,
-    OP_CLr9b = 15,
-    OP_DLr10b = 16,
-    OP_BLr11b = 17,
-    OP_AHr12b = 18,
-    OP_CHr13b = 19,
-    OP_DHr14b = 20,
-    OP_BHr15b = 21,
-    OP_AX = 22,
-    OP_CX = 23,
-    OP_DX = 24,
-    OP_BX = 25,
-    OP_SI = 26,
-    OP_DI = 27,
-    OP_SP = 28,
-    OP_BP = 29,
-    OP_rAX = 30,
-    OP_rCX = 31,
-    OP_rDX = 32,
-    OP_rBX = 33,
-    OP_rSP = 34,
-    OP_rBP = 35,
-    OP_rSI = 36,
-    OP_rDI = 37,
-    OP_rAXr8 = 38,
-    OP_rCXr9 = 39,
-    OP_rDXr10 = 40,
-    OP_rBXr11 = 41,
-    OP_rSPr12 = 42,
-    OP_rBPr13 = 43,
-    OP_rSIr14 = 44,
-    OP_rDIr15 = 45,
-    OP_eAX = 46,
-    OP_eCX = 47,
-    OP_eDX = 48,
-    OP_eBX = 49,
-    OP_eSP = 50,
-    OP_eBP = 51,
-    OP_eSI = 52,
-    OP_eDI = 53,
-    OP_ES = 54,
-    OP_CS = 55,
-    OP_SS = 56,
-    OP_DS = 57,
-    OP_FS = 58,
-    OP_GS = 59,
-    OP_ST0 = 60,
-    OP_ST1 = 61,
-    OP_ST2 = 62,
-    OP_ST3 = 63,
-    OP_ST4 = 64,
-    OP_ST5 = 65,
-    OP_ST6 = 66,
-    OP_ST7 = 67,
-    OP_J = 68,
-    OP_S = 69,
-    OP_O = 70,
-    OP_I1 = 71,
-    OP_I3 = 72,
-    OP_V = 73,
-    OP_W = 74,
-    OP_Q = 75,
-    OP_P = 76,
-    OP_R = 77,
-    OP_C = 78,
-    OP_D = 79,
-    OP_VR = 80,
-    OP_PR = 81
-}
-
-#[repr(u32)]
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub enum ud_operand_size {
-    SZ_NA  = 0,
-    SZ_Z   = 1,
-    SZ_V   = 2,
-    SZ_P   = 3,
-    SZ_WP  = 4,
-    SZ_DP  = 5,
-    SZ_MDQ = 6,
-    SZ_RDQ = 7,
-
-    /* the following values are used as is,
-     * and thus hard-coded. changing them 
-     * will break internals 
-     */
-    SZ_B   = 8,
-    SZ_W   = 16,
-    SZ_D   = 32,
-    SZ_Q   = 64,
-    SZ_T   = 80,
-}
-
-#[repr(C)]
-pub struct ud_itab_entry_operand {
-    pub typ: ud_operand_code,
-    pub size: ud_operand_size,
-}
-
-#[repr(C)]
-pub struct ud_itab_entry {
-    pub mnemonic: ud_mnemonic_code,
-    pub operand1: ud_itab_entry_operand,
-    pub operand2: ud_itab_entry_operand,
-    pub operand3: ud_itab_entry_operand,
-    pub prefix: u32,
-}
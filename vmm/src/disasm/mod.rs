@@ -1,38 +1,73 @@
-pub use self::api::*;
-pub use self::itab::*;
-pub use self::types::*;
+pub use self::insn::*;
+pub use self::decode::{decode, DecodeError};
 
-mod api;
-mod itab;
-mod types;
+mod insn;
+mod decode;
 
+use vm;
+use vm::seg::{self, SegAccess};
 use vmx::exit::VMMStatus;
+use cpumode::CPUState;
+use share::info::InformationData;
+use share::vmx::vmcs::access::Access;
+use share::cpu::INSN_MAX_LEN;
+use share::paging::utils::PG_4KB;
+use share::utils;
+use share::utils::RawValue;
 
+// Fetch up to len bytes at CS:RIP+offset into the instruction cache
+fn fetch(info: &mut InformationData, offset: usize, len: usize) -> VMMStatus {
+    let rip = info.vm.vmcs.guest.rip.as_ref().as_u64() + offset as u64;
+    let mut insn = info.vm.cpu.insn_cache;
 
+    match vm::mem::read_seg(info, seg::CS, SegAccess::Exec, rip, &mut insn[offset..offset+len]) {
+        VMMStatus::Done => (),
+        rc @ _ => return rc,
+    }
 
-// use disasm::{disassemble, ud};
-// let mut dis: ud = unsafe { core::mem::zeroed() };
-// unsafe { disassemble(&mut dis); }
+    info.vm.cpu.insn_cache = insn;
+    VMMStatus::Done
+}
 
+// Disassemble the VM instruction at CS:RIP
+//
+// Do not fetch across a page boundary or past the CS limit unless
+// the decoder asks for it, so that we do not inject a #PF or #GP
+// the CPU would not have raised.
+pub fn disassemble(info: &mut InformationData) -> Result<Instruction, VMMStatus> {
+    let mode = CPUState::addr_size(info) / 8;
+    let rip  = info.vm.vmcs.guest.rip.as_ref().as_u64();
 
-pub unsafe fn disassemble(dis: &mut ud) -> VMMStatus {
+    let addr = seg::linear(info, seg::CS, rip, 1, SegAccess::Exec)?;
+    let left = PG_4KB - (addr as usize & (PG_4KB - 1));
+    let mut len = utils::min(left, INSN_MAX_LEN);
 
-    //get_insn(&vaddr, &mode)
+    // first byte passed the limit check
+    if !CPUState::init(info).is_long64() {
+        let limit = info.vm.vmcs.guest.cs.limit.as_ref().as_u64();
+        len = utils::min(len, (limit - rip + 1) as usize);
+    }
 
-    let data = [ 0x8B, 0x04, 0xB2 ];
-    let mode = 16;
+    match fetch(info, 0, len) {
+        VMMStatus::Done => (),
+        rc @ _ => return Err(rc),
+    }
 
-    ud_init(dis);
-    ud_set_input_buffer(dis,
-                        data.as_ptr(), // info->vm.cpu.insn_cache
-                        data.len());    // sizeof(info->vm.cpu.insn_cache)
+    loop {
+        let insn = info.vm.cpu.insn_cache;
 
-    ud_set_mode(dis, mode);
-    ud_set_vendor(dis, ud_vendor::AMD);
+        match decode(&insn[..len], mode) {
+            Ok(decoded) => return Ok(decoded),
+            Err(DecodeError::Truncated) if len < INSN_MAX_LEN => (),
+            Err(e) => {
+                log!("can't disassemble {:?}: {:?}\n", insn, e);
+                return Err(VMMStatus::Fail)
+            },
+        }
 
-    if ud_disassemble(dis) == 0 {
-        return VMMStatus::Fail;
+        match fetch(info, len, INSN_MAX_LEN - len) {
+            VMMStatus::Done => len = INSN_MAX_LEN,
+            rc @ _ => return Err(rc),
+        }
     }
-
-    VMMStatus::Done
 }
@@ -0,0 +1,157 @@
+// Integer operations and their RFLAGS effects
+//
+// Sizes are in bytes. Operands are masked to size, results are
+// returned with the arithmetic flags they set.
+
+pub const CF: u64 = 1<<0;
+pub const PF: u64 = 1<<2;
+pub const AF: u64 = 1<<4;
+pub const ZF: u64 = 1<<6;
+pub const SF: u64 = 1<<7;
+pub const OF: u64 = 1<<11;
+
+pub const ARITH_MSK: u64 = CF|PF|AF|ZF|SF|OF;
+
+pub fn mask(size: usize) -> u64 {
+    if size >= 8 { !0 } else { (1<<(size*8)) - 1 }
+}
+
+fn sign(size: usize) -> u64 {
+    1<<(size*8 - 1)
+}
+
+pub fn sign_extend(value: u64, from: usize, to: usize) -> u64 {
+    let v = value & mask(from);
+
+    if v & sign(from) != 0 {
+        (v | !mask(from)) & mask(to)
+    } else {
+        v
+    }
+}
+
+// ZF, SF and PF of a result
+fn result_flags(res: u64, size: usize) -> u64 {
+    let mut flags = 0;
+
+    if res == 0 {
+        flags |= ZF;
+    }
+
+    if res & sign(size) != 0 {
+        flags |= SF;
+    }
+
+    if (res as u8).count_ones() & 1 == 0 {
+        flags |= PF;
+    }
+
+    flags
+}
+
+pub fn add(a: u64, b: u64, size: usize) -> (u64, u64) {
+    let (a, b) = (a & mask(size), b & mask(size));
+    let res = a.wrapping_add(b) & mask(size);
+    let mut flags = result_flags(res, size);
+
+    if res < a {
+        flags |= CF;
+    }
+
+    if (a ^ res) & (b ^ res) & sign(size) != 0 {
+        flags |= OF;
+    }
+
+    if (a ^ b ^ res) & 0x10 != 0 {
+        flags |= AF;
+    }
+
+    (res, flags)
+}
+
+pub fn sub(a: u64, b: u64, size: usize) -> (u64, u64) {
+    let (a, b) = (a & mask(size), b & mask(size));
+    let res = a.wrapping_sub(b) & mask(size);
+    let mut flags = result_flags(res, size);
+
+    if a < b {
+        flags |= CF;
+    }
+
+    if (a ^ b) & (a ^ res) & sign(size) != 0 {
+        flags |= OF;
+    }
+
+    if (a ^ b ^ res) & 0x10 != 0 {
+        flags |= AF;
+    }
+
+    (res, flags)
+}
+
+// AND/OR/XOR/TEST clear CF and OF, AF is undefined (cleared)
+pub fn logic(res: u64, size: usize) -> (u64, u64) {
+    let res = res & mask(size);
+    (res, result_flags(res, size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_flags() {
+        assert_eq!(add(1, 1, 1), (2, 0));
+        assert_eq!(add(0xff, 1, 1), (0, CF|ZF|PF|AF));
+        assert_eq!(add(0x7f, 1, 1), (0x80, OF|SF|AF));
+        assert_eq!(add(0x80, 0x80, 1), (0, CF|OF|ZF|PF));
+        // operands are masked to size
+        assert_eq!(add(0x1ff, 0x101, 1), (0, CF|ZF|PF|AF));
+
+        assert_eq!(add(0xffff, 1, 2), (0, CF|ZF|PF|AF));
+        assert_eq!(add(0x7fff, 1, 2), (0x8000, OF|SF|AF|PF));
+        assert_eq!(add(0xffffffff, 1, 4), (0, CF|ZF|PF|AF));
+        assert_eq!(add(0x7fffffff, 1, 4), (0x80000000, OF|SF|AF|PF));
+        assert_eq!(add(!0, 1, 8), (0, CF|ZF|PF|AF));
+        assert_eq!(add(0x7fffffffffffffff, 1, 8), (1<<63, OF|SF|AF|PF));
+        assert_eq!(add(1<<63, 1<<63, 8), (0, CF|OF|ZF|PF));
+    }
+
+    #[test]
+    fn sub_flags() {
+        assert_eq!(sub(5, 5, 1), (0, ZF|PF));
+        assert_eq!(sub(0, 1, 1), (0xff, CF|SF|AF|PF));
+        assert_eq!(sub(0x80, 1, 1), (0x7f, OF|AF));
+        assert_eq!(sub(0x7f, 0xff, 1), (0x80, CF|OF|SF));
+        assert_eq!(sub(0x10, 1, 1), (0x0f, AF|PF));
+
+        assert_eq!(sub(0, 1, 2), (0xffff, CF|SF|AF|PF));
+        assert_eq!(sub(0x8000, 1, 2), (0x7fff, OF|AF|PF));
+        assert_eq!(sub(0, 1, 4), (0xffffffff, CF|SF|AF|PF));
+        assert_eq!(sub(0x80000000, 1, 4), (0x7fffffff, OF|AF|PF));
+        assert_eq!(sub(0, 1, 8), (!0, CF|SF|AF|PF));
+        assert_eq!(sub(1<<63, 1, 8), (0x7fffffffffffffff, OF|AF|PF));
+    }
+
+    #[test]
+    fn logic_flags() {
+        assert_eq!(logic(0x80, 1), (0x80, SF));
+        assert_eq!(logic(0x100, 1), (0, ZF|PF));
+        assert_eq!(logic(3, 1), (3, PF));
+        assert_eq!(logic(0x8001, 2), (0x8001, SF));
+        assert_eq!(logic(0x80000000, 4), (0x80000000, SF|PF));
+        assert_eq!(logic(1<<32, 4), (0, ZF|PF));
+        assert_eq!(logic(1<<63, 8), (1<<63, SF|PF));
+    }
+
+    #[test]
+    fn extend() {
+        assert_eq!(sign_extend(0x7f, 1, 8), 0x7f);
+        assert_eq!(sign_extend(0x80, 1, 8), 0xffffffffffffff80);
+        assert_eq!(sign_extend(0x1ff, 1, 2), 0xffff);
+        assert_eq!(sign_extend(0x8000, 2, 4), 0xffff8000);
+        assert_eq!(sign_extend(0x7fff, 2, 8), 0x7fff);
+        assert_eq!(sign_extend(0x80000000, 4, 8), 0xffffffff80000000);
+        assert_eq!(sign_extend(1<<63, 8, 8), 1<<63);
+    }
+}
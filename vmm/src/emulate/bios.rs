@@ -0,0 +1,176 @@
+// BIOS services emulation
+//
+// INT 0x15 memory services are answered by the VMM so that the guest
+// never sees the VMM area as usable memory. Other services are left
+// to the BIOS.
+
+use vm;
+use dev::a20;
+use vm::seg::{self, SegAccess};
+use vmx::exit::VMMStatus;
+use share::vmx::vmcs::access::Access;
+use share::info::InformationData;
+use share::smap::{E820Entry, SystemMapEntryType};
+use share::rmode;
+use share::utils;
+use share::utils::RawValue;
+use core::mem;
+
+const KB: u64 = 1<<10;
+const E801_HIGH_BLOCK: u64 = 64<<10;
+const FOUR_GB: u64 = 1<<32;
+
+// A20 gate through keyboard controller and port 0x92
+const A20_SUPPORT: u16 = 3;
+
+fn set_cf(info: &mut InformationData, cf: bool) {
+    info.vm.vmcs.guest.rflags.as_mut().set_cf(cf);
+}
+
+fn set_ah(info: &mut InformationData, ah: u8) {
+    let rax = &mut info.vm.cpu.gpr.rax;
+    let al  = rax.as_u16() & 0xff;
+    rax.update_u16((ah as u16) << 8 | al);
+}
+
+fn success(info: &mut InformationData) -> VMMStatus {
+    set_cf(info, false);
+    VMMStatus::Done
+}
+
+fn error(info: &mut InformationData, code: u8) -> VMMStatus {
+    set_ah(info, code);
+    set_cf(info, true);
+    VMMStatus::Done
+}
+
+// nth entry of the guest memory map (VMM area already punched out)
+fn e820(info: &InformationData, n: usize) -> Option<E820Entry> {
+    info.vm.smap.e820().nth(n)
+}
+
+// End of RAM contiguous from 1MB
+fn ext_mem_end(info: &InformationData) -> u64 {
+    let mut n = 0;
+
+    while let Some(e) = e820(info, n) {
+        if e.typ == SystemMapEntryType::Available as u32 && e.base == rmode::EXT_MEM_START {
+            return e.base + e.len
+        }
+        n += 1;
+    }
+
+    rmode::EXT_MEM_START
+}
+
+fn get_smap(info: &mut InformationData) -> VMMStatus {
+    let idx  = info.vm.cpu.gpr.rbx.as_u32() as usize;
+    let sig  = info.vm.cpu.gpr.rdx.as_u32();
+    let size = info.vm.cpu.gpr.rcx.as_u32() as usize;
+    let di   = info.vm.cpu.gpr.rdi.as_u16() as u64;
+
+    if sig != rmode::BIOS_SMAP_ID || size < mem::size_of::<E820Entry>() {
+        return error(info, rmode::BIOS_SMAP_ERROR as u8)
+    }
+
+    let entry = match e820(info, idx) {
+        Some(e) => e,
+        None => return error(info, rmode::BIOS_SMAP_ERROR as u8),
+    };
+
+    #[cfg(feature = "debug_bios")]
+    log!("e820 #{} {:#x} - {:#x} type {}\n"
+         ,idx, {entry.base}, {entry.base + entry.len}, {entry.typ});
+
+    match vm::mem::write_seg(info, seg::ES, SegAccess::Write, di, entry.as_u8()) {
+        VMMStatus::Done => (),
+        rc @ _ => return rc,
+    }
+
+    let next = if e820(info, idx+1).is_some() { idx as u32 + 1 } else { 0 };
+
+    info.vm.cpu.gpr.rax.update_u32(rmode::BIOS_SMAP_ID);
+    info.vm.cpu.gpr.rbx.update_u32(next);
+    info.vm.cpu.gpr.rcx.update_u32(mem::size_of::<E820Entry>() as u32);
+    success(info)
+}
+
+// AX/CX: KB between 1MB and 16MB, BX/DX: 64KB blocks above 16MB
+fn get_ext_mem(info: &mut InformationData, large: bool) -> VMMStatus {
+    let top  = ext_mem_end(info);
+    let low  = (utils::min(top, rmode::DMA_END) - rmode::EXT_MEM_START) / KB;
+    let high = if top > rmode::DMA_END {
+        (utils::min(top, FOUR_GB) - rmode::DMA_END) / E801_HIGH_BLOCK
+    } else {
+        0
+    };
+
+    let gpr = &mut info.vm.cpu.gpr;
+
+    if large {
+        gpr.rax.update_u32(low as u32);
+        gpr.rcx.update_u32(low as u32);
+        gpr.rbx.update_u32(high as u32);
+        gpr.rdx.update_u32(high as u32);
+    } else {
+        gpr.rax.update_u16(low as u16);
+        gpr.rcx.update_u16(low as u16);
+        gpr.rbx.update_u16(high as u16);
+        gpr.rdx.update_u16(high as u16);
+    }
+
+    success(info)
+}
+
+// AX: KB above 1MB
+fn old_get_ext_mem(info: &mut InformationData) -> VMMStatus {
+    let kb = (ext_mem_end(info) - rmode::EXT_MEM_START) / KB;
+    info.vm.cpu.gpr.rax.update_u16(utils::min(kb, 0xffff) as u16);
+    success(info)
+}
+
+// DX:AX: KB above 1MB
+fn get_big_mem(info: &mut InformationData) -> VMMStatus {
+    let kb = (ext_mem_end(info) - rmode::EXT_MEM_START) / KB;
+    info.vm.cpu.gpr.rax.update_u16(kb as u16);
+    info.vm.cpu.gpr.rdx.update_u16((kb >> 16) as u16);
+    success(info)
+}
+
+fn a20_gate(info: &mut InformationData, ax: u16) -> VMMStatus {
+    match ax {
+        rmode::BIOS_DISABLE_A20 => a20::set(info, false),
+        rmode::BIOS_ENABLE_A20  => a20::set(info, true),
+        rmode::BIOS_STATUS_A20  => {
+            let on = info.vm.cpu.a20 as u16;
+            info.vm.cpu.gpr.rax.update_u16(on)
+        },
+        _ => info.vm.cpu.gpr.rbx.update_u16(A20_SUPPORT),
+    }
+
+    set_ah(info, 0);
+    success(info)
+}
+
+// Native status means the BIOS handler should be called
+pub fn misc(info: &mut InformationData) -> VMMStatus {
+    let ax = info.vm.cpu.gpr.rax.as_u16();
+
+    #[cfg(feature = "debug_bios")]
+    log!("int 0x15 ax {:#x}\n", ax);
+
+    match ax {
+        rmode::BIOS_GET_SMAP       => get_smap(info),
+        rmode::BIOS_GET_EXT_MEM    => get_ext_mem(info, false),
+        rmode::BIOS_GET_EXT_MEM_32 => get_ext_mem(info, true),
+        rmode::BIOS_DISABLE_A20 |
+        rmode::BIOS_ENABLE_A20  |
+        rmode::BIOS_STATUS_A20  |
+        rmode::BIOS_SUPPORT_A20    => a20_gate(info, ax),
+        _ => match (ax >> 8) as u8 {
+            rmode::BIOS_OLD_GET_EXT_MEM => old_get_ext_mem(info),
+            rmode::BIOS_GET_BIG_MEM     => get_big_mem(info),
+            _ => VMMStatus::Native,
+        },
+    }
+}
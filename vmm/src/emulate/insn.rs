@@ -0,0 +1,412 @@
+// Instruction emulation
+//
+// Decode the VM instruction at CS:RIP and emulate the subset of
+// memory accessing instructions needed for MMIO and EPT protection
+// faults. RIP is advanced here, not from the vm-exit instruction
+// length which is undefined for EPT violations.
+
+use vm;
+use vm::gpr;
+use vm::seg::{self, SegAccess};
+use vmx::exit::VMMStatus;
+use emulate::alu;
+use disasm::{self, Instruction, Mnemonic, Operand, OperandKind, Register, Memory, Repeat};
+use cpumode::CPUState;
+use share::info::InformationData;
+use share::vmx::vmcs::access::Access;
+use share::utils::RawValue;
+
+fn reg_read(info: &mut InformationData, reg: Register) -> Option<u64> {
+    match reg {
+        Register::Gpr(idx, size) => Some(gpr::read(info, idx) & alu::mask(size)),
+        Register::GprHigh(idx)   => Some((gpr::read(info, idx) >> 8) & 0xff),
+        _ => None,
+    }
+}
+
+// 32 bits writes zero extend, smaller ones merge
+fn reg_write(info: &mut InformationData, reg: Register, value: u64) -> bool {
+    let (idx, new) = match reg {
+        Register::Gpr(idx, size) => {
+            let old = gpr::read(info, idx);
+            let new = if size < 4 {
+                (old & !alu::mask(size)) | (value & alu::mask(size))
+            } else {
+                value & alu::mask(size)
+            };
+            (idx, new)
+        },
+        Register::GprHigh(idx) => {
+            let old = gpr::read(info, idx);
+            (idx, (old & !0xff00) | (value & 0xff) << 8)
+        },
+        _ => return false,
+    };
+
+    gpr::write(info, idx, new);
+    true
+}
+
+// Update register with respect to its size (ie. SI, ESI, RSI)
+fn reg_update(info: &mut InformationData, idx: u8, size: usize, value: u64) {
+    let old = gpr::read(info, idx);
+    let new = if size == 8 {
+        value
+    } else {
+        (old & !alu::mask(size)) | (value & alu::mask(size))
+    };
+
+    gpr::write(info, idx, new);
+}
+
+fn stack_size(info: &mut InformationData) -> usize {
+    if CPUState::init(info).is_long64() {
+        8
+    } else if info.vm.vmcs.guest.ss.attr.as_ref().d() {
+        4
+    } else {
+        2
+    }
+}
+
+// Segment relative offset of a memory operand
+fn offset(info: &mut InformationData, insn: &Instruction, mem: &Memory) -> u64 {
+    let mut addr = mem.disp as u64;
+
+    match mem.base {
+        Some(Register::Rip) => {
+            let rip = info.vm.vmcs.guest.rip.as_ref().as_u64();
+            addr = addr.wrapping_add(rip + insn.len as u64);
+        },
+        Some(reg) => if let Some(base) = reg_read(info, reg) {
+            addr = addr.wrapping_add(base);
+        },
+        None => (),
+    }
+
+    if let Some(reg) = mem.index {
+        if let Some(index) = reg_read(info, reg) {
+            addr = addr.wrapping_add(index * mem.scale as u64);
+        }
+    }
+
+    addr & alu::mask(insn.adsz)
+}
+
+fn mem_read(info: &mut InformationData, seg: u8, access: SegAccess,
+            offset: u64, size: usize) -> Result<u64, VMMStatus> {
+    let mut bytes = [0u8; 8];
+
+    match vm::mem::read_seg(info, seg, access, offset, &mut bytes[..size]) {
+        VMMStatus::Done => (),
+        rc @ _ => return Err(rc),
+    }
+
+    let mut value = 0;
+    for n in 0..size {
+        value |= (bytes[n] as u64) << (n*8);
+    }
+
+    Ok(value)
+}
+
+fn mem_write(info: &mut InformationData, seg: u8, access: SegAccess,
+             offset: u64, size: usize, value: u64) -> VMMStatus {
+    let mut bytes = [0u8; 8];
+
+    for n in 0..size {
+        bytes[n] = (value >> (n*8)) as u8;
+    }
+
+    vm::mem::write_seg(info, seg, access, offset, &bytes[..size])
+}
+
+fn unsupported(op: &Operand) -> VMMStatus {
+    log!("emulate: unsupported operand {:?}\n", op.kind);
+    VMMStatus::Fail
+}
+
+// Read operand n, zero extended
+fn read(info: &mut InformationData, insn: &Instruction, n: usize) -> Result<u64, VMMStatus> {
+    let op = insn.ops[n];
+
+    match op.kind {
+        OperandKind::Reg(reg) => match reg_read(info, reg) {
+            Some(value) => Ok(value),
+            None => Err(unsupported(&op)),
+        },
+        OperandKind::Mem(mem) => {
+            let off = offset(info, insn, &mem);
+            mem_read(info, mem.seg, SegAccess::Read, off, op.size)
+        },
+        OperandKind::Imm(value) => Ok(value & alu::mask(op.size)),
+        _ => Err(unsupported(&op)),
+    }
+}
+
+fn write(info: &mut InformationData, insn: &Instruction, n: usize, value: u64) -> VMMStatus {
+    let op = insn.ops[n];
+
+    match op.kind {
+        OperandKind::Reg(reg) => if reg_write(info, reg, value) {
+            VMMStatus::Done
+        } else {
+            unsupported(&op)
+        },
+        OperandKind::Mem(mem) => {
+            let off = offset(info, insn, &mem);
+            mem_write(info, mem.seg, SegAccess::Write, off, op.size, value)
+        },
+        _ => unsupported(&op),
+    }
+}
+
+fn set_flags(info: &mut InformationData, flags: u64) {
+    let rflags = info.vm.vmcs.guest.rflags.as_ref().as_u64();
+    let value  = (rflags & !alu::ARITH_MSK) | (flags & alu::ARITH_MSK);
+    info.vm.vmcs.guest.rflags.as_mut().update_u64(value);
+}
+
+fn mov(info: &mut InformationData, insn: &Instruction) -> VMMStatus {
+    match read(info, insn, 1) {
+        Ok(value) => write(info, insn, 0, value),
+        Err(rc) => rc,
+    }
+}
+
+fn mov_extend(info: &mut InformationData, insn: &Instruction, signed: bool) -> VMMStatus {
+    let (dsz, ssz) = (insn.size(0), insn.size(1));
+
+    match read(info, insn, 1) {
+        Ok(value) => {
+            let value = if signed { alu::sign_extend(value, ssz, dsz) } else { value };
+            write(info, insn, 0, value)
+        },
+        Err(rc) => rc,
+    }
+}
+
+// ADD, SUB, AND, OR, XOR, CMP, TEST
+fn arith(info: &mut InformationData, insn: &Instruction) -> VMMStatus {
+    let size = insn.size(0);
+
+    let a = match read(info, insn, 0) {
+        Ok(v) => v,
+        Err(rc) => return rc,
+    };
+
+    let b = match read(info, insn, 1) {
+        Ok(v) => v,
+        Err(rc) => return rc,
+    };
+
+    let (res, flags, store) = match insn.mnemonic {
+        Mnemonic::Add  => { let (r, f) = alu::add(a, b, size); (r, f, true) },
+        Mnemonic::Sub  => { let (r, f) = alu::sub(a, b, size); (r, f, true) },
+        Mnemonic::Cmp  => { let (r, f) = alu::sub(a, b, size); (r, f, false) },
+        Mnemonic::And  => { let (r, f) = alu::logic(a & b, size); (r, f, true) },
+        Mnemonic::Or   => { let (r, f) = alu::logic(a | b, size); (r, f, true) },
+        Mnemonic::Xor  => { let (r, f) = alu::logic(a ^ b, size); (r, f, true) },
+        Mnemonic::Test => { let (r, f) = alu::logic(a & b, size); (r, f, false) },
+        _ => return VMMStatus::Fail,
+    };
+
+    if store {
+        match write(info, insn, 0, res) {
+            VMMStatus::Done => (),
+            rc @ _ => return rc,
+        }
+    }
+
+    set_flags(info, flags);
+    VMMStatus::Done
+}
+
+fn push(info: &mut InformationData, insn: &Instruction) -> VMMStatus {
+    let size = insn.opsz;
+    let ssz  = stack_size(info);
+
+    let value = match read(info, insn, 0) {
+        Ok(v) => v,
+        Err(rc) => return rc,
+    };
+
+    let rsp = info.vm.vmcs.guest.rsp.as_ref().as_u64();
+    let sp  = rsp.wrapping_sub(size as u64) & alu::mask(ssz);
+
+    match mem_write(info, seg::SS, SegAccess::Stack, sp, size, value) {
+        VMMStatus::Done => (),
+        rc @ _ => return rc,
+    }
+
+    reg_update(info, gpr::RSP, ssz, sp);
+    VMMStatus::Done
+}
+
+fn pop(info: &mut InformationData, insn: &Instruction) -> VMMStatus {
+    let size = insn.opsz;
+    let ssz  = stack_size(info);
+    let rsp  = info.vm.vmcs.guest.rsp.as_ref().as_u64();
+    let sp   = rsp & alu::mask(ssz);
+
+    let value = match mem_read(info, seg::SS, SegAccess::Stack, sp, size) {
+        Ok(v) => v,
+        Err(rc) => return rc,
+    };
+
+    // destination address computed with incremented rsp
+    reg_update(info, gpr::RSP, ssz, sp + size as u64);
+
+    match write(info, insn, 0, value) {
+        VMMStatus::Done => VMMStatus::Done,
+        rc @ _ => {
+            info.vm.vmcs.guest.rsp.as_mut().update_u64(rsp);
+            rc
+        },
+    }
+}
+
+// LGDT/LIDT m16&32 (m16&64 in 64 bits mode)
+fn load_dtr(info: &mut InformationData, insn: &Instruction) -> VMMStatus {
+    let mem = match insn.kind(0) {
+        OperandKind::Mem(mem) => mem,
+        _ => return unsupported(&insn.ops[0]),
+    };
+
+    let off = offset(info, insn, &mem);
+    let bsz = insn.size(0) - 2;
+
+    let limit = match mem_read(info, mem.seg, SegAccess::Read, off, 2) {
+        Ok(v) => v,
+        Err(rc) => return rc,
+    };
+
+    let mut base = match mem_read(info, mem.seg, SegAccess::Read, off + 2, bsz) {
+        Ok(v) => v,
+        Err(rc) => return rc,
+    };
+
+    if insn.opsz == 2 && bsz == 4 {
+        base &= 0xffffff;
+    }
+
+    let dtr = if insn.mnemonic == Mnemonic::Lgdt {
+        &mut info.vm.vmcs.guest.gdtr
+    } else {
+        &mut info.vm.vmcs.guest.idtr
+    };
+
+    dtr.base.as_mut().update_u64(base);
+    dtr.limit.as_mut().update_u64(limit);
+    VMMStatus::Done
+}
+
+// MOVS and STOS, with REP prefix
+fn string(info: &mut InformationData, insn: &Instruction) -> VMMStatus {
+    let load = insn.mnemonic == Mnemonic::Movs;
+    let size = insn.size(0);
+    let adsz = insn.adsz;
+    let step = if info.vm.vmcs.guest.rflags.as_ref().df() {
+        (size as u64).wrapping_neg()
+    } else {
+        size as u64
+    };
+
+    let src_seg = match insn.kind(1) {
+        OperandKind::Mem(mem) => mem.seg,
+        _ => seg::DS,
+    };
+
+    let rep = insn.rep != Repeat::None;
+    let mut count = if rep {
+        gpr::read(info, gpr::RCX) & alu::mask(adsz)
+    } else {
+        1
+    };
+
+    // XXX: pending interrupts are not checked between iterations
+    while count != 0 {
+        let si = gpr::read(info, gpr::RSI) & alu::mask(adsz);
+        let di = gpr::read(info, gpr::RDI) & alu::mask(adsz);
+
+        let value = if load {
+            match mem_read(info, src_seg, SegAccess::Read, si, size) {
+                Ok(v) => v,
+                Err(rc) => return rc,
+            }
+        } else {
+            gpr::read(info, gpr::RAX) & alu::mask(size)
+        };
+
+        match mem_write(info, seg::ES, SegAccess::Write, di, size, value) {
+            VMMStatus::Done => (),
+            rc @ _ => return rc,
+        }
+
+        if load {
+            reg_update(info, gpr::RSI, adsz, si.wrapping_add(step));
+        }
+
+        reg_update(info, gpr::RDI, adsz, di.wrapping_add(step));
+        count -= 1;
+
+        if rep {
+            reg_update(info, gpr::RCX, adsz, count);
+        }
+    }
+
+    VMMStatus::Done
+}
+
+fn execute(info: &mut InformationData, insn: &Instruction) -> VMMStatus {
+    match insn.mnemonic {
+        Mnemonic::Mov    => mov(info, insn),
+        Mnemonic::Movzx  => mov_extend(info, insn, false),
+        Mnemonic::Movsx  |
+        Mnemonic::Movsxd => mov_extend(info, insn, true),
+
+        Mnemonic::Add | Mnemonic::Sub  | Mnemonic::Cmp | Mnemonic::Test |
+        Mnemonic::And | Mnemonic::Or   | Mnemonic::Xor => arith(info, insn),
+
+        Mnemonic::Push => push(info, insn),
+        Mnemonic::Pop  => pop(info, insn),
+
+        Mnemonic::Lgdt |
+        Mnemonic::Lidt => load_dtr(info, insn),
+
+        Mnemonic::Movs |
+        Mnemonic::Stos => string(info, insn),
+
+        _ => {
+            log!("emulate: unsupported instruction {:?}\n", insn.mnemonic);
+            VMMStatus::Fail
+        },
+    }
+}
+
+fn set_rip(info: &mut InformationData, size: usize, value: u64) {
+    let old = info.vm.vmcs.guest.rip.as_ref().as_u64();
+    let new = (old & !alu::mask(size)) | (value & alu::mask(size));
+    info.vm.vmcs.guest.rip.as_mut().update_u64(new);
+}
+
+// Emulate instruction at CS:RIP
+pub fn emulate(info: &mut InformationData) -> VMMStatus {
+    let insn = match disasm::disassemble(info) {
+        Ok(insn) => insn,
+        Err(rc) => return rc,
+    };
+
+    #[cfg(feature = "debug_emulate")]
+    log!("emulate {:?}\n", insn);
+
+    match execute(info, &insn) {
+        VMMStatus::Done => (),
+        rc @ _ => return rc,
+    }
+
+    let ipsz = CPUState::addr_size(info) / 8;
+    let rip  = info.vm.vmcs.guest.rip.as_ref().as_u64();
+    set_rip(info, ipsz, rip.wrapping_add(insn.len as u64));
+    VMMStatus::DoneLetRip
+}
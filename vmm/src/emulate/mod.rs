@@ -1,12 +1,18 @@
 use vmx::exit::VMMStatus;
 use share::info::InformationData;
+use share::vmx::vmcs::access::Access;
+use share::utils::RawValue;
 use cpumode::{CPUMode, CPUState};
 
 pub mod rmode;
+pub mod bios;
+pub mod alu;
+pub mod insn;
 
 pub fn soft_int(info: &mut InformationData, vector: u8) -> VMMStatus {
-    // int3/into area 1 byte long and raise SoftExcp not SoftInt
-    interrupt(info, vector, 2)
+    // int n is 2 bytes long, int3/into 1 byte (SoftExcp)
+    let isz = info.vm.vmcs.exit.insn_len.as_ref().as_u32() as u16;
+    interrupt(info, vector, isz)
 }
 
 pub fn hard_int(info: &mut InformationData, vector: u8) -> VMMStatus {
@@ -20,3 +26,12 @@ fn interrupt(info: &mut InformationData, vector: u8, isz: u16) -> VMMStatus {
         VMMStatus::Fail
     }
 }
+
+// Faulting instruction not related to an event delivery
+pub fn instruction(info: &mut InformationData) -> VMMStatus {
+    if CPUState::mode(info, CPUMode::real) {
+        rmode::instruction(info)
+    } else {
+        VMMStatus::Fail
+    }
+}
@@ -1,33 +1,71 @@
 use vm;
+use vm::seg::{self, SegAccess};
+use emulate::bios;
 use vmx::exit::VMMStatus;
 use share::vmx::regs::EventType;
 use share::vmx::vmcs::access::Access;
 use share::info::InformationData;
 use share::rmode;
+use share::exceptions as excp;
 use share::utils;
 use share::utils::{RawValue, Raw64, ArithmeticMod16};
 use core::mem;
 use core::slice;
 
+// Opcodes
+const OPSZ_PREFIX: u8 = 0x66;
+const OP_INT3:     u8 = 0xcc;
+const OP_INT:      u8 = 0xcd;
+const OP_INTO:     u8 = 0xce;
+const OP_IRET:     u8 = 0xcf;
+
+// IRETD in real mode keeps VM, VIF and VIP
+const IRETD_KEEP_MSK: u64 = 1<<17 | 1<<19 | 1<<20;
+
 #[repr(C, packed)]
 struct FarPointer {
     offset:   u32,
     selector: u16,
 }
 
+// Real mode stack accesses wrap SP at 16 bits
 fn push(info: &mut InformationData, value: u16) -> VMMStatus {
-
-    info.vm.vmcs.guest.rsp.as_mut().sub_mod16(2);
-
-    let ss   = info.vm.vmcs.guest.ss.base.as_ref().as_u64();
-    let addr = info.vm.vmcs.guest.rsp.as_ref().as_u64() + ss;
+    let mut sp = Raw64(info.vm.vmcs.guest.rsp.as_ref().as_u64());
+    sp.sub_mod16(2);
 
     let src = unsafe {
         let ptr = &value as *const _ as *const u8;
         slice::from_raw_parts(ptr, 2)
     };
 
-    vm::mem::write(info, addr, src)
+    match vm::mem::write_seg(info, seg::SS, SegAccess::Stack, sp.as_u16() as u64, src) {
+        VMMStatus::Done => (),
+        rc @ _ => return rc,
+    }
+
+    info.vm.vmcs.guest.rsp.as_mut().update_u64(sp.as_u64());
+    VMMStatus::Done
+}
+
+fn pop(info: &mut InformationData, size: usize) -> Result<u32, VMMStatus> {
+    let mut sp = Raw64(info.vm.vmcs.guest.rsp.as_ref().as_u64());
+    let mut value: u32 = 0;
+
+    {
+        let dst = unsafe {
+            let ptr = &mut value as *mut _ as *mut u8;
+            slice::from_raw_parts_mut(ptr, size)
+        };
+
+        match vm::mem::read_seg(info, seg::SS, SegAccess::Stack, sp.as_u16() as u64, dst) {
+            VMMStatus::Done => (),
+            rc @ _ => return Err(rc),
+        }
+    }
+
+    sp.add_mod16(size as u16);
+    info.vm.vmcs.guest.rsp.as_mut().update_u64(sp.as_u64());
+    Ok(value)
 }
 
 fn far_jump(info: &mut InformationData, target: &FarPointer) -> VMMStatus {
@@ -78,18 +116,27 @@ pub fn interrupt(info: &mut InformationData, vector: u8, isz: u16) -> VMMStatus
     log!("rmode int {:#x}\n", vector);
 
     if vector == rmode::BIOS_MISC_INTERRUPT {
-        log!("rmode int 0x15 not implemented !\n");
-        return VMMStatus::Fail
+        match bios::misc(info) {
+            VMMStatus::Done => {
+                info.vm.vmcs.guest.rip.as_mut().add_mod16(isz);
+                return VMMStatus::DoneLetRip
+            },
+            VMMStatus::Native => (),
+            rc @ _ => return rc,
+        }
     }
 
+    // IVT limit is reduced to trap vectors above BIOS_MISC_INTERRUPT
     let mut entry = rmode::IVTEntry {..Default::default()};
-    let addr = (vector as usize * mem::size_of::<rmode::IVTEntry>()) as u64;
+    let base = info.vm.vmcs.guest.idtr.base.as_ref().as_u64();
+    let addr = base + (vector as usize * mem::size_of::<rmode::IVTEntry>()) as u64;
 
     match vm::mem::read(info, addr, entry.as_mut_u8()) {
         VMMStatus::Done => (),
         rc @ _ => return rc,
     }
 
+    let sp    = info.vm.vmcs.guest.rsp.as_ref().as_u64();
     let flags = info.vm.vmcs.guest.rflags.as_ref().as_u16();
 
     match push(info, flags) {
@@ -97,8 +144,98 @@ pub fn interrupt(info: &mut InformationData, vector: u8, isz: u16) -> VMMStatus
         rc @ _ => return rc,
     }
 
-    int_clear_flags(info);
+    let fptr = FarPointer { offset: entry.ip as u32, selector: entry.cs };
+
+    match far_call(info, &fptr, isz) {
+        VMMStatus::DoneLetRip => {
+            int_clear_flags(info);
+            VMMStatus::DoneLetRip
+        },
+        rc @ _ => {
+            // do not partially update the stack
+            info.vm.vmcs.guest.rsp.as_mut().update_u64(sp);
+            rc
+        },
+    }
+}
+
+// IRET: pop IP, CS and FLAGS (operand size 2 or 4)
+fn iret(info: &mut InformationData, size: usize) -> VMMStatus {
+    let sp = info.vm.vmcs.guest.rsp.as_ref().as_u64();
+    let mut frame = [0u32;3];
+
+    for n in 0..3 {
+        match pop(info, size) {
+            Ok(v) => frame[n] = v,
+            Err(rc) => {
+                // do not partially update the stack
+                info.vm.vmcs.guest.rsp.as_mut().update_u64(sp);
+                return rc
+            },
+        }
+    }
 
-    let fptr  = FarPointer { offset: entry.ip as u32, selector: entry.cs };
-    far_call(info, &fptr, isz)
+    let old   = info.vm.vmcs.guest.rflags.as_ref().as_u64();
+    let flags = if size == 2 {
+        (old & !0xffff) | frame[2] as u64
+    } else {
+        (old & IRETD_KEEP_MSK) | (frame[2] as u64 & !IRETD_KEEP_MSK)
+    };
+
+    info.vm.vmcs.guest.rflags.as_mut().update_u64(flags);
+
+    #[cfg(feature = "debug_rmode")]
+    log!("iret to {:#x}:{:#x} flags {:#x}\n", frame[1] as u16, frame[0], flags);
+
+    let fptr = FarPointer { offset: frame[0], selector: frame[1] as u16 };
+    far_jump(info, &fptr)
+}
+
+fn fetch(info: &mut InformationData, offset: u16) -> Result<u8, VMMStatus> {
+    let mut ip = Raw64(info.vm.vmcs.guest.rip.as_ref().as_u64());
+    ip.add_mod16(offset);
+
+    let mut byte = [0u8;1];
+    match vm::mem::read_seg(info, seg::CS, SegAccess::Exec, ip.as_u16() as u64, &mut byte) {
+        VMMStatus::Done => Ok(byte[0]),
+        rc @ _ => Err(rc),
+    }
+}
+
+// Emulate interrupt related instruction at CS:IP
+pub fn instruction(info: &mut InformationData) -> VMMStatus {
+    let (opsz, pfx) = match fetch(info, 0) {
+        Ok(OPSZ_PREFIX) => (4, 1),
+        Ok(_) => (2, 0),
+        Err(rc) => return rc,
+    };
+
+    let opcode = match fetch(info, pfx) {
+        Ok(b) => b,
+        Err(rc) => return rc,
+    };
+
+    #[cfg(feature = "debug_rmode")]
+    log!("rmode insn {:#x}\n", opcode);
+
+    match opcode {
+        OP_IRET => iret(info, opsz),
+        OP_INT3 => interrupt(info, excp::BP as u8, pfx+1),
+        OP_INTO => {
+            if info.vm.vmcs.guest.rflags.as_ref().of() {
+                interrupt(info, excp::OF as u8, pfx+1)
+            } else {
+                info.vm.vmcs.guest.rip.as_mut().add_mod16(pfx+1);
+                VMMStatus::DoneLetRip
+            }
+        },
+        OP_INT  => match fetch(info, pfx+1) {
+            Ok(vector) => interrupt(info, vector, pfx+2),
+            Err(rc) => rc,
+        },
+        _ => {
+            log!("rmode insn {:#x} not supported\n", opcode);
+            VMMStatus::Fail
+        },
+    }
 }
@@ -0,0 +1,313 @@
+// VM event injection through VM-entry interruption information
+//
+// Exceptions raised while the CPU was delivering another event
+// (IDT-vectoring information valid) are merged as the CPU would
+// do, escalating to #DF or triple fault.
+//
+// External interrupts and NMIs are queued until the guest can
+// accept them, interrupt/NMI-window exiting tells us when.
+
+use vm;
+use step;
+use vmx::exit::VMMStatus;
+use cpumode::CPUState;
+use share::info::InformationData;
+use share::exceptions::{self as excp, Exception, ExceptionClass};
+use share::vmx::ACTIVITY_STATE;
+use share::vmx::regs::{EventType, ExitInfoInterrupt};
+use share::vmx::vmcs::access::Access;
+use share::utils::RawValue;
+use core::convert::TryFrom;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Escalation {
+    Deliver,      // second exception is delivered serially
+    DoubleFault,
+    TripleFault,
+}
+
+fn class(vector: u8) -> ExceptionClass {
+    match Exception::try_from(vector) {
+        Ok(e) => e.class(),
+        Err(_) => ExceptionClass::Benign,
+    }
+}
+
+// Exception second raised while delivering first
+pub fn escalate(first: u8, second: u8) -> Escalation {
+    if first == excp::DF as u8 {
+        return if class(second) == ExceptionClass::Benign {
+            Escalation::Deliver
+        } else {
+            Escalation::TripleFault
+        }
+    }
+
+    match (class(first), class(second)) {
+        (ExceptionClass::Contributory, ExceptionClass::Contributory) |
+        (ExceptionClass::PageFault,    ExceptionClass::Contributory) |
+        (ExceptionClass::PageFault,    ExceptionClass::PageFault) => Escalation::DoubleFault,
+        _ => Escalation::Deliver,
+    }
+}
+
+// Real mode does not push error codes (VM-entry would fail)
+pub fn has_code(info: &mut InformationData, vector: u8) -> bool {
+    if !CPUState::init(info).is_prot() {
+        return false
+    }
+
+    match Exception::try_from(vector) {
+        Ok(e) => e.has_code(),
+        Err(_) => false,
+    }
+}
+
+pub fn event(kind: EventType, vector: u8, code: bool) -> ExitInfoInterrupt {
+    let mut event = ExitInfoInterrupt::default();
+
+    event.set_vector(vector);
+    event.set_kind(kind as u8);
+    event.set_v_err(code);
+    event.set_v(true);
+    event
+}
+
+fn is_soft(kind: u8) -> bool {
+    match EventType::try_from(kind) {
+        Ok(EventType::SoftInt)  |
+        Ok(EventType::PSExcp)   |
+        Ok(EventType::SoftExcp) => true,
+        _ => false,
+    }
+}
+
+// Software events need the instruction length to compute the
+// return address pushed by the CPU
+fn inject(info: &mut InformationData, event: ExitInfoInterrupt, code: u32, len: u32) {
+    #[cfg(feature = "debug_inject")]
+    log!("inject {:?} code {:#x} len {}\n", event, code, len);
+
+    if event.v_err() {
+        info.vm.vmcs.ctrl.entry.int_err_code.as_mut().update_u64(code as u64);
+    }
+
+    if is_soft(event.kind()) {
+        info.vm.vmcs.ctrl.entry.insn_len.as_mut().update_u64(len as u64);
+    }
+
+    *info.vm.vmcs.ctrl.entry.int_info.as_mut() = event;
+}
+
+// Hardware exception being delivered when the vm-exit occured
+fn vectoring_exception(info: &mut InformationData) -> Option<u8> {
+    let idt = *info.vm.vmcs.exit.idt_info.as_ref();
+
+    match EventType::try_from(idt.kind()) {
+        Ok(EventType::HardExcp) if idt.v() => Some(idt.vector()),
+        _ => None,
+    }
+}
+
+// Inject an hardware exception, the guest resumes at the faulting
+// instruction so we do not touch rip
+pub fn exception(info: &mut InformationData, vector: u32, code: Option<u32>) -> VMMStatus {
+    let mut vector = vector as u8;
+    let mut code   = code;
+
+    if let Some(first) = vectoring_exception(info) {
+        match escalate(first, vector) {
+            Escalation::Deliver => (),
+            Escalation::DoubleFault => {
+                vector = excp::DF as u8;
+                code   = Some(0);
+            },
+            Escalation::TripleFault => {
+                log!("guest triple fault (#{} while delivering #{})\n", vector, first);
+                return vm::reset::shutdown(info)
+            },
+        }
+    }
+
+    let err = code.is_some() && has_code(info, vector);
+    inject(info, event(EventType::HardExcp, vector, err), code.unwrap_or(0), 0);
+    VMMStatus::DoneLetRip
+}
+
+// External interrupt
+pub fn interrupt(info: &mut InformationData, vector: u8) -> VMMStatus {
+    inject(info, event(EventType::HardInt, vector, false), 0, 0);
+    VMMStatus::DoneLetRip
+}
+
+pub fn nmi(info: &mut InformationData) -> VMMStatus {
+    inject(info, event(EventType::NMI, excp::NMI as u8, false), 0, 0);
+    VMMStatus::DoneLetRip
+}
+
+// INT n, INT3/INTO and INT1 (kind gives which one)
+pub fn software(info: &mut InformationData, kind: EventType, vector: u8, len: u32) -> VMMStatus {
+    inject(info, event(kind, vector, false), 0, len);
+    VMMStatus::DoneLetRip
+}
+
+// Event delivery interrupted by a vm-exit we resolved transparently
+// (ie. EPT violation on the IDT or the stack) must be delivered again
+pub fn reinject(info: &mut InformationData) -> bool {
+    let idt = *info.vm.vmcs.exit.idt_info.as_ref();
+
+    if !idt.v() {
+        return false
+    }
+
+    let code = info.vm.vmcs.exit.idt_err_code.as_ref().as_u32();
+    let len  = info.vm.vmcs.exit.insn_len.as_ref().as_u32();
+
+    // bit 12 (NMI unblocking) is undefined here and reserved on entry
+    let ev = event(EventType::try_from(idt.kind()).unwrap_or(EventType::Other),
+                   idt.vector(), idt.v_err());
+
+    #[cfg(feature = "debug_inject")]
+    log!("re-inject idt event {:?}\n", idt);
+
+    inject(info, ev, code, len);
+    true
+}
+
+// Shutdown and wait-for-SIPI guests only leave on reset
+fn parked(info: &mut InformationData) -> bool {
+    let state = info.vm.vmcs.guest.activity.as_ref().as_u32();
+    state == ACTIVITY_STATE::Shutdown as u32 || state == ACTIVITY_STATE::Sipi as u32
+}
+
+// Entry slot already used or guest interrupts blocked
+fn int_blocked(info: &mut InformationData) -> bool {
+    if info.vm.vmcs.ctrl.entry.int_info.as_ref().v() {
+        return true
+    }
+
+    let state = *info.vm.vmcs.guest.interrupt.as_ref();
+    !info.vm.vmcs.guest.rflags.as_ref().it() || state.sti() || state.mov_ss()
+}
+
+fn nmi_blocked(info: &mut InformationData) -> bool {
+    if info.vm.vmcs.ctrl.entry.int_info.as_ref().v() {
+        return true
+    }
+
+    let state = *info.vm.vmcs.guest.interrupt.as_ref();
+    state.nmi() || state.sti() || state.mov_ss()
+}
+
+// Deliver what the guest accepts now and ask for a vm-exit
+// when it will accept the remaining events
+pub fn pending(info: &mut InformationData) {
+    let mut iwe = false;
+    let mut nwe = false;
+
+    if parked(info) {
+        return
+    }
+
+    // delivered once stepping is over
+    if step::quiet(info) {
+        return
+    }
+
+    if info.vm.cpu.events.nmi {
+        if nmi_blocked(info) {
+            nwe = true;
+        } else {
+            info.vm.cpu.events.nmi = false;
+            nmi(info);
+        }
+    }
+
+    if let Some(vector) = info.vm.cpu.events.peek() {
+        if !int_blocked(info) {
+            info.vm.cpu.events.pop();
+            interrupt(info, vector);
+        }
+    }
+
+    if !info.vm.cpu.events.is_empty() {
+        iwe = true;
+    }
+
+    // NMI-window exiting needs virtual NMIs
+    if nwe && !info.vm.vmcs.ctrl.exec.pin.as_ref().vnmi() {
+        nwe = false;
+        iwe = true;
+    }
+
+    let proc1 = *info.vm.vmcs.ctrl.exec.proc1.as_ref();
+    if proc1.iwe() != iwe || proc1.nwe() != nwe {
+        let proc1 = info.vm.vmcs.ctrl.exec.proc1.as_mut();
+        proc1.set_iwe(iwe);
+        proc1.set_nwe(nwe);
+    }
+}
+
+pub fn queue_interrupt(info: &mut InformationData, vector: u8) -> VMMStatus {
+    #[cfg(feature = "debug_inject")]
+    log!("queue interrupt {:#x}\n", vector);
+
+    if !info.vm.cpu.events.push(vector) {
+        log!("event queue full, interrupt {:#x} lost\n", vector);
+    }
+
+    pending(info);
+    VMMStatus::DoneLetRip
+}
+
+pub fn queue_nmi(info: &mut InformationData) -> VMMStatus {
+    #[cfg(feature = "debug_inject")]
+    log!("queue nmi\n");
+
+    info.vm.cpu.events.nmi = true;
+    pending(info);
+    VMMStatus::DoneLetRip
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(pairs: &[(u32, u32)], expected: Escalation) {
+        for &(first, second) in pairs {
+            assert_eq!(escalate(first as u8, second as u8), expected,
+                       "#{} then #{}", first, second);
+        }
+    }
+
+    #[test]
+    fn benign() {
+        // either one benign, interrupts included
+        check(&[(excp::DB, excp::GP), (excp::NMI, excp::PF), (excp::BP, excp::TS),
+                (excp::GP, excp::UD), (excp::PF, excp::DB), (excp::PF, excp::MC),
+                (32, excp::GP), (excp::GP, 32)], Escalation::Deliver);
+    }
+
+    #[test]
+    fn contributory() {
+        check(&[(excp::DE, excp::GP), (excp::TS, excp::NP), (excp::SS, excp::GP),
+                (excp::GP, excp::GP)], Escalation::DoubleFault);
+
+        // the page fault handler may run
+        check(&[(excp::GP, excp::PF), (excp::DE, excp::PF)], Escalation::Deliver);
+    }
+
+    #[test]
+    fn page_fault() {
+        check(&[(excp::PF, excp::PF), (excp::PF, excp::GP), (excp::PF, excp::SS),
+                (20, excp::PF)], Escalation::DoubleFault);
+    }
+
+    #[test]
+    fn double_fault() {
+        check(&[(excp::DF, excp::DB), (excp::DF, excp::NMI), (excp::DF, excp::UD)],
+              Escalation::Deliver);
+        check(&[(excp::DF, excp::GP), (excp::DF, excp::NP), (excp::DF, excp::PF),
+                (excp::DF, 20)], Escalation::TripleFault);
+    }
+}
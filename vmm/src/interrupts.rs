@@ -1,6 +1,8 @@
 // Interrupts handling
 
 use share::gpr::GPR64Context;
+use share::info::info_data;
+use share::exceptions as excp;
 
 #[repr(C, packed)]
 #[derive(Copy, Clone)]
@@ -45,7 +47,23 @@ bitfield!{
     pub rmode,set_rmode:17;
 }
 
+// Only expected while the VMM halts on behalf of the guest:
+// interrupts and NMIs are queued for it
 #[no_mangle]
 pub extern "C" fn intr_hdlr(ctx: &InterruptContext) {
+    let info = info_data();
+
+    if ctx.nr == excp::NMI as u64 {
+        info.vm.cpu.events.nmi = true;
+        return
+    }
+
+    if ctx.nr >= 32 {
+        if !info.vm.cpu.events.push(ctx.nr as u8) {
+            log!("event queue full, interrupt {:#x} lost\n", ctx.nr);
+        }
+        return
+    }
+
     panic!("VMM exception !\n{:#?}", ctx);
 }
@@ -1,5 +1,6 @@
 #![feature(lang_items, const_fn, asm, unique, try_from)]
-#![no_std]
+// host unit tests run with std
+#![cfg_attr(not(test), no_std)]
 
 use core::fmt;
 
@@ -9,12 +10,19 @@ extern crate bitfield;
 #[macro_use]
 extern crate share;
 
+#[cfg(test)]
+extern crate udis86;
+
 mod vmx;
 mod interrupts;
 mod disasm;
 mod emulate;
 mod cpumode;
 mod vm;
+mod inject;
+mod step;
+mod debug;
+mod dev;
 
 // no explicit rust usage, so prevent LD gc-section
 pub use vmx::exit::vmexit_handler;
@@ -23,6 +31,7 @@ pub use interrupts::intr_hdlr;
 
 use share::log;
 
+#[cfg(not(test))]
 #[lang = "panic_fmt"]
 #[no_mangle]
 pub extern fn panic_fmt(args: fmt::Arguments, file: &'static str, line: u32) -> ! {
@@ -31,5 +40,6 @@ pub extern fn panic_fmt(args: fmt::Arguments, file: &'static str, line: u32) ->
     loop{}
 }
 
+#[cfg(not(test))]
 #[lang = "eh_personality"]
 extern fn eh_personality() {}
@@ -0,0 +1,94 @@
+// Guest single-stepping through the monitor trap flag
+//
+// A MTF vm-exit occurs at the next instruction boundary. The
+// debugger steps the guest and gets it back stopped, EPT tracking
+// steps over the faulting instruction with relaxed permissions and
+// protects the page back on the MTF exit.
+
+use vmx::exit::VMMStatus;
+use inject;
+use debug;
+use share::info::InformationData;
+use share::vmx::vmcs::access::Access;
+use share::vmx::ept;
+use vmx::exit::ept::set_pvl;
+use share::utils::RawValue;
+
+// Arm the monitor trap flag while someone steps
+fn update(info: &mut InformationData) {
+    let active = info.vm.cpu.step.active();
+
+    if info.vm.vmcs.ctrl.exec.proc1.as_ref().mtf() != active {
+        info.vm.vmcs.ctrl.exec.proc1.as_mut().set_mtf(active);
+    }
+}
+
+// Events stay queued while stepping quietly
+pub fn quiet(info: &mut InformationData) -> bool {
+    let step = &info.vm.cpu.step;
+    !step.relaxed().is_empty() || (step.user && step.quiet)
+}
+
+// Debugger single-step of the next guest instruction
+pub fn start(info: &mut InformationData, quiet: bool) {
+    info.vm.cpu.step.user  = true;
+    info.vm.cpu.step.quiet = quiet;
+    update(info);
+}
+
+pub fn stop(info: &mut InformationData) {
+    info.vm.cpu.step.user  = false;
+    info.vm.cpu.step.quiet = false;
+    update(info);
+    inject::pending(info);
+}
+
+// Execute the instruction accessing gpa with default permissions
+pub fn over(info: &mut InformationData, gpa: u64) -> VMMStatus {
+    if !info.vm.cpu.step.relax(gpa) {
+        log!("too many pages relaxed for EPT step-over at {:#x}\n", gpa);
+        return VMMStatus::Fail
+    }
+
+    set_pvl(gpa, ept::attr_pvl_dft());
+    update(info);
+    VMMStatus::DoneLetRip
+}
+
+fn restore(info: &mut InformationData) {
+    while let Some(gpa) = info.vm.cpu.step.unrelax() {
+        // region may have been removed meanwhile
+        if let Some(pvl) = info.vm.ept.pvl(gpa) {
+            set_pvl(gpa, pvl);
+        }
+    }
+}
+
+pub fn handler(info: &mut InformationData) -> VMMStatus {
+    if !info.vm.cpu.step.active() {
+        log!("unexpected MTF exit\n");
+        return VMMStatus::Fail
+    }
+
+    restore(info);
+    debug::brk::rearm(info);
+
+    if info.vm.cpu.step.user {
+        #[cfg(feature = "debug_step")]
+        log!("single-step rip {:#x}\n", info.vm.vmcs.guest.rip.as_ref().as_u64());
+
+        info.vm.cpu.step.user = false;
+    }
+
+    // address space stop filter hit on the previous instruction
+    let space = info.vm.spaces.stop.take().is_some();
+    debug::gdb::stepped(info, space);
+
+    update(info);
+
+    if !quiet(info) {
+        inject::pending(info);
+    }
+
+    VMMStatus::DoneLetRip
+}
@@ -0,0 +1,54 @@
+// VM general purpose registers access by instruction encoding index
+//
+// RSP is not part of the saved context, it lives in the VMCS.
+
+use share::info::InformationData;
+use share::vmx::vmcs::access::Access;
+use share::utils::{RawValue, Raw64};
+use share::gpr::GPR64Context;
+
+pub const RAX: u8 =  0;
+pub const RCX: u8 =  1;
+pub const RDX: u8 =  2;
+pub const RBX: u8 =  3;
+pub const RSP: u8 =  4;
+pub const RBP: u8 =  5;
+pub const RSI: u8 =  6;
+pub const RDI: u8 =  7;
+
+fn saved(gpr: &mut GPR64Context, idx: u8) -> &mut Raw64 {
+    match idx & 0xf {
+        0  => &mut gpr.rax,
+        1  => &mut gpr.rcx,
+        2  => &mut gpr.rdx,
+        3  => &mut gpr.rbx,
+        5  => &mut gpr.rbp,
+        6  => &mut gpr.rsi,
+        7  => &mut gpr.rdi,
+        8  => &mut gpr.r8,
+        9  => &mut gpr.r9,
+        10 => &mut gpr.r10,
+        11 => &mut gpr.r11,
+        12 => &mut gpr.r12,
+        13 => &mut gpr.r13,
+        14 => &mut gpr.r14,
+        15 => &mut gpr.r15,
+        _  => panic!("gpr {} not in saved context", idx),
+    }
+}
+
+pub fn read(info: &mut InformationData, idx: u8) -> u64 {
+    if idx == RSP {
+        info.vm.vmcs.guest.rsp.as_ref().as_u64()
+    } else {
+        saved(info.vm.cpu.gpr, idx).as_u64()
+    }
+}
+
+pub fn write(info: &mut InformationData, idx: u8, value: u64) {
+    if idx == RSP {
+        info.vm.vmcs.guest.rsp.as_mut().update_u64(value)
+    } else {
+        saved(info.vm.cpu.gpr, idx).update_u64(value)
+    }
+}
@@ -1,14 +1,22 @@
 // VM memory access
 
+use inject;
+use dev::mmio;
+use vm::seg::{self, SegAccess};
 use vmx::exit::VMMStatus;
 use share::info::InformationData;
 use share::vmx::vmcs::access::Access as VMCSAccess;
 use share::utils::RawValue;
+use share::utils;
+use share::exceptions as excp;
+use share::paging::walk::{PhysicalMemory, PagingMode, WalkCtx, WalkAccess, WalkError};
 use cpumode::{CPUMode, CPUState};
 use share::cr;
+use share::vmx::ept::EPTRegionKind;
+use share::paging::utils::{pg_align_next, PG_4K_SHIFT};
 use core::slice;
 
-struct Access<'a> {    
+struct Access<'a> {
     cr3:   u64,
     src:   &'a[u8],
     dst:   &'a mut[u8],
@@ -17,28 +25,367 @@ struct Access<'a> {
 
 fn access_system(info: &mut InformationData, access: &mut Access) -> VMMStatus {
     if access.write {
-        if info.hwmm.area.touch(access.dst) { return VMMStatus::Fail }
+        if info.hwmm.area.touch(access.dst) { return VMMStatus::Secret }
     } else {
-        if info.hwmm.area.touch(access.src) { return VMMStatus::Fail }
+        if info.hwmm.area.touch(access.src) { return VMMStatus::Secret }
     }
 
     access.dst.copy_from_slice(access.src);
     VMMStatus::Done
 }
 
+// Device registers are accessed with the operand size, up to 8 bytes
+fn access_mmio(info: &mut InformationData, access: &mut Access) -> VMMStatus {
+    let (gpa, len) = guest_range(access);
+
+    let mut done = 0;
+    while done < len {
+        let sz = match len - done {
+            n if n >= 8 => 8,
+            n if n >= 4 => 4,
+            n if n >= 2 => 2,
+            _ => 1,
+        };
+
+        let addr = gpa + done as u64;
+        let mut value = 0u64;
+
+        let rc = if access.write {
+            for i in 0..sz {
+                value |= (access.src[done+i] as u64) << (i*8);
+            }
+            mmio::write(info, addr, sz, value)
+        } else {
+            let rc = mmio::read(info, addr, sz, &mut value);
+            for i in 0..sz {
+                access.dst[done+i] = (value >> (i*8)) as u8;
+            }
+            rc
+        };
+
+        match rc {
+            VMMStatus::Done => (),
+            rc @ _ => return rc,
+        }
+
+        done += sz;
+    }
+
+    VMMStatus::Done
+}
+
+// Translate guest physical address through EPT, tell if it is
+// memory mapped i/o
+fn nested(info: &mut InformationData, gpa: u64) -> Result<(u64, usize, bool), VMMStatus> {
+    if gpa >= info.hwmm.area.start && gpa < info.hwmm.area.end {
+        #[cfg(feature = "debug_vm_access_fault")]
+        log!("access to vmm area {:#x}\n", gpa);
+        return Err(VMMStatus::Secret)
+    }
+
+    let tr = match info.vm.pg.translate(gpa) {
+        Some(tr) => tr,
+        None => {
+            #[cfg(feature = "debug_vm_access_fault")]
+            log!("unmapped guest physical {:#x}\n", gpa);
+            return Err(VMMStatus::Unmapped)
+        },
+    };
+
+    let region = match info.vm.ept.find(gpa) {
+        Some(region) => region.kind == EPTRegionKind::MMIO,
+        None => false,
+    };
+
+    let io = region || gpa >= info.hwmm.ram || mmio::lookup(gpa).is_some();
+
+    #[cfg(feature = "debug_vm_access_fault")]
+    {
+        if io {
+            log!("mm i/o access {:#x}\n", gpa);
+        }
+    }
+
+    // regions and devices are page granular
+    let left = (pg_align_next(PG_4K_SHIFT, gpa) - gpa) as usize;
+    Ok((tr.paddr, utils::min(left, tr.remaining()), io))
+}
+
 fn access_physical(info: &mut InformationData, access: &mut Access) -> VMMStatus {
-    // XXX: nested translation, out of ram, mm i/o, ...
-    access_system(info, access)
+    let (gpa, len) = guest_range(access);
+
+    // translate every page first so that we never partially copy
+    let mut done = 0;
+    while done < len {
+        match nested(info, gpa.wrapping_add(done as u64)) {
+            Ok((_, left, _)) => done += left,
+            Err(rc) => return rc,
+        }
+    }
+
+    let mut done = 0;
+    while done < len {
+        let addr = gpa.wrapping_add(done as u64);
+        let (hpa, left, io) = match nested(info, addr) {
+            Ok(tr) => tr,
+            Err(rc) => return rc,
+        };
+
+        let sz = utils::min(left, len - done);
+
+        let rc = if io && access.write {
+            let mut chunk = Access {
+                cr3:   access.cr3,
+                src:   &access.src[done..done+sz],
+                dst:   unsafe { slice::from_raw_parts_mut(addr as *mut u8, sz) },
+                write: true,
+            };
+            access_mmio(info, &mut chunk)
+        } else if io {
+            let mut chunk = Access {
+                cr3:   access.cr3,
+                src:   unsafe { slice::from_raw_parts(addr as *const u8, sz) },
+                dst:   &mut access.dst[done..done+sz],
+                write: false,
+            };
+            access_mmio(info, &mut chunk)
+        } else if access.write {
+            let mut chunk = Access {
+                cr3:   access.cr3,
+                src:   &access.src[done..done+sz],
+                dst:   unsafe { slice::from_raw_parts_mut(hpa as *mut u8, sz) },
+                write: true,
+            };
+            access_system(info, &mut chunk)
+        } else {
+            let mut chunk = Access {
+                cr3:   access.cr3,
+                src:   unsafe { slice::from_raw_parts(hpa as *const u8, sz) },
+                dst:   &mut access.dst[done..done+sz],
+                write: false,
+            };
+            access_system(info, &mut chunk)
+        };
+
+        match rc {
+            VMMStatus::Done => (),
+            rc @ _ => return rc,
+        }
+
+        done += sz;
+    }
+
+    VMMStatus::Done
+}
+
+// Guest physical memory seen by the page walker
+struct GuestPhysical<'a> {
+    info: &'a mut InformationData,
+}
+
+impl<'a> GuestPhysical<'a> {
+    fn copy(&mut self, addr: u64, buf: &mut [u8], write: bool) -> bool {
+        let cr3 = self.info.vm.vmcs.guest.cr3.as_ref().as_u64();
+
+        let guest = unsafe {
+            slice::from_raw_parts_mut(addr as *mut u8, buf.len())
+        };
+
+        let mut access = if write {
+            Access { cr3: cr3, src: buf, dst: guest, write: true }
+        } else {
+            Access { cr3: cr3, src: guest, dst: buf, write: false }
+        };
+
+        match access_physical(self.info, &mut access) {
+            VMMStatus::Done => true,
+            _ => false,
+        }
+    }
+}
+
+impl<'a> PhysicalMemory for GuestPhysical<'a> {
+    fn read_u32(&mut self, addr: u64) -> Option<u32> {
+        let mut value: u32 = 0;
+        let buf = unsafe { slice::from_raw_parts_mut(&mut value as *mut _ as *mut u8, 4) };
+        if self.copy(addr, buf, false) { Some(value) } else { None }
+    }
+
+    fn read_u64(&mut self, addr: u64) -> Option<u64> {
+        let mut value: u64 = 0;
+        let buf = unsafe { slice::from_raw_parts_mut(&mut value as *mut _ as *mut u8, 8) };
+        if self.copy(addr, buf, false) { Some(value) } else { None }
+    }
+
+    fn write_u32(&mut self, addr: u64, value: u32) -> bool {
+        let mut value = value;
+        let buf = unsafe { slice::from_raw_parts_mut(&mut value as *mut _ as *mut u8, 4) };
+        self.copy(addr, buf, true)
+    }
+
+    fn write_u64(&mut self, addr: u64, value: u64) -> bool {
+        let mut value = value;
+        let buf = unsafe { slice::from_raw_parts_mut(&mut value as *mut _ as *mut u8, 8) };
+        self.copy(addr, buf, true)
+    }
+}
+
+fn walk_ctx(info: &mut InformationData, cpu: &CPUState) -> WalkCtx {
+    let mode = if cpu.is_paging64() {
+        PagingMode::Long4
+    } else if cpu.is_paging36() {
+        PagingMode::Pae
+    } else {
+        PagingMode::Legacy32
+    };
+
+    let guest = &mut info.vm.vmcs.guest;
+
+    WalkCtx {
+        mode:  mode,
+        cr3:   guest.cr3.as_ref().as_u64(),
+        pdpte: [guest.pdpe_0.as_ref().as_u64(), guest.pdpe_1.as_ref().as_u64(),
+                guest.pdpe_2.as_ref().as_u64(), guest.pdpe_3.as_ref().as_u64()],
+        wp:    guest.cr0.as_ref().wp(),
+        pse:   guest.cr4.as_ref().pse(),
+        nxe:   guest.ia32_efer.as_ref().nx_e(),
+    }
+}
+
+fn page_fault(info: &mut InformationData, vaddr: u64, code: u32) -> VMMStatus {
+    #[cfg(feature = "debug_vm_access_fault")]
+    log!("#PF {:#x} code {:#x}\n", vaddr, code);
+
+    info.vm.vmcs.guest.cr2.as_mut().update_u64(vaddr);
+    inject::exception(info, excp::PF, Some(code));
+    VMMStatus::Fault
+}
+
+// Translate linear address or inject #PF, accessed/dirty
+// bits are set on update only
+fn translate(info: &mut InformationData, ctx: &WalkCtx, vaddr: u64,
+             wacc: &WalkAccess, update: bool) -> Result<(u64, usize), VMMStatus> {
+    let walk = {
+        let mut mem = GuestPhysical { info: &mut *info };
+        if update {
+            ctx.walk(&mut mem, vaddr, wacc)
+        } else {
+            ctx.probe(&mut mem, vaddr, wacc)
+        }
+    };
+
+    match walk {
+        Ok(tr) => Ok((tr.paddr, tr.remaining())),
+        Err(WalkError::Fault(code)) => Err(page_fault(info, vaddr, code)),
+        Err(WalkError::Memory(addr)) => {
+            log!("page walk failed at {:#x} for {:#x}\n", addr, vaddr);
+            Err(VMMStatus::Fail)
+        },
+    }
+}
+
+// Guest linear to physical, without fault nor page table update
+pub fn physical(info: &mut InformationData, vaddr: u64) -> Option<(u64, usize)> {
+    let cpu = CPUState::init(info);
+
+    if cpu.is_real() || cpu.is_v8086() || !cpu.is_paged() {
+        return Some((vaddr, (pg_align_next(PG_4K_SHIFT, vaddr) - vaddr) as usize))
+    }
+
+    let ctx  = walk_ctx(info, &cpu);
+    let wacc = WalkAccess::default();
+    let mut mem = GuestPhysical { info: &mut *info };
+
+    ctx.probe(&mut mem, vaddr, &wacc).ok().map(|tr| (tr.paddr, tr.remaining()))
+}
+
+fn walk_access(info: &mut InformationData, access: &Access) -> WalkAccess {
+    WalkAccess {
+        write: access.write,
+        user:  info.vm.vmcs.guest.ss.attr.as_ref().dpl() == 3,
+        fetch: false,
+    }
+}
+
+// Guest side of the access (source when reading, destination when writing)
+fn guest_range(access: &Access) -> (u64, usize) {
+    if access.write {
+        (access.dst.as_ptr() as u64, access.dst.len())
+    } else {
+        (access.src.as_ptr() as u64, access.src.len())
+    }
 }
 
+// Walk every page of the access so that we never partially copy
+// nor leave accessed/dirty bits behind a fault
 fn validate_virtual(info: &mut InformationData, access: &mut Access) -> VMMStatus {
-    VMMStatus::Fail
+    let cpu  = CPUState::init(info);
+    let ctx  = walk_ctx(info, &cpu);
+    let wacc = walk_access(info, access);
+    let (laddr, len) = guest_range(access);
+
+    let mut done = 0;
+    while done < len {
+        let vaddr = laddr.wrapping_add(done as u64);
+        match translate(info, &ctx, vaddr, &wacc, false) {
+            Ok((_, left)) => done += left,
+            Err(rc) => return rc,
+        }
+    }
+
+    VMMStatus::Done
 }
 
 fn access_virtual(info: &mut InformationData, access: &mut Access) -> VMMStatus {
-    log!("VM virtual mem access not implemented\n");
-    // rebuild slice after page walk !
-    VMMStatus::Fail
+    match validate_virtual(info, access) {
+        VMMStatus::Done => (),
+        rc @ _ => return rc,
+    }
+
+    let cpu  = CPUState::init(info);
+    let ctx  = walk_ctx(info, &cpu);
+    let wacc = walk_access(info, access);
+    let cr3  = access.cr3;
+    let (laddr, len) = guest_range(access);
+
+    // rebuild slices page by page
+    let mut done = 0;
+    while done < len {
+        let vaddr = laddr.wrapping_add(done as u64);
+        let (paddr, left) = match translate(info, &ctx, vaddr, &wacc, true) {
+            Ok(tr) => tr,
+            Err(rc) => return rc,
+        };
+
+        let sz = utils::min(left, len - done);
+
+        let rc = if access.write {
+            let mut chunk = Access {
+                cr3:   cr3,
+                src:   &access.src[done..done+sz],
+                dst:   unsafe { slice::from_raw_parts_mut(paddr as *mut u8, sz) },
+                write: true,
+            };
+            access_physical(info, &mut chunk)
+        } else {
+            let mut chunk = Access {
+                cr3:   cr3,
+                src:   unsafe { slice::from_raw_parts(paddr as *const u8, sz) },
+                dst:   &mut access.dst[done..done+sz],
+                write: false,
+            };
+            access_physical(info, &mut chunk)
+        };
+
+        match rc {
+            VMMStatus::Done => (),
+            rc @ _ => return rc,
+        }
+
+        done += sz;
+    }
+
+    VMMStatus::Done
 }
 
 fn access_linear(info: &mut InformationData, access: &mut Access) -> VMMStatus {
@@ -48,7 +395,7 @@ fn access_linear(info: &mut InformationData, access: &mut Access) -> VMMStatus {
         return access_physical(info, access);
     }
 
-    // XXX: segmentation checks
+    // segmentation is checked by *_seg() callers
 
     if cpu.is_paged() {
         access_virtual(info, access)
@@ -76,6 +423,46 @@ pub fn read(info: &mut InformationData, addr: u64, dst: &mut[u8]) -> VMMStatus {
     access_linear(info, &mut access)
 }
 
+// Bypass segmentation and paging (ie. PDPTEs, page walks, ...)
+pub fn read_physical(info: &mut InformationData, addr: u64, dst: &mut[u8]) -> VMMStatus {
+    #[cfg(feature = "debug_vm_access_read")]
+    log!("read {} bytes from VM physical memory from {:#x} to {:#x}\n"
+         ,dst.len(), addr, dst.as_ptr() as u64);
+
+    let src = unsafe {
+        slice::from_raw_parts(addr as *const u8, dst.len())
+    };
+
+    let mut access = Access {
+        cr3:   info.vm.vmcs.guest.cr3.as_ref().as_u64(),
+        src:   src,
+        dst:   dst,
+        write: false
+    };
+
+    access_physical(info, &mut access)
+}
+
+// Bypass segmentation and paging (ie. PDPTEs, page walks, ...)
+pub fn write_physical(info: &mut InformationData, addr: u64, src: &[u8]) -> VMMStatus {
+    #[cfg(feature = "debug_vm_access_write")]
+    log!("write {} bytes to VM physical memory from {:#x} to {:#x}\n"
+         ,src.len(), src.as_ptr() as u64, addr);
+
+    let dst = unsafe {
+        slice::from_raw_parts_mut(addr as *mut u8, src.len())
+    };
+
+    let mut access = Access {
+        cr3:   info.vm.vmcs.guest.cr3.as_ref().as_u64(),
+        src:   src,
+        dst:   dst,
+        write: true
+    };
+
+    access_physical(info, &mut access)
+}
+
 pub fn write(info: &mut InformationData, addr: u64, src: &[u8]) -> VMMStatus {
     #[cfg(feature = "debug_vm_access_write")]
     log!("write {} bytes to VM memory from {:#x} to {:#x}\n"
@@ -94,3 +481,66 @@ pub fn write(info: &mut InformationData, addr: u64, src: &[u8]) -> VMMStatus {
 
     access_linear(info, &mut access)
 }
+
+// Debugger accesses to guest linear memory through current paging:
+// no fault is injected nor accessed/dirty bit set
+fn debug_chunk(info: &mut InformationData, addr: u64, done: usize, len: usize) -> Option<(u64, usize)> {
+    physical(info, addr.wrapping_add(done as u64)).map(|(paddr, left)| (paddr, utils::min(left, len - done)))
+}
+
+pub fn read_debug(info: &mut InformationData, addr: u64, dst: &mut[u8]) -> VMMStatus {
+    let mut done = 0;
+
+    while done < dst.len() {
+        let (paddr, sz) = match debug_chunk(info, addr, done, dst.len()) {
+            Some(chunk) => chunk,
+            None => return VMMStatus::Fail,
+        };
+
+        match read_physical(info, paddr, &mut dst[done..done+sz]) {
+            VMMStatus::Done => (),
+            rc @ _ => return rc,
+        }
+
+        done += sz;
+    }
+
+    VMMStatus::Done
+}
+
+pub fn write_debug(info: &mut InformationData, addr: u64, src: &[u8]) -> VMMStatus {
+    let mut done = 0;
+
+    while done < src.len() {
+        let (paddr, sz) = match debug_chunk(info, addr, done, src.len()) {
+            Some(chunk) => chunk,
+            None => return VMMStatus::Fail,
+        };
+
+        match write_physical(info, paddr, &src[done..done+sz]) {
+            VMMStatus::Done => (),
+            rc @ _ => return rc,
+        }
+
+        done += sz;
+    }
+
+    VMMStatus::Done
+}
+
+// Segment relative accesses
+pub fn read_seg(info: &mut InformationData, sreg: u8, access: SegAccess,
+                offset: u64, dst: &mut[u8]) -> VMMStatus {
+    match seg::linear(info, sreg, offset, dst.len(), access) {
+        Ok(addr) => read(info, addr, dst),
+        Err(rc) => rc,
+    }
+}
+
+pub fn write_seg(info: &mut InformationData, sreg: u8, access: SegAccess,
+                 offset: u64, src: &[u8]) -> VMMStatus {
+    match seg::linear(info, sreg, offset, src.len(), access) {
+        Ok(addr) => write(info, addr, src),
+        Err(rc) => rc,
+    }
+}
@@ -1 +1,5 @@
 pub mod mem;
+pub mod seg;
+pub mod gpr;
+pub mod reset;
+pub mod space;
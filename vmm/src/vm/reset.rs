@@ -0,0 +1,231 @@
+// VM reset, shutdown and wait-for-SIPI
+//
+// Reset puts the guest back in the state given at VMM setup and
+// enters either the INT19 boot entry or the architectural reset
+// vector. Pending events are dropped.
+
+use vm::{gpr, mem};
+use dev::a20;
+use debug::hw;
+use vmx::exit::VMMStatus;
+use share::info::InformationData;
+use share::vm::ResetPolicy;
+use share::vmx::ACTIVITY_STATE;
+use share::vmx::regs::*;
+use share::vmx::vmcs::GuestSegDesc;
+use share::vmx::vmcs::access::Access;
+use share::utils::RawValue;
+use share::cpuid;
+use share::dr;
+use share::rmode;
+
+// Architectural power-on state
+const RESET_CS_SEL:  u64 = 0xf000;
+const RESET_CS_BASE: u64 = 0xffff0000;
+const RESET_IP:      u64 = 0xfff0;
+const RESET_CR0:     u64 = 0x60000010; // CD, NW, ET
+
+fn segment(seg: &mut GuestSegDesc, sel: u64, base: u64, attr: u32) {
+    seg.sel.as_mut().update_u64(sel);
+    seg.base.as_mut().update_u64(base);
+    seg.limit.as_mut().update_u64(0xffff);
+    seg.attr.as_mut().update_u64(attr as u64);
+}
+
+fn activity(info: &mut InformationData, state: ACTIVITY_STATE) {
+    info.vm.vmcs.guest.activity.as_mut().update_u64(state as u64);
+}
+
+// Drop pending and injected events
+fn events(info: &mut InformationData) {
+    info.vm.cpu.events.clear();
+    info.vm.vmcs.ctrl.entry.int_info.as_mut().set_v(false);
+
+    let proc1 = info.vm.vmcs.ctrl.exec.proc1.as_mut();
+    proc1.set_iwe(false);
+    proc1.set_nwe(false);
+}
+
+pub fn reset(info: &mut InformationData, power_on: bool) -> VMMStatus {
+    log!("guest reset ({})\n", if power_on {"power-on"} else {"int19"});
+
+    events(info);
+    activity(info, ACTIVITY_STATE::Active);
+    info.vm.vmcs.guest.interrupt.as_mut().update_u64(0);
+    info.vm.vmcs.guest.pending_dbg.as_mut().update_u64(0);
+
+    for idx in 0..16 {
+        gpr::write(info, idx, 0);
+    }
+
+    // processor signature
+    let sig = cpuid::cpuid(cpuid::CPUID_FEATURES, 0).eax;
+    gpr::write(info, gpr::RDX, sig as u64);
+
+    {
+        let guest = &mut info.vm.vmcs.guest;
+
+        guest.ia32_efer.as_mut().update_u64(0);
+        guest.cr0.as_mut().update_u64(0);
+        guest.cr2.as_mut().update_u64(0);
+        guest.cr3.as_mut().update_u64(0);
+        guest.cr4.as_mut().update_u64(0);
+
+        guest.pdpe_0.as_mut().update_u64(0);
+        guest.pdpe_1.as_mut().update_u64(0);
+        guest.pdpe_2.as_mut().update_u64(0);
+        guest.pdpe_3.as_mut().update_u64(0);
+
+        guest.ia32_sysenter_cs.as_mut().update_u64(0);
+        guest.ia32_sysenter_esp.as_mut().update_u64(0);
+        guest.ia32_sysenter_eip.as_mut().update_u64(0);
+
+        segment(&mut guest.ds, 0, 0, SEG_ATTR_DATA_16_R3);
+        segment(&mut guest.es, 0, 0, SEG_ATTR_DATA_16_R3);
+        segment(&mut guest.fs, 0, 0, SEG_ATTR_DATA_16_R3);
+        segment(&mut guest.gs, 0, 0, SEG_ATTR_DATA_16_R3);
+
+        guest.ldtr.sel.as_mut().update_u64(0);
+        guest.ldtr.base.as_mut().update_u64(0);
+        guest.ldtr.limit.as_mut().update_u64(0xffff);
+        guest.ldtr.attr.as_mut().update_u64(SEG_ATTR_UNUSABLE as u64);
+
+        guest.tr.sel.as_mut().update_u64(0);
+        guest.tr.base.as_mut().update_u64(0);
+        guest.tr.limit.as_mut().update_u64(0xffff);
+        guest.tr.attr.as_mut().update_u64(SEG_ATTR_TSS_32 as u64);
+
+        // IVT limit is kept short, see setup
+        let limit = rmode::ivt_limit(rmode::BIOS_MISC_INTERRUPT) as u64;
+        guest.idtr.base.as_mut().update_u64(0);
+        guest.idtr.limit.as_mut().update_u64(limit);
+        guest.gdtr.base.as_mut().update_u64(0);
+        guest.gdtr.limit.as_mut().update_u64(0xffff);
+
+        guest.rflags.as_mut().update_u64(0);
+    }
+
+    // VMM hardware breakpoints survive
+    hw::write_dr(info, 6, dr::DR6_DFT);
+    hw::write_dr(info, 7, dr::DR7_DFT);
+
+    info.vm.vmcs.ctrl.entry.entry.as_mut().set_ia32e(false);
+    info.vm.vmcs.ctrl.exec.cr4_read_shadow.as_mut().update_u64(0);
+
+    a20::set(info, true);
+
+    if power_on {
+        info.vm.vmcs.ctrl.exec.cr0_read_shadow.as_mut().update_u64(RESET_CR0);
+        segment(&mut info.vm.vmcs.guest.cs, RESET_CS_SEL, RESET_CS_BASE, SEG_ATTR_CODE_16_R0_CO);
+        segment(&mut info.vm.vmcs.guest.ss, 0, 0, SEG_ATTR_DATA_16_R0);
+        info.vm.vmcs.guest.rsp.as_mut().update_u64(0);
+        info.vm.vmcs.guest.rip.as_mut().update_u64(RESET_IP);
+        return VMMStatus::DoneLetRip
+    }
+
+    info.vm.vmcs.ctrl.exec.cr0_read_shadow.as_mut().update_u64(0);
+    info.vm.vmcs.guest.rflags.as_mut().set_it(true);
+    segment(&mut info.vm.vmcs.guest.cs, 0, 0, SEG_ATTR_CODE_16_R0_CO);
+    segment(&mut info.vm.vmcs.guest.ss, rmode::BASE_SS, rmode::BASE_SS*16, SEG_ATTR_DATA_16_R0);
+    info.vm.vmcs.guest.rsp.as_mut().update_u64(rmode::BASE_SP);
+    info.vm.vmcs.guest.rip.as_mut().update_u64(rmode::BASE_IP);
+
+    // the guest may have overwritten the boot entry
+    let int19 = [rmode::INT19 as u8, (rmode::INT19>>8) as u8];
+    match mem::write(info, rmode::BASE_IP, &int19) {
+        VMMStatus::Done => VMMStatus::DoneLetRip,
+        rc @ _ => rc,
+    }
+}
+
+pub fn dump(info: &mut InformationData) {
+    let reason = info.vm.vmcs.exit.reason.as_ref().basic();
+    let qual   = info.vm.vmcs.exit.qualification.as_ref().as_u64();
+    let idt    = info.vm.vmcs.exit.idt_info.as_ref().as_u64();
+
+    let guest  = &mut info.vm.vmcs.guest;
+
+    log!("
+- guest state
+exit reason {} qualification {:#x} idt info {:#x}
+rip {:#x} rsp {:#x} rflags {:#x}
+cr0 {:#x} cr3 {:#x} cr4 {:#x} efer {:#x}
+cs {:#x} base {:#x} limit {:#x} attr {:#x}
+ss {:#x} base {:#x} limit {:#x} attr {:#x}
+gdtr {:#x}:{:#x} idtr {:#x}:{:#x}
+activity {:#x} interruptibility {:#x}
+"
+         ,reason, qual, idt
+         ,guest.rip.as_ref().as_u64()
+         ,guest.rsp.as_ref().as_u64()
+         ,guest.rflags.as_ref().as_u64()
+         ,guest.cr0.as_ref().as_u64()
+         ,guest.cr3.as_ref().as_u64()
+         ,guest.cr4.as_ref().as_u64()
+         ,guest.ia32_efer.as_ref().as_u64()
+         ,guest.cs.sel.as_ref().as_u64()
+         ,guest.cs.base.as_ref().as_u64()
+         ,guest.cs.limit.as_ref().as_u64()
+         ,guest.cs.attr.as_ref().as_u64()
+         ,guest.ss.sel.as_ref().as_u64()
+         ,guest.ss.base.as_ref().as_u64()
+         ,guest.ss.limit.as_ref().as_u64()
+         ,guest.ss.attr.as_ref().as_u64()
+         ,guest.gdtr.base.as_ref().as_u64()
+         ,guest.gdtr.limit.as_ref().as_u64()
+         ,guest.idtr.base.as_ref().as_u64()
+         ,guest.idtr.limit.as_ref().as_u64()
+         ,guest.activity.as_ref().as_u64()
+         ,guest.interrupt.as_ref().as_u64());
+}
+
+// Park the guest in shutdown state, only a reset gets it out
+fn halt(info: &mut InformationData) -> VMMStatus {
+    dump(info);
+
+    if !info.vmm.cpu.vmx.misc.sht() {
+        log!("vmx shutdown activity state not supported\n");
+        return VMMStatus::Fail
+    }
+
+    events(info);
+    activity(info, ACTIVITY_STATE::Shutdown);
+    VMMStatus::DoneLetRip
+}
+
+fn wait_sipi(info: &mut InformationData) -> VMMStatus {
+    if !info.vmm.cpu.vmx.misc.ipi() {
+        log!("vmx wait-for-SIPI activity state not supported\n");
+        return halt(info)
+    }
+
+    events(info);
+    activity(info, ACTIVITY_STATE::Sipi);
+    VMMStatus::DoneLetRip
+}
+
+// Triple fault and INIT
+pub fn shutdown(info: &mut InformationData) -> VMMStatus {
+    match info.vm.reset {
+        ResetPolicy::Reboot   => reset(info, false),
+        ResetPolicy::PowerOn  => reset(info, true),
+        ResetPolicy::Halt     => halt(info),
+        ResetPolicy::WaitSipi => wait_sipi(info),
+    }
+}
+
+// Start-up IPI: real mode entry at vector:0000
+pub fn sipi(info: &mut InformationData, vector: u8) -> VMMStatus {
+    let sel = (vector as u64)<<8;
+
+    log!("guest sipi vector {:#x}\n", vector);
+
+    match reset(info, true) {
+        VMMStatus::DoneLetRip => (),
+        rc @ _ => return rc,
+    }
+
+    segment(&mut info.vm.vmcs.guest.cs, sel, sel<<4, SEG_ATTR_CODE_16_R0_CO);
+    info.vm.vmcs.guest.rip.as_mut().update_u64(0);
+    VMMStatus::DoneLetRip
+}
@@ -0,0 +1,150 @@
+// VM segmentation
+//
+// Turn segment relative offsets into linear addresses, enforcing
+// type, limit and canonical checks as the processor would do.
+
+use inject;
+use vmx::exit::VMMStatus;
+use share::info::InformationData;
+use share::vmx::vmcs::GuestSegDesc;
+use share::vmx::vmcs::access::Access;
+use share::utils::RawValue;
+use share::exceptions as excp;
+use cpumode::CPUState;
+
+// Segment registers (VMX instruction information encoding)
+pub const ES: u8 = 0;
+pub const CS: u8 = 1;
+pub const SS: u8 = 2;
+pub const DS: u8 = 3;
+pub const FS: u8 = 4;
+pub const GS: u8 = 5;
+
+// Segment type bits
+const SEG_TYPE_CODE: u8 = 1<<3;
+const SEG_TYPE_EXPD: u8 = 1<<2; // data: expand-down
+const SEG_TYPE_RD:   u8 = 1<<1; // code: readable
+const SEG_TYPE_WR:   u8 = 1<<1; // data: writable
+
+// XXX: no 5 levels paging
+const VADDR_BITS: u32 = 48;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SegAccess {
+    Read,
+    Write,
+    Exec,
+    Stack,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SegFault {
+    pub vector: u32,
+    pub code:   u32,
+}
+
+impl SegFault {
+    fn new(kind: SegAccess) -> SegFault {
+        let vector = if kind == SegAccess::Stack { excp::SS } else { excp::GP };
+        SegFault { vector: vector, code: 0 }
+    }
+}
+
+fn canonical(addr: u64) -> bool {
+    let top = (addr as i64) >> (VADDR_BITS - 1);
+    top == 0 || top == -1
+}
+
+fn type_allowed(kind: u8, access: SegAccess) -> bool {
+    let code = kind & SEG_TYPE_CODE != 0;
+
+    match access {
+        SegAccess::Exec  => code,
+        SegAccess::Read  => !code || kind & SEG_TYPE_RD != 0,
+        SegAccess::Write |
+        SegAccess::Stack => !code && kind & SEG_TYPE_WR != 0,
+    }
+}
+
+fn limit_allowed(desc: &mut GuestSegDesc, first: u64, last: u64) -> bool {
+    let limit = desc.limit.as_ref().as_u64();
+    let attr  = *desc.attr.as_ref();
+    let code  = attr.kind() as u8 & SEG_TYPE_CODE != 0;
+
+    if attr.s() && !code && attr.kind() as u8 & SEG_TYPE_EXPD != 0 {
+        let upper = if attr.d() { 0xffffffff } else { 0xffff };
+        first > limit && last <= upper
+    } else {
+        last <= limit
+    }
+}
+
+// Linear address of [offset, offset+len[ in segment seg
+pub fn check(cpu: &CPUState, seg: u8, desc: &mut GuestSegDesc,
+             offset: u64, len: usize, access: SegAccess) -> Result<u64, SegFault> {
+    let size = if len == 0 { 0 } else { len as u64 - 1 };
+
+    if cpu.is_long64() {
+        let base = if seg == FS || seg == GS {
+            desc.base.as_ref().as_u64()
+        } else {
+            0
+        };
+
+        let first = base.wrapping_add(offset);
+        let last  = first.wrapping_add(size);
+
+        if !canonical(first) || !canonical(last) {
+            return Err(SegFault::new(access))
+        }
+
+        return Ok(first)
+    }
+
+    let first = offset;
+    let last  = offset + size;
+
+    if cpu.is_prot() && !cpu.is_v8086() {
+        let attr = *desc.attr.as_ref();
+
+        if attr.u() || !attr.s() || !type_allowed(attr.kind() as u8, access) {
+            return Err(SegFault::new(access))
+        }
+    }
+
+    if !limit_allowed(desc, first, last) {
+        return Err(SegFault::new(access))
+    }
+
+    Ok(desc.base.as_ref().as_u64().wrapping_add(first) & 0xffffffff)
+}
+
+pub fn desc(info: &mut InformationData, seg: u8) -> &mut GuestSegDesc {
+    let guest = &mut info.vm.vmcs.guest;
+    match seg {
+        ES => &mut guest.es,
+        CS => &mut guest.cs,
+        SS => &mut guest.ss,
+        DS => &mut guest.ds,
+        FS => &mut guest.fs,
+        _  => &mut guest.gs,
+    }
+}
+
+// Linear address or inject #GP/#SS
+pub fn linear(info: &mut InformationData, seg: u8,
+              offset: u64, len: usize, access: SegAccess) -> Result<u64, VMMStatus> {
+    let cpu = CPUState::init(info);
+    let rc  = check(&cpu, seg, desc(info, seg), offset, len, access);
+
+    match rc {
+        Ok(addr) => Ok(addr),
+        Err(fault) => {
+            #[cfg(feature = "debug_vm_access_fault")]
+            log!("segment {} {:?} fault at {:#x} len {}\n", seg, access, offset, len);
+
+            inject::exception(info, fault.vector, Some(fault.code));
+            Err(VMMStatus::Fault)
+        },
+    }
+}
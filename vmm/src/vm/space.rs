@@ -0,0 +1,99 @@
+// Guest address space switches
+//
+// CR3-load exiting reports every switch but to CR3-target values,
+// which the VMM reserves to ignored address spaces.
+
+use step;
+use vmx::exit::VMMStatus;
+use share::info::InformationData;
+use share::space::{Cr3Action, Cr3Filter, CR3_FILTER_MAX};
+use share::vmx::vmcs::access::Access;
+use share::utils::RawValue;
+
+// Guest loaded cr3 (vm-exit on mov to CR3)
+pub fn switch(info: &mut InformationData, cr3: u64) {
+    #[cfg(feature = "debug_cr")]
+    log!("address space switch to {:#x}\n", cr3);
+
+    info.vm.spaces.switch(cr3);
+
+    if let Some(Cr3Action::Stop) = info.vm.spaces.filter(cr3) {
+        log!("stop on address space {:#x}\n", cr3);
+
+        // debugger takes over on the MTF exit of the first
+        // instruction run in the new address space
+        info.vm.spaces.stop = Some(cr3);
+        step::start(info, false);
+    }
+}
+
+// Program CR3-target values from ignore filters
+fn targets(info: &mut InformationData) -> bool {
+    let max = info.vmm.cpu.vmx.misc.cr3() as usize;
+    let mut values = [0u64; CR3_FILTER_MAX];
+    let mut count = 0;
+
+    for f in info.vm.spaces.filters() {
+        if f.action != Cr3Action::Ignore {
+            continue
+        }
+
+        if count >= max {
+            return false
+        }
+
+        values[count] = f.cr3;
+        count += 1;
+    }
+
+    let exec = &mut info.vm.vmcs.ctrl.exec;
+    exec.cr3_target_0.as_mut().update_u64(values[0]);
+    exec.cr3_target_1.as_mut().update_u64(values[1]);
+    exec.cr3_target_2.as_mut().update_u64(values[2]);
+    exec.cr3_target_3.as_mut().update_u64(values[3]);
+    exec.cr3_target_cnt.as_mut().update_u64(count as u64);
+    true
+}
+
+pub fn filter(info: &mut InformationData, cr3: u64, action: Cr3Action) -> VMMStatus {
+    let old = info.vm.spaces.filter(cr3);
+
+    if !info.vm.spaces.add_filter(Cr3Filter { cr3: cr3, action: action }) {
+        log!("no more cr3 filter available for {:#x}\n", cr3);
+        return VMMStatus::Fail
+    }
+
+    if !targets(info) {
+        log!("no more cr3 target value available for {:#x}\n", cr3);
+
+        info.vm.spaces.del_filter(cr3);
+        if let Some(action) = old {
+            info.vm.spaces.add_filter(Cr3Filter { cr3: cr3, action: action });
+        }
+        return VMMStatus::Fail
+    }
+
+    VMMStatus::Done
+}
+
+pub fn unfilter(info: &mut InformationData, cr3: u64) -> VMMStatus {
+    if let Some(f) = info.vm.spaces.del_filter(cr3) {
+        if f.action == Cr3Action::Ignore {
+            targets(info);
+        }
+    }
+
+    VMMStatus::Done
+}
+
+pub fn dump(info: &mut InformationData) {
+    log!("- address spaces\n");
+
+    for s in info.vm.spaces.list() {
+        log!("cr3 {:#x} switches {}\n", s.cr3, s.switches);
+    }
+
+    if info.vm.spaces.untracked != 0 {
+        log!("untracked switches {}\n", info.vm.spaces.untracked);
+    }
+}
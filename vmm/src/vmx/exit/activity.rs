@@ -0,0 +1,63 @@
+use vmx::exit::VMMStatus;
+use share::vm::MwaitPolicy;
+use share::vmx::ACTIVITY_STATE;
+use share::vmx::vmcs::access::Access;
+use share::utils::RawValue;
+use share::info::InformationData;
+use share::exceptions as excp;
+use inject;
+
+// Sleep until an interrupt or a NMI shows up, the VMM
+// interrupt handler queues them for the guest
+fn native_hlt(info: &mut InformationData) {
+    while info.vm.cpu.events.is_empty() && !info.vm.cpu.events.nmi {
+        unsafe { asm!("sti; hlt; cli" ::: "memory" : "volatile"); }
+    }
+}
+
+// The guest sleeps until an interrupt is delivered, unless one
+// is already waiting to be accepted
+pub fn hlt_handler(info: &mut InformationData) -> VMMStatus {
+    let it = info.vm.vmcs.guest.rflags.as_ref().it();
+
+    // HLT completes, so does a STI/MOV SS shadow
+    {
+        let state = info.vm.vmcs.guest.interrupt.as_mut();
+        state.set_sti(false);
+        state.set_mov_ss(false);
+    }
+
+    if it && !info.vm.cpu.events.is_empty() {
+        return VMMStatus::Done
+    }
+
+    if it && info.vm.native_hlt {
+        native_hlt(info);
+        inject::pending(info);
+        return VMMStatus::Done
+    }
+
+    info.vm.vmcs.guest.activity.as_mut().update_u64(ACTIVITY_STATE::Halt as u64);
+    VMMStatus::Done
+}
+
+// MWAIT may always wake up spuriously
+pub fn mwait_handler(info: &mut InformationData) -> VMMStatus {
+    match info.vm.mwait {
+        MwaitPolicy::Ud => inject::exception(info, excp::UD, None),
+        MwaitPolicy::Emulate => {
+            if info.vm.vmcs.guest.rflags.as_ref().it() {
+                hlt_handler(info)
+            } else {
+                VMMStatus::Done
+            }
+        },
+    }
+}
+
+pub fn monitor_handler(info: &mut InformationData) -> VMMStatus {
+    match info.vm.mwait {
+        MwaitPolicy::Ud => inject::exception(info, excp::UD, None),
+        MwaitPolicy::Emulate => VMMStatus::Done,
+    }
+}
@@ -0,0 +1,24 @@
+use vmx::exit::VMMStatus;
+use share::info::InformationData;
+use share::utils::RawValue;
+use share::cpuid;
+
+pub fn handler(info: &mut InformationData) -> VMMStatus {
+    let leaf    = info.vm.cpu.gpr.rax.as_u64() as u32;
+    let subleaf = info.vm.cpu.gpr.rcx.as_u64() as u32;
+
+    let mut res = cpuid::cpuid(leaf, subleaf);
+    info.vm.cpu.cpuid.filter(leaf, subleaf, &mut res);
+
+    #[cfg(feature = "debug_cpuid")]
+    log!("cpuid {:#x}:{:#x} eax {:#x} ebx {:#x} ecx {:#x} edx {:#x}\n",
+         leaf, subleaf, res.eax, res.ebx, res.ecx, res.edx);
+
+    let gpr = &mut info.vm.cpu.gpr;
+    gpr.rax.update_u64(res.eax as u64);
+    gpr.rbx.update_u64(res.ebx as u64);
+    gpr.rcx.update_u64(res.ecx as u64);
+    gpr.rdx.update_u64(res.edx as u64);
+
+    VMMStatus::Done
+}
@@ -0,0 +1,259 @@
+use vm;
+use inject;
+use vmx::exit::VMMStatus;
+use share::info::InformationData;
+use share::vmx::regs::{CRAccessType, ExitQualCR};
+use share::vmx::vmcs::access::Access;
+use share::utils::RawValue;
+use share::exceptions as excp;
+use share::cr;
+use cpumode::CPUState;
+use core::convert::TryFrom;
+use core::slice;
+
+// LMSW only loads PE, MP, EM and TS
+const CR0_LMSW_MSK: u64 = 0xf;
+
+// PDPTE reserved bits in PAE mode
+const PDPTE_RSV_MSK: u64 = 0x1e6;
+
+// Guest view of control registers: owned bits come from read shadows
+fn guest_cr0(info: &mut InformationData) -> cr::Cr0 {
+    let mask   = info.vm.vmcs.ctrl.exec.cr0_mask.as_ref().as_u64();
+    let shadow = info.vm.vmcs.ctrl.exec.cr0_read_shadow.as_ref().as_u64();
+    let real   = info.vm.vmcs.guest.cr0.as_ref().as_u64();
+
+    cr::Cr0((real & !mask) | (shadow & mask))
+}
+
+fn guest_cr4(info: &mut InformationData) -> cr::Cr4 {
+    let mask   = info.vm.vmcs.ctrl.exec.cr4_mask.as_ref().as_u64();
+    let shadow = info.vm.vmcs.ctrl.exec.cr4_read_shadow.as_ref().as_u64();
+    let real   = info.vm.vmcs.guest.cr4.as_ref().as_u64();
+
+    cr::Cr4((real & !mask) | (shadow & mask))
+}
+
+fn tlb_flush(info: &mut InformationData, global: bool) {
+    let vpid = info.vm.pg.asid;
+    info.vm.cpu.tlb_flush(vpid, global);
+}
+
+// PAE paging (not IA-32e) loads PDPTEs on CR0/CR3/CR4 updates
+fn load_pdpte(info: &mut InformationData) -> VMMStatus {
+    let addr = info.vm.vmcs.guest.cr3.as_ref().as_u64() & 0xffffffe0;
+    let mut pdpte = [0u64;4];
+
+    {
+        let dst = unsafe {
+            slice::from_raw_parts_mut(pdpte.as_mut_ptr() as *mut u8, 32)
+        };
+
+        match vm::mem::read_physical(info, addr, dst) {
+            VMMStatus::Done => (),
+            VMMStatus::Unmapped | VMMStatus::Secret => {
+                return inject::exception(info, excp::GP, Some(0))
+            },
+            rc @ _ => return rc,
+        }
+    }
+
+    for e in pdpte.iter() {
+        if *e & 1 != 0 && *e & PDPTE_RSV_MSK != 0 {
+            return inject::exception(info, excp::GP, Some(0))
+        }
+    }
+
+    info.vm.vmcs.guest.pdpe_0.as_mut().update_u64(pdpte[0]);
+    info.vm.vmcs.guest.pdpe_1.as_mut().update_u64(pdpte[1]);
+    info.vm.vmcs.guest.pdpe_2.as_mut().update_u64(pdpte[2]);
+    info.vm.vmcs.guest.pdpe_3.as_mut().update_u64(pdpte[3]);
+
+    VMMStatus::Done
+}
+
+fn long_mode(info: &mut InformationData, enable: bool) {
+    #[cfg(feature = "debug_cr")]
+    log!("long mode {}\n", if enable {"activated"} else {"deactivated"});
+
+    info.vm.vmcs.guest.ia32_efer.as_mut().set_ia32_a(enable);
+    info.vm.vmcs.ctrl.entry.entry.as_mut().set_ia32e(enable);
+}
+
+fn mov_to_cr0(info: &mut InformationData, value: u64) -> VMMStatus {
+    let old = guest_cr0(info);
+    let new = cr::Cr0(value);
+
+    if value>>32 != 0 || (new.pg() && !new.pe()) || (new.nw() && !new.cd()) {
+        return inject::exception(info, excp::GP, Some(0))
+    }
+
+    let (lme, lma) = {
+        let efer = info.vm.vmcs.guest.ia32_efer.as_ref();
+        (efer.ia32_e(), efer.ia32_a())
+    };
+
+    let pae = guest_cr4(info).pae();
+
+    if !old.pg() && new.pg() {
+        if lme {
+            if !pae {
+                return inject::exception(info, excp::GP, Some(0))
+            }
+            long_mode(info, true);
+        } else if pae {
+            match load_pdpte(info) {
+                VMMStatus::Done => (),
+                rc @ _ => return rc,
+            }
+        }
+    } else if old.pg() && !new.pg() && lma {
+        // only allowed from compatibility mode
+        if CPUState::init(info).is_long64() {
+            return inject::exception(info, excp::GP, Some(0))
+        }
+        long_mode(info, false);
+    }
+
+    #[cfg(feature = "debug_cr")]
+    log!("cr0 {:#x} -> {:#x}\n", old.0, value);
+
+    info.vm.vmcs.ctrl.exec.cr0_read_shadow.as_mut().update_u64(value);
+    info.vm.vmcs.guest.cr0.as_mut().update_u64(value);
+
+    if old.pe() != new.pe() || old.pg() != new.pg() || old.wp() != new.wp() {
+        tlb_flush(info, true);
+    }
+
+    VMMStatus::Done
+}
+
+fn mov_to_cr3(info: &mut InformationData, value: u64) -> VMMStatus {
+    #[cfg(feature = "debug_cr")]
+    log!("cr3 {:#x}\n", value);
+
+    let noflush = cr::Cr3(value).noflush() && guest_cr4(info).pcide();
+    let value   = value & !(1<<63);
+
+    info.vm.vmcs.guest.cr3.as_mut().update_u64(value);
+
+    if CPUState::init(info).is_paging36() {
+        match load_pdpte(info) {
+            VMMStatus::Done => (),
+            rc @ _ => return rc,
+        }
+    }
+
+    if !noflush {
+        tlb_flush(info, false);
+    }
+
+    vm::space::switch(info, value);
+    VMMStatus::Done
+}
+
+fn mov_to_cr4(info: &mut InformationData, value: u64) -> VMMStatus {
+    let old   = guest_cr4(info);
+    let new   = cr::Cr4(value);
+    let fixed = info.vmm.cpu.vmx.fixed.cr4;
+    let lma   = info.vm.vmcs.guest.ia32_efer.as_ref().ia32_a();
+
+    // VMX is hidden to the guest
+    if value & !fixed.allow_1.as_u64() != 0 || new.vmxe() || (lma && !new.pae()) {
+        return inject::exception(info, excp::GP, Some(0))
+    }
+
+    if !lma && !old.pae() && new.pae() && guest_cr0(info).pg() {
+        match load_pdpte(info) {
+            VMMStatus::Done => (),
+            rc @ _ => return rc,
+        }
+    }
+
+    #[cfg(feature = "debug_cr")]
+    log!("cr4 {:#x} -> {:#x}\n", old.0, value);
+
+    info.vm.vmcs.ctrl.exec.cr4_read_shadow.as_mut().update_u64(value);
+    info.vm.vmcs.guest.cr4.as_mut().update_u64(value);
+
+    if old.pae() != new.pae() || old.pse() != new.pse() ||
+        old.pge() != new.pge() || old.pcide() != new.pcide() ||
+        old.smep() != new.smep() {
+        tlb_flush(info, true);
+    }
+
+    VMMStatus::Done
+}
+
+fn mov_to_cr8(info: &mut InformationData, value: u64) -> VMMStatus {
+    if value & !0xf != 0 {
+        return inject::exception(info, excp::GP, Some(0))
+    }
+
+    cr::cr8_write(value);
+    VMMStatus::Done
+}
+
+fn mov_to_cr(info: &mut InformationData, nr: u8, gpr: u8) -> VMMStatus {
+    let mut value = vm::gpr::read(info, gpr);
+
+    if ! CPUState::init(info).is_long64() {
+        value &= 0xffffffff;
+    }
+
+    match nr {
+        0 => mov_to_cr0(info, value),
+        3 => mov_to_cr3(info, value),
+        4 => mov_to_cr4(info, value),
+        8 => mov_to_cr8(info, value),
+        _ => {
+            log!("mov to cr{} not supported\n", nr);
+            VMMStatus::Fail
+        },
+    }
+}
+
+fn mov_from_cr(info: &mut InformationData, nr: u8, gpr: u8) -> VMMStatus {
+    let value = match nr {
+        3 => info.vm.vmcs.guest.cr3.as_ref().as_u64(),
+        8 => cr::cr8_read(),
+        _ => {
+            log!("mov from cr{} not supported\n", nr);
+            return VMMStatus::Fail
+        },
+    };
+
+    vm::gpr::write(info, gpr, value);
+    VMMStatus::Done
+}
+
+fn clts(info: &mut InformationData) -> VMMStatus {
+    let mut value = guest_cr0(info);
+    value.set_ts(false);
+    mov_to_cr0(info, value.0)
+}
+
+// LMSW can set PE but not clear it
+fn lmsw(info: &mut InformationData, src: u16) -> VMMStatus {
+    let old   = guest_cr0(info).0;
+    let value = (old & !CR0_LMSW_MSK) | (src as u64 & CR0_LMSW_MSK) | (old & 1);
+    mov_to_cr0(info, value)
+}
+
+pub fn handler(info: &mut InformationData) -> VMMStatus {
+    let qual = ExitQualCR(info.vm.vmcs.exit.qualification.as_ref().as_u64());
+
+    #[cfg(feature = "debug_cr")]
+    log!("{:?}\n", qual);
+
+    match CRAccessType::try_from(qual.access()) {
+        Ok(CRAccessType::MovToCr)   => mov_to_cr(info, qual.nr(), qual.gpr()),
+        Ok(CRAccessType::MovFromCr) => mov_from_cr(info, qual.nr(), qual.gpr()),
+        Ok(CRAccessType::Clts)      => clts(info),
+        Ok(CRAccessType::Lmsw)      => lmsw(info, qual.lmsw_src()),
+        Err(n) => {
+            log!("invalid cr access {}\n", n);
+            VMMStatus::Fail
+        },
+    }
+}
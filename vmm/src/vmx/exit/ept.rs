@@ -0,0 +1,134 @@
+// EPT violation and misconfiguration
+//
+// Guest physical regions registered in info.vm.ept get reduced
+// permissions. Violations are dispatched to the region handler
+// which tells what to do with the faulting instruction.
+
+use vmx::exit::VMMStatus;
+use emulate;
+use inject;
+use step;
+use debug;
+use dev;
+use share::info::InformationData;
+use share::vmx::regs::ExitQualEPT;
+use share::vmx::vmcs::access::Access;
+use share::vmx::ept::{self, EPTRegion, EPTRegionKind, attr_pvl_dft};
+use share::utils::RawValue;
+use share::paging::utils::*;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum EPTAction {
+    SingleStep, // relax permissions for one instruction
+    Emulate,    // emulate the faulting instruction
+    Restore,    // give default permissions back
+}
+
+pub fn set_pvl(gpa: u64, pvl: u64) {
+    let page = pg_align(PG_4K_SHIFT, gpa);
+    ept::protect(page, page + PG_4KB as u64, pvl);
+}
+
+// Emulated accesses reach the device claiming gpa
+fn mmio(_info: &mut InformationData, _region: &EPTRegion,
+        _qual: &ExitQualEPT, gpa: u64) -> EPTAction {
+    match dev::mmio::lookup(gpa) {
+        Some(_dev) => {
+            #[cfg(feature = "debug_ept")]
+            log!("{} access at {:#x}\n", _dev.name, gpa);
+            EPTAction::Emulate
+        },
+        None => {
+            log!("no device at {:#x}, default permissions restored\n", gpa);
+            EPTAction::Restore
+        },
+    }
+}
+
+fn write_track(info: &mut InformationData, _region: &EPTRegion,
+               _qual: &ExitQualEPT, gpa: u64) -> EPTAction {
+    #[cfg(feature = "debug_ept")]
+    log!("write to {:#x} from rip {:#x}\n"
+         ,gpa, info.vm.vmcs.guest.rip.as_ref().as_u64());
+
+    EPTAction::SingleStep
+}
+
+fn exec_track(info: &mut InformationData, _region: &EPTRegion,
+              _qual: &ExitQualEPT, gpa: u64) -> EPTAction {
+    #[cfg(feature = "debug_ept")]
+    log!("fetch at {:#x} from rip {:#x}\n"
+         ,gpa, info.vm.vmcs.guest.rip.as_ref().as_u64());
+
+    EPTAction::SingleStep
+}
+
+pub fn violation_handler(info: &mut InformationData) -> VMMStatus {
+    let qual = ExitQualEPT(info.vm.vmcs.exit.qualification.as_ref().as_u64());
+    let gpa  = info.vm.vmcs.exit.guest_physical.as_ref().as_u64();
+
+    #[cfg(feature = "debug_ept")]
+    {
+        log!("EPT violation {:#x} {:?}\n", gpa, qual);
+        if qual.gla_valid() {
+            log!("linear {:#x}\n", info.vm.vmcs.exit.guest_linear.as_ref().as_u64());
+        }
+    }
+
+    // XXX: NMI unblocking due to IRET (qual.nmi_unblk())
+
+    let watched = debug::watch::hit(info, &qual, gpa);
+
+    let mut action = match info.vm.ept.find(gpa) {
+        Some(region) => match region.kind {
+            EPTRegionKind::MMIO       => mmio(info, &region, &qual, gpa),
+            EPTRegionKind::WriteTrack => write_track(info, &region, &qual, gpa),
+            EPTRegionKind::ExecTrack  => exec_track(info, &region, &qual, gpa),
+            EPTRegionKind::WriteWatch |
+            EPTRegionKind::AccessWatch => debug::watch::violation(info, &region, &qual, gpa),
+        },
+        // stale permissions of a removed region
+        None => {
+            log!("EPT violation at {:#x} outside any region, default permissions restored\n", gpa);
+            EPTAction::Restore
+        },
+    };
+
+    // watchpoint hit is reported after the access
+    if watched && action == EPTAction::Restore {
+        action = EPTAction::SingleStep;
+    }
+
+    // faulting access belongs to an event delivery, not to an instruction
+    let vectoring = info.vm.vmcs.exit.idt_info.as_ref().v();
+
+    let rc = match action {
+        EPTAction::SingleStep => step::over(info, gpa),
+        EPTAction::Restore    => {
+            set_pvl(gpa, attr_pvl_dft());
+            VMMStatus::DoneLetRip
+        },
+        // the device is native, let the CPU do what we can not emulate
+        EPTAction::Emulate if vectoring => step::over(info, gpa),
+        EPTAction::Emulate    => match emulate::insn::emulate(info) {
+            VMMStatus::Fail => step::over(info, gpa),
+            rc @ _ => rc,
+        },
+    };
+
+    if let VMMStatus::DoneLetRip = rc {
+        if vectoring {
+            inject::reinject(info);
+        }
+    }
+
+    rc
+}
+
+pub fn misconfig_handler(info: &mut InformationData) -> VMMStatus {
+    let gpa = info.vm.vmcs.exit.guest_physical.as_ref().as_u64();
+
+    log!("EPT misconfiguration\n");
+    info.vm.pg.dump(gpa);
+    VMMStatus::Fail
+}
@@ -0,0 +1,23 @@
+use vmx::exit::VMMStatus;
+use share::vmx::vmcs::access::Access;
+use share::info::InformationData;
+use inject;
+
+// Interrupt acknowledged on exit, the exit interrupt
+// information holds its vector
+pub fn interrupt_handler(info: &mut InformationData) -> VMMStatus {
+    let vector = info.vm.vmcs.exit.int_info.as_ref().vector();
+
+    #[cfg(feature = "debug_inject")]
+    log!("external interrupt {:#x}\n", vector);
+
+    // the exit may have interrupted an event delivery
+    inject::reinject(info);
+    inject::queue_interrupt(info, vector)
+}
+
+// Guest is now ready to accept queued events
+pub fn window_handler(info: &mut InformationData) -> VMMStatus {
+    inject::pending(info);
+    VMMStatus::DoneLetRip
+}
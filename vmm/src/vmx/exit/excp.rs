@@ -5,12 +5,15 @@ use share::vmx::vmcs::access::Access;
 use share::utils::RawValue;
 use share::info::InformationData;
 use emulate;
+use inject;
+use debug;
 use cpumode::{CPUMode, CPUState};
 use core;
 use core::convert::TryFrom;
 
-// #GP in real mode must be related to IDT vectoring events
-// because of real mode IVT limit fixed to int15.
+// #GP in real mode is mostly related to IDT vectoring events
+// because of real mode IVT limit fixed to int15. Otherwise
+// the faulting instruction (ie. iret) is emulated.
 fn excp_gp_rmode(info: &mut InformationData) -> VMMStatus {
     let (vector, kind, valid) = {
         let idt_info = info.vm.vmcs.exit.idt_info.as_ref();
@@ -20,11 +23,13 @@ fn excp_gp_rmode(info: &mut InformationData) -> VMMStatus {
     if !valid {
         #[cfg(feature = "debug_excp")]
         log!("rmode #GP not related to IDT event\n");
-        VMMStatus::Fail
+        emulate::instruction(info)
     } else {
         match EventType::try_from(kind) {
             Ok(EventType::SoftInt) => emulate::soft_int(info, vector),
             Ok(EventType::HardInt) => emulate::hard_int(info, vector),
+            Ok(EventType::SoftExcp) |
+            Ok(EventType::PSExcp)  => emulate::soft_int(info, vector),
 
             // XXX: this illustrates idiomatic Rust enum faillible From()
             // could be replaced by "_ =>" placeholder (as in handler() below)
@@ -49,7 +54,16 @@ fn excp_gp(info: &mut InformationData) -> VMMStatus {
 }
 
 pub fn handler(info: &mut InformationData) -> VMMStatus {
-    let vector = info.vm.vmcs.exit.int_info.as_ref().vector();
+    let (vector, kind) = {
+        let int_info = info.vm.vmcs.exit.int_info.as_ref();
+        (int_info.vector(), int_info.kind())
+    };
+
+    // NMI exiting, forward it to the guest
+    if let Ok(EventType::NMI) = EventType::try_from(kind) {
+        inject::reinject(info);
+        return inject::queue_nmi(info)
+    }
 
     match Exception::try_from(vector) {
         Err(n) => {
@@ -62,6 +76,8 @@ pub fn handler(info: &mut InformationData) -> VMMStatus {
             log!("Exception #{:#?}\n", excp);
             match excp {
                 Exception::GeneralProtection => excp_gp(info),
+                Exception::Breakpoint => debug::brk::handler(info),
+                Exception::Debug => debug::hw::handler(info),
                 _ => {log!("-= unhandled =-"); VMMStatus::Fail},
             }
         },
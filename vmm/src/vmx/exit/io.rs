@@ -0,0 +1,138 @@
+use vm;
+use vm::seg::{self, SegAccess};
+use dev;
+use vmx::exit::VMMStatus;
+use share::info::InformationData;
+use share::vmx::regs::{ExitQualIO, ExitInsnInfoIO};
+use share::vmx::vmcs::access::Access;
+use share::utils::RawValue;
+use core::slice;
+
+fn size_mask(size: usize) -> u64 {
+    match size {
+        1 => 0xff,
+        2 => 0xffff,
+        4 => 0xffffffff,
+        _ => !0,
+    }
+}
+
+fn update_masked(old: u64, new: u64, mask: u64) -> u64 {
+    (old & !mask) | (new & mask)
+}
+
+// IN/OUT al/ax/eax
+fn simple(info: &mut InformationData, qual: &ExitQualIO) -> VMMStatus {
+    let port = qual.port();
+    let size = qual.bytes();
+    let msk  = size_mask(size);
+
+    if qual.input() {
+        let mut value = 0;
+        match dev::read(info, port, size, &mut value) {
+            VMMStatus::Done => (),
+            rc @ _ => return rc,
+        }
+
+        // 32 bits operand zero extends
+        let rax = &mut info.vm.cpu.gpr.rax;
+        let old = if size == 4 { 0 } else { rax.as_u64() };
+        rax.update_u64(update_masked(old, value as u64, msk));
+        VMMStatus::Done
+    } else {
+        let value = (info.vm.cpu.gpr.rax.as_u64() & msk) as u32;
+        dev::write(info, port, size, value)
+    }
+}
+
+// INS/OUTS with optional REP prefix
+//
+// Exit io_rcx/io_rsi/io_rdi are only saved on SMM vm-exits, the
+// guest registers hold the current values.
+fn string(info: &mut InformationData, qual: &ExitQualIO) -> VMMStatus {
+    let port  = qual.port();
+    let size  = qual.bytes();
+    let iinfo = ExitInsnInfoIO(info.vm.vmcs.exit.insn_info.as_ref().as_u64() as u32);
+    let amsk  = match iinfo.addr() {
+        0 => size_mask(2),
+        1 => size_mask(4),
+        _ => size_mask(8),
+    };
+
+    let df = info.vm.vmcs.guest.rflags.as_ref().df();
+
+    let mut count = if qual.rep() {
+        info.vm.cpu.gpr.rcx.as_u64() & amsk
+    } else {
+        1
+    };
+
+    while count != 0 {
+        let mut value: u32 = 0;
+
+        let data = unsafe {
+            slice::from_raw_parts_mut(&mut value as *mut _ as *mut u8, size)
+        };
+
+        let ptr = if qual.input() {
+            let rdi = info.vm.cpu.gpr.rdi.as_u64() & amsk;
+
+            match dev::read(info, port, size, &mut value) {
+                VMMStatus::Done => (),
+                rc @ _ => return rc,
+            }
+
+            match vm::mem::write_seg(info, seg::ES, SegAccess::Write, rdi, data) {
+                VMMStatus::Done => (),
+                rc @ _ => return rc,
+            }
+
+            &mut info.vm.cpu.gpr.rdi
+        } else {
+            let rsi = info.vm.cpu.gpr.rsi.as_u64() & amsk;
+
+            match vm::mem::read_seg(info, iinfo.seg(), SegAccess::Read, rsi, data) {
+                VMMStatus::Done => (),
+                rc @ _ => return rc,
+            }
+
+            match dev::write(info, port, size, value) {
+                VMMStatus::Done => (),
+                rc @ _ => return rc,
+            }
+
+            &mut info.vm.cpu.gpr.rsi
+        };
+
+        let old  = ptr.as_u64();
+        let next = if df {
+            old.wrapping_sub(size as u64)
+        } else {
+            old.wrapping_add(size as u64)
+        };
+        ptr.update_u64(update_masked(old, next, amsk));
+
+        count -= 1;
+
+        if qual.rep() {
+            let rcx = &mut info.vm.cpu.gpr.rcx;
+            let old = rcx.as_u64();
+            rcx.update_u64(update_masked(old, count, amsk));
+        }
+    }
+
+    VMMStatus::Done
+}
+
+pub fn handler(info: &mut InformationData) -> VMMStatus {
+    let qual = ExitQualIO(info.vm.vmcs.exit.qualification.as_ref().as_u64());
+
+    #[cfg(feature = "debug_io")]
+    log!("{:?}\n", qual);
+
+    if qual.string() {
+        string(info, &qual)
+    } else {
+        simple(info, &qual)
+    }
+}
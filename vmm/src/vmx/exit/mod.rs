@@ -1,13 +1,25 @@
 // submodules implementing specific vmexit handlers
 mod excp;
 mod reason;
+mod cpuid;
+mod cr;
+mod msr;
+mod io;
+mod event;
+mod reset;
+mod activity;
+pub mod ept;
 
 use vmx::exit::reason::BasicReason;
 use vmx::vmcs::commit::Commit;
 use share::vmx::vmcs::access::Access;
-use share::utils::RawValue;
+use share::utils::{RawValue, ArithmeticMod16};
 use share::info::InformationData;
 use share::info::info_data;
+use cpumode::CPUState;
+use debug;
+use inject;
+use share::exceptions as excp;
 
 #[derive(Debug, Copy, Clone)]
 pub enum VMMStatus {
@@ -19,6 +31,8 @@ pub enum VMMStatus {
     Ignore,
     Internal,
     Partial,
+    Unmapped, // guest physical not mapped in EPT
+    Secret,   // access to VMM area
 }
 
 #[no_mangle]
@@ -26,14 +40,38 @@ pub extern fn vmresume_failure(vmx_err: u32) -> ! {
     panic!("vmresume failed {}", vmx_err);
 }
 
+// Skip the instruction that caused the vm-exit, wrapping rip
+// according to current code segment size
+fn next_insn(info: &mut InformationData) {
+    let len  = info.vm.vmcs.exit.insn_len.as_ref().as_u64();
+    let size = CPUState::addr_size(info);
+    let rip  = info.vm.vmcs.guest.rip.as_mut();
+
+    match size {
+        64 => { let v = rip.as_u64().wrapping_add(len); rip.update_u64(v) },
+        32 => { let v = rip.as_u32().wrapping_add(len as u32); rip.update_u64(v as u64) },
+        _  => rip.add_mod16(len as u16),
+    }
+}
+
 #[no_mangle]
 pub extern fn vmexit_handler() {
     let info  = info_data();
     let basic = info.vm.vmcs.exit.reason.as_ref().basic();
 
-    if let VMMStatus::Fail = BasicReason::resolve(info, basic) {
-        panic!("vm-exit failure !\n{:#?}\n", info.vm.vmcs.exit.reason.as_ref());
+    match BasicReason::resolve(info, basic) {
+        VMMStatus::Fail => {
+            panic!("vm-exit failure !\n{:#?}\n", info.vm.vmcs.exit.reason.as_ref());
+        },
+        // guest controlled address, fail the instruction not the VMM
+        rc @ VMMStatus::Unmapped | rc @ VMMStatus::Secret => {
+            log!("vm-exit memory access failure ({:?}), #GP\n", rc);
+            inject::exception(info, excp::GP, Some(0));
+        },
+        VMMStatus::Done => next_insn(info),
+        _ => (),
     }
 
+    debug::gdb::poll(info);
     info.vm.vmcs.commit();
 }
@@ -0,0 +1,92 @@
+use inject;
+use vmx::exit::VMMStatus;
+use share::info::InformationData;
+use share::vmx::vmcs::access::Access;
+use share::utils::RawValue;
+use share::exceptions as excp;
+use share::msr;
+use share::msr::{MsrError, MsrPolicy};
+
+fn efer_write(info: &mut InformationData, value: u64) -> VMMStatus {
+    let old = info.vm.vmcs.guest.ia32_efer.as_ref().as_u64();
+    let pg  = info.vm.vmcs.guest.cr0.as_ref().pg();
+
+    // LME can not be changed while paging is enabled
+    if value & !msr::IA32_EFER_MSK != 0 ||
+        (pg && (old ^ value) & msr::IA32_EFER_LME != 0) {
+        return inject::exception(info, excp::GP, Some(0))
+    }
+
+    // LMA is read-only, managed on CR0 update
+    let efer = (value & !msr::IA32_EFER_LMA) | (old & msr::IA32_EFER_LMA);
+    info.vm.vmcs.guest.ia32_efer.as_mut().update_u64(efer);
+    VMMStatus::Done
+}
+
+fn pat_write(info: &mut InformationData, value: u64) -> VMMStatus {
+    if ! msr::pat_valid(value) {
+        return inject::exception(info, excp::GP, Some(0))
+    }
+
+    info.vm.vmcs.guest.ia32_pat.as_mut().update_u64(value);
+    VMMStatus::Done
+}
+
+pub fn rdmsr_handler(info: &mut InformationData) -> VMMStatus {
+    let index = info.vm.cpu.gpr.rcx.as_u32();
+
+    let value = match index {
+        msr::IA32_EFER => info.vm.vmcs.guest.ia32_efer.as_ref().as_u64(),
+        msr::IA32_PAT  => info.vm.vmcs.guest.ia32_pat.as_ref().as_u64(),
+        _ => match info.vm.cpu.msr.read(index) {
+            Ok(value) => value,
+            Err(MsrError::Reserved) => return inject::exception(info, excp::GP, Some(0)),
+            Err(MsrError::Unknown)  => match info.vm.cpu.msr.policy {
+                MsrPolicy::Passthrough => msr::rdmsr(index),
+                MsrPolicy::Ignore      => 0,
+                MsrPolicy::Fault       => {
+                    log!("rdmsr unknown msr {:#x}\n", index);
+                    return inject::exception(info, excp::GP, Some(0))
+                },
+            },
+        },
+    };
+
+    #[cfg(feature = "debug_msr")]
+    log!("rdmsr {:#x} = {:#x}\n", index, value);
+
+    let gpr = &mut info.vm.cpu.gpr;
+    gpr.rax.update_u64(value & 0xffffffff);
+    gpr.rdx.update_u64(value >> 32);
+
+    VMMStatus::Done
+}
+
+pub fn wrmsr_handler(info: &mut InformationData) -> VMMStatus {
+    let index = info.vm.cpu.gpr.rcx.as_u32();
+    let value = (info.vm.cpu.gpr.rdx.as_u32() as u64) << 32
+        | info.vm.cpu.gpr.rax.as_u32() as u64;
+
+    #[cfg(feature = "debug_msr")]
+    log!("wrmsr {:#x} = {:#x}\n", index, value);
+
+    match index {
+        msr::IA32_EFER => efer_write(info, value),
+        msr::IA32_PAT  => pat_write(info, value),
+        _ => match info.vm.cpu.msr.write(index, value) {
+            Ok(_) => VMMStatus::Done,
+            Err(MsrError::Reserved) => inject::exception(info, excp::GP, Some(0)),
+            Err(MsrError::Unknown)  => match info.vm.cpu.msr.policy {
+                MsrPolicy::Passthrough => {
+                    unsafe { msr::wrmsr(index, value) };
+                    VMMStatus::Done
+                },
+                MsrPolicy::Ignore => VMMStatus::Done,
+                MsrPolicy::Fault  => {
+                    log!("wrmsr unknown msr {:#x}\n", index);
+                    inject::exception(info, excp::GP, Some(0))
+                },
+            },
+        },
+    }
+}
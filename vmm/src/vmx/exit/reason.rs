@@ -1,4 +1,6 @@
 use vmx;
+use step;
+use debug;
 use vmx::exit::VMMStatus;
 use share::info::InformationData;
 
@@ -160,6 +162,24 @@ impl BasicReason {
                 log!("vm-exit {:?}\n", reason);
                 match reason {
                     ExceptionOrNMI => vmx::exit::excp::handler(info),
+                    ExternalInterrupt => vmx::exit::event::interrupt_handler(info),
+                    TripleFault    => vmx::exit::reset::triple_fault_handler(info),
+                    INIT           => vmx::exit::reset::init_handler(info),
+                    SIPI           => vmx::exit::reset::sipi_handler(info),
+                    InterruptWindows => vmx::exit::event::window_handler(info),
+                    NMIWindow      => vmx::exit::event::window_handler(info),
+                    CPUID          => vmx::exit::cpuid::handler(info),
+                    HLT            => vmx::exit::activity::hlt_handler(info),
+                    MWAIT          => vmx::exit::activity::mwait_handler(info),
+                    MONITOR        => vmx::exit::activity::monitor_handler(info),
+                    CRAccess       => vmx::exit::cr::handler(info),
+                    DRAcess        => debug::hw::access_handler(info),
+                    IO             => vmx::exit::io::handler(info),
+                    RDMSR          => vmx::exit::msr::rdmsr_handler(info),
+                    WRMSR          => vmx::exit::msr::wrmsr_handler(info),
+                    MTF            => step::handler(info),
+                    EPTViolation   => vmx::exit::ept::violation_handler(info),
+                    EPTMisconfig   => vmx::exit::ept::misconfig_handler(info),
                     _ => {log!("-= unhandled =-\n"); VMMStatus::Fail},
                 }
             },
@@ -0,0 +1,21 @@
+use vm;
+use vmx::exit::VMMStatus;
+use share::vmx::vmcs::access::Access;
+use share::utils::RawValue;
+use share::info::InformationData;
+
+pub fn triple_fault_handler(info: &mut InformationData) -> VMMStatus {
+    log!("guest triple fault\n");
+    vm::reset::shutdown(info)
+}
+
+pub fn init_handler(info: &mut InformationData) -> VMMStatus {
+    log!("guest INIT\n");
+    vm::reset::shutdown(info)
+}
+
+// Exit qualification holds the SIPI vector
+pub fn sipi_handler(info: &mut InformationData) -> VMMStatus {
+    let vector = info.vm.vmcs.exit.qualification.as_ref().as_u64() as u8;
+    vm::reset::sipi(info, vector)
+}
@@ -0,0 +1,14 @@
+// VMX instructions of vmx_insn.s for host unit tests, which link
+// them but never run them
+
+#[no_mangle]
+pub extern fn __vmx_vmread(_err: *mut u64, _val: *mut u64, _enc: u64) -> u8 { 0 }
+
+#[no_mangle]
+pub extern fn __vmx_vmwrite(_err: *mut u64, _val: u64, _enc: u64) -> u8 { 0 }
+
+#[no_mangle]
+pub extern fn __vmx_invvpid(_err: *mut u64, _kind: u64, _desc: *const u64) -> u8 { 0 }
+
+#[no_mangle]
+pub extern fn __vmx_invept(_err: *mut u64, _kind: u64, _desc: *const u64) -> u8 { 0 }
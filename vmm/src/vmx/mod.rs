@@ -1,2 +1,4 @@
 pub mod exit;
 pub mod vmcs;
+#[cfg(test)]
+mod host;
@@ -157,7 +157,7 @@ impl Commit for Guest {
             self.ia32_perf.flush();
         }
 
-        // self.ia32_dbgctl.flush();
+        self.ia32_dbgctl.flush();
         // self.ia32_bndcfg.flush();
         self.pdpe_0.flush();
         self.pdpe_1.flush();
@@ -23,6 +23,12 @@
 .globl __vmx_vmread
 .type  __vmx_vmread,"function"
 
+.globl __vmx_invvpid
+.type  __vmx_invvpid,"function"
+
+.globl __vmx_invept
+.type  __vmx_invept,"function"
+
 .globl vmx_vmresume
 .type  vmx_vmresume,"function"
 
@@ -135,6 +141,38 @@ __vmx_vmread:
         vmread  %rdx, (%rsi)
         jmp     vmx_check_error
 
+/*
+** INVVPID
+**
+** params:
+**      RDI = mem64 VMX error code ptr
+**      RSI = invalidation type
+**      RDX = mem128 descriptor ptr
+**
+** returns:
+**      0 on failure
+**      1 on success
+*/
+__vmx_invvpid:
+        invvpid (%rdx), %rsi
+        jmp     vmx_check_error
+
+/*
+** INVEPT
+**
+** params:
+**      RDI = mem64 VMX error code ptr
+**      RSI = invalidation type
+**      RDX = mem128 descriptor ptr
+**
+** returns:
+**      0 on failure
+**      1 on success
+*/
+__vmx_invept:
+        invept  (%rdx), %rsi
+        jmp     vmx_check_error
+
 /*
 ** Failure handling
 */